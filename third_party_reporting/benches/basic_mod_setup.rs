@@ -1,19 +1,20 @@
 use criterion::*;
-use third_party_reporting::lib_basic as basic;
+use third_party_reporting::lib_basic::Basic;
+use third_party_reporting::lib_scheme::*;
 use third_party_reporting::lib_common::*;
 
 
 pub fn basic_mod_setup(c: &mut Criterion) {
-    // One time setup to generate platform needed for mod setup
-    let mut platforms: Vec<basic::Platform> = Vec::new();
+    // One time setup to generate platforms needed for mod setup
+    let mut platforms: Vec<<Basic as ReportingScheme>::Platform> = Vec::with_capacity(MOD_SCALE.len());
     for _i in 0..MOD_SCALE.len() {
-        platforms.push(basic::Platform::new());
+        platforms.push(Basic::setup_platform());
     }
 
     let mut group = c.benchmark_group("basic.setup_mod()");
     for (i, num_moderators) in MOD_SCALE.iter().enumerate() {
         group.bench_with_input(format!("basic.setup_mod() with {} moderators", num_moderators), num_moderators, |b, &num_moderators| {
-            b.iter(|| basic::test_basic_setup_mod(&mut platforms[i], num_moderators))
+            b.iter(|| Basic::setup_mod(&mut platforms[i], num_moderators))
         });
     }
     group.finish();
@@ -1,56 +1,37 @@
 use criterion::*;
-
-use curve25519_dalek::ristretto::CompressedRistretto;
-use aes_gcm::Nonce;
-use generic_array::typenum::U12;
-use third_party_reporting::lib_basic as basic;
+use rand::rngs::OsRng;
+use third_party_reporting::lib_basic::Basic;
+use third_party_reporting::lib_scheme::*;
 use third_party_reporting::lib_common::*;
-
-type Point = CompressedRistretto;
-type Ciphertext = (Point, Point);
-
+use third_party_reporting::lib_context::ExpiryPolicy;
 
 pub fn basic_moderate(c: &mut Criterion) {
     // Setup platforms and moderators
-    let (platforms, moderators, pks) = basic::test_setup();
+    let (mut platforms, moderators, pks) = test_setup::<Basic, _>(&mut OsRng);
 
     // One time setup to generate client needed for message sending
-    let clients = basic::test_basic_init_clients(1);
+    let clients = test_init_clients::<Basic, _>(1, &mut OsRng);
 
     // One time setup to generate messages of various sizes
-    let mut ms: Vec<Vec<String>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-    for msg_size in MSG_SIZE_SCALE.iter() {
-        ms.push(basic::test_basic_init_messages(1, *msg_size));
-    }
+    let ms = test_init_messages_variable(&mut OsRng);
 
     // Send messages
-    let c1c2ad = basic::test_send_variable(&clients, &ms);
+    let c1c2ad = test_send_variable::<Basic, _>(&pks, &clients, &ms, &mut OsRng);
 
     // Process messages
-    let sigma_st = basic::test_process_variable(&moderators, &c1c2ad, &platforms);
+    let sigma_st = test_process_variable::<Basic, _>(&c1c2ad, &mut platforms, &mut OsRng);
 
     // Read messages
-    let mut reports: Vec<Vec<(String, u32, ([u8; 32], Vec<u8>, Vec<u8>, Ciphertext))>> = Vec::new();
-    // reports[i][j] = report on message j to moderator for platform i
-    for i in 0..moderators.len() {
-        let mut tmp: Vec<(String, u32, ([u8; 32], Vec<u8>, Vec<u8>, Ciphertext))> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-        for (j, _msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
-            let rds = basic::test_basic_read(1, &c1c2ad[i][j], &sigma_st[i][j], &clients, &pks[i], false);
-
-            // Generate reports
-            let reports = basic::test_report(1, &rds, false);
-            tmp.push(reports[0].clone());
-        }
-        reports.push(tmp);
-    }
-    
+    let rds = test_read_variable::<Basic>(&c1c2ad, &sigma_st, &clients, &pks);
+
     let mut group = c.benchmark_group("basic.moderate()");
     for (i, num_moderators) in MOD_SCALE.iter().enumerate() {
         for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
             group.bench_with_input(format!("basic.moderate() message of size {} with {} moderators", msg_size, num_moderators), msg_size, |b, &_msg_size| {
-                let (_message, ad, report) = &reports[i][j];
-                let k: usize = usize::try_from(*ad).unwrap();
-                b.iter(|| basic::Moderator::moderate(&moderators[i][k].sk_enc, &moderators[i][k].sk_p, &ms[j][0], report))
+                let (message, moderator_id, rd) = &rds[i][j];
+                let report = Basic::report_gen(message, rd).unwrap();
+                let k = usize::try_from(*moderator_id).unwrap();
+                b.iter(|| Basic::moderate(&moderators[i][k], &ms[j][0], &report, &ExpiryPolicy::default()))
             });
         }
     }
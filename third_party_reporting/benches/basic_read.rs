@@ -1,50 +1,37 @@
 use criterion::*;
-use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
-use aes_gcm::Nonce;
-use generic_array::typenum::U12;
-use third_party_reporting::lib_basic as basic;
+use rand::rngs::OsRng;
+use third_party_reporting::lib_basic::Basic;
+use third_party_reporting::lib_scheme::*;
 use third_party_reporting::lib_common::*;
 
-type Point = CompressedRistretto;
-type Ciphertext = (Point, Point);
-
-
-pub fn read(clients: &Vec<basic::Client>, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, u32)>, pks: &Vec<Point>, sigma_st: &Vec<(Ciphertext, (Vec<u8>, u32))>) {
-    let (c1, c2, _ad) = &c1c2ad[0];
-    let (sigma, st) = &sigma_st[0];
-    basic::Client::read(&clients[0].msg_key, &pks, &c1, &c2, &sigma, &st);
-}
-
-
 pub fn bench_basic_read(c: &mut Criterion) {
     // Setup platforms and moderators
-    let (platforms, moderators, pks) = basic::test_setup();
+    let (mut platforms, _moderators, pks) = test_setup::<Basic, _>(&mut OsRng);
 
     // One time setup to generate client needed for message sending
-    let clients = basic::test_basic_init_clients(1);
+    let clients = test_init_clients::<Basic, _>(1, &mut OsRng);
 
     // One time setup to generate messages of various sizes
-    let mut ms: Vec<Vec<String>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-    for msg_size in MSG_SIZE_SCALE.iter() {
-        ms.push(basic::test_basic_init_messages(1, *msg_size));
-    }
+    let ms = test_init_messages_variable(&mut OsRng);
 
     // Send messages
-    let c1c2ad = basic::test_send_variable(&clients, &ms);
+    let c1c2ad = test_send_variable::<Basic, _>(&pks, &clients, &ms, &mut OsRng);
 
     // Process messages
-    let sigma_st = basic::test_process_variable(&moderators, &c1c2ad, &platforms);
+    let sigma_st = test_process_variable::<Basic, _>(&c1c2ad, &mut platforms, &mut OsRng);
 
     let mut group = c.benchmark_group("basic.read()");
     for (i, num_moderators) in MOD_SCALE.iter().enumerate() {
         for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
             group.bench_with_input(format!("basic.read() message of size {} with {} moderators", msg_size, num_moderators), msg_size, |b, &_msg_size| {
-                b.iter(|| read(&clients, &c1c2ad[i][j], &pks[i], &sigma_st[i][j]))
+                let (sigma, st) = &sigma_st[i][j][0];
+                b.iter(|| Basic::read(&clients[0], &pks[i], &c1c2ad[i][j][0], sigma, st))
             });
         }
     }
     
     group.finish();
 }
+
 criterion_group!(benches, bench_basic_read);
 criterion_main!(benches);
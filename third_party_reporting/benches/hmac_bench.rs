@@ -1,13 +1,15 @@
 use criterion::*;
 use third_party_reporting::lib_common::*;
-use rand::thread_rng;
+use third_party_reporting::lib_domain::*;
+use third_party_reporting::lib_wire::SCHEME_PLAIN;
 use rand::distributions::{Alphanumeric, DistString};
 
 pub fn bench_hmac(c: &mut Criterion) {
-    let mut k = mac_keygen();
+    let k = mac_keygen();
+    let domain = Domain::new(SCHEME_PLAIN, Role::Platform, Usage::PlatformTag);
     let msg = Alphanumeric.sample_string(&mut rand::thread_rng(), 100);
 
-    c.bench_function("hmac-sign", |b| b.iter(|| mac_sign(&k, black_box(&msg.as_bytes().to_vec()))));
+    c.bench_function("hmac-sign", |b| b.iter(|| mac_sign(&domain, &k, black_box(msg.as_bytes()))));
 }
 
 criterion_group!(benches, bench_hmac);
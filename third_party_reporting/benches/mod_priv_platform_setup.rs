@@ -1,9 +1,10 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use third_party_reporting::lib_mod_priv as mod_priv;
+use third_party_reporting::lib_mod_priv::ModPriv;
+use third_party_reporting::lib_scheme::ReportingScheme;
 
 pub fn mod_priv_platform_setup(c: &mut Criterion) {
     let mut group = c.benchmark_group("mod_priv.setup_platform()");
-    group.bench_function("mod-priv.setup_platform()", |b| b.iter(|| mod_priv::test_setup_platform()));
+    group.bench_function("mod-priv.setup_platform()", |b| b.iter(ModPriv::setup_platform));
     group.finish();
 }
 
@@ -1,39 +1,34 @@
 use criterion::*;
-use rand::distributions::Alphanumeric;
-use rand::distributions::DistString;
-use third_party_reporting::lib_mod_priv as mod_priv;
+use rand::rngs::OsRng;
+use third_party_reporting::lib_mod_priv::ModPriv;
+use third_party_reporting::lib_scheme::*;
 use third_party_reporting::lib_common::*;
-use curve25519_dalek::ristretto::CompressedRistretto;
-
-
-type Point = CompressedRistretto;
-
-pub fn process(platform: &mod_priv::Platform, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, Point)>) {
-    let (c1, c2, ad) = &c1c2ad[0];
-    mod_priv::Platform::process(&platform.k_p, &platform.sk_p, &c1, &c2, ad, &(CTX.to_vec()));
-}
-
+use third_party_reporting::lib_context::ReportContext;
 
 pub fn bench_mod_priv_process(c: &mut Criterion) {
     // Setup platforms and moderators
-    let (platforms, moderators, _pks) = mod_priv::test_setup();
+    let (mut platforms, _moderators, pks) = test_setup::<ModPriv, _>(&mut OsRng);
 
     // One time setup to generate client needed for message sending
-    let clients = mod_priv::test_init_clients(1);
+    let clients = test_init_clients::<ModPriv, _>(1, &mut OsRng);
 
     // One time setup to generate messages of various sizes
-    let mut ms: Vec<Vec<String>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-    for msg_size in MSG_SIZE_SCALE.iter() {
-        ms.push(mod_priv::test_init_messages(1, *msg_size));
-    }
+    let ms = test_init_messages_variable(&mut OsRng);
 
-    let c1c2ad = mod_priv::test_send_variable(&moderators, &clients, &ms);
+    // Send messages
+    let c1c2ad = test_send_variable::<ModPriv, _>(&pks, &clients, &ms, &mut OsRng);
 
+    // A fresh conversation per iteration, the replay cache rejects the same
+    // commitment twice in one conversation
+    let mut conversation: u64 = 0;
     let mut group = c.benchmark_group("mod-priv.process()");
-    for (i, num_moderators) in  MOD_SCALE.iter().enumerate() {
+    for (i, num_moderators) in MOD_SCALE.iter().enumerate() {
         for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
             group.bench_with_input(format!("mod-priv.process() with message of size {} with {} moderators", msg_size, num_moderators), msg_size, |b, &_msg_size| {
-                b.iter(|| process(&platforms[i], &c1c2ad[i][j]))
+                b.iter(|| {
+                    conversation += 1;
+                    ModPriv::process(&mut platforms[i], &c1c2ad[i][j][0], &ReportContext { conversation, ..CTX })
+                })
             });
         }
     }
@@ -41,7 +36,6 @@ pub fn bench_mod_priv_process(c: &mut Criterion) {
     group.finish();
 }
 
-
 criterion_group!{
     name=benches;
     config=Criterion::default().significance_level(0.01).sample_size(5000);
@@ -0,0 +1,174 @@
+// dudect-style timing leakage test for moderate
+//
+// A valid report is generated (and checked to be accepted), then moderate is
+// measured on two classes of invalid reports derived from it:
+//   fixed:  the checked value with only its last byte flipped
+//   random: the checked value replaced by random bytes
+// Both are rejected, but a comparison that stops at the first differing byte
+// takes longer on the fixed class. The basic tag is a point decrypted from
+// sigma, so there the fixed class keeps sigma, which decrypts to the expected
+// point, and the random class adds a random point to it. A policy that
+// rejects every report keeps the fixed class from being accepted, it is
+// checked after the comparison. Classes are interleaved at random and Welch's
+// t-test is run on the timings, uncropped and cropped at upper percentiles as
+// in dudect. |t| above 4.5 points to a leak.
+//
+// cargo bench --bench moderate_timing runs the full test and reports leaks,
+// cargo bench --bench moderate_timing -- --fail-on-leak also exits with an
+// error on one. cargo test --benches only runs a short smoke test and prints
+// the statistics.
+
+use std::hint::black_box;
+use std::time::Instant;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
+use third_party_reporting::lib_common::*;
+use third_party_reporting::lib_context::ExpiryPolicy;
+use third_party_reporting::lib_scheme::*;
+use third_party_reporting::lib_plain::{self, Plain};
+use third_party_reporting::lib_basic::{self, Basic};
+use third_party_reporting::lib_mod_priv::{self, ModPriv};
+use third_party_reporting::lib_constant_mod_priv::{self, ConstantModPriv};
+
+const MEASUREMENTS: usize = 100_000;
+const SMOKE_MEASUREMENTS: usize = 2_000;
+const T_THRESHOLD: f64 = 4.5;
+const NUM_CROPS: usize = 10;
+
+// Welch's t-test over the two classes, with running means and variances
+#[derive(Clone, Copy, Default)]
+struct TTest {
+    n: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2]
+}
+
+impl TTest {
+    fn push(&mut self, class: usize, x: f64) {
+        self.n[class] += 1.0;
+        let delta = x - self.mean[class];
+        self.mean[class] += delta / self.n[class];
+        self.m2[class] += delta * (x - self.mean[class]);
+    }
+
+    fn t(&self) -> f64 {
+        if self.n[0] < 2.0 || self.n[1] < 2.0 {
+            return 0.0;
+        }
+        let var_0 = self.m2[0] / (self.n[0] - 1.0);
+        let var_1 = self.m2[1] / (self.n[1] - 1.0);
+
+        (self.mean[0] - self.mean[1]) / (var_0 / self.n[0] + var_1 / self.n[1]).sqrt()
+    }
+}
+
+// Largest |t| over the uncropped timings and the percentile crops
+fn max_t(samples: &[(usize, f64)]) -> f64 {
+    let mut sorted: Vec<f64> = samples.iter().map(|(_, x)| *x).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut thresholds = vec![f64::INFINITY];
+    for i in 0..NUM_CROPS {
+        let p = 1.0 - 0.5f64.powf(10.0 * (i + 1) as f64 / NUM_CROPS as f64);
+        thresholds.push(sorted[((sorted.len() as f64) * p) as usize]);
+    }
+
+    let mut tests = vec![TTest::default(); thresholds.len()];
+    for (class, x) in samples {
+        for (test, threshold) in tests.iter_mut().zip(&thresholds) {
+            if x < threshold {
+                test.push(*class, *x);
+            }
+        }
+    }
+
+    tests.iter().map(|test| test.t().abs()).fold(0.0, f64::max)
+}
+
+// Flip the last byte (fixed class) or randomize every byte (random class)
+fn perturb(bytes: &mut [u8], fixed: bool, rng: &mut StdRng) {
+    if fixed {
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+    } else {
+        rng.fill_bytes(bytes);
+    }
+}
+
+// Keep sigma (fixed class) or add a random point to the tag it encrypts (random class)
+fn shift(sigma: &mut (CompressedRistretto, CompressedRistretto), fixed: bool, rng: &mut StdRng) {
+    if !fixed {
+        let v = sigma.1.decompress().unwrap() + RistrettoPoint::random(rng);
+        sigma.1 = v.compress();
+    }
+}
+
+// Rejects every report, after its tag has been checked
+fn expired() -> u64 {
+    u64::MAX
+}
+
+fn leakage<S: ReportingScheme>(measurements: usize, policy: &ExpiryPolicy, mutate: fn(&mut S::Report, bool, &mut StdRng)) -> f64 {
+    let mut rng = StdRng::from_entropy();
+
+    let mut platform = S::setup_platform_with_rng(&mut rng);
+    let (moderators, pks) = S::setup_mod_with_rng(&mut platform, 1, &mut rng);
+    let client = S::init_client_with_rng(&mut rng);
+
+    let ms = test_init_messages(1, 100, &mut rng);
+    let ct = S::send_with_rng(&client, &ms[0], 0, &pks, &mut rng).unwrap();
+    let (sigma, st) = S::process_with_rng(&mut platform, &ct, &CTX, &mut rng).unwrap();
+    let (message, _moderator_id, rd) = S::read(&client, &pks, &ct, &sigma, &st).unwrap();
+    let report = S::report_gen(&message, &rd).unwrap();
+    assert!(S::moderate(&moderators[0], &message, &report, &ExpiryPolicy::unlimited()).unwrap().is_accept());
+
+    let mut samples: Vec<(usize, f64)> = Vec::with_capacity(measurements);
+    for _i in 0..measurements {
+        let class: usize = rng.gen_range(0..2);
+        let mut invalid = report.clone();
+        mutate(&mut invalid, class == 0, &mut rng);
+
+        let start = Instant::now();
+        let verdict = black_box(S::moderate(&moderators[0], &message, black_box(&invalid), policy));
+        let elapsed = start.elapsed();
+        assert!(!matches!(verdict, Ok(Verdict::Accept(_))));
+
+        samples.push((class, elapsed.as_nanos() as f64));
+    }
+
+    max_t(&samples)
+}
+
+fn main() {
+    let full = std::env::args().any(|arg| arg == "--bench");
+    let fail_on_leak = std::env::args().any(|arg| arg == "--fail-on-leak");
+    let n = if full { MEASUREMENTS } else { SMOKE_MEASUREMENTS };
+    let unlimited = ExpiryPolicy::unlimited();
+    let expiring = ExpiryPolicy::with_clock(0, expired);
+
+    // Pairings dominate the constant scheme, so it gets fewer measurements
+    let targets: Vec<(&str, usize, f64)> = vec![
+        ("plain commitment", n, leakage::<Plain>(n, &unlimited, |r: &mut lib_plain::Report, fixed, rng| perturb(&mut r.c2, fixed, rng))),
+        ("plain tag", n, leakage::<Plain>(n, &unlimited, |r: &mut lib_plain::Report, fixed, rng| perturb(&mut r.sigma, fixed, rng))),
+        ("basic commitment", n, leakage::<Basic>(n, &unlimited, |r: &mut lib_basic::Report, fixed, rng| perturb(&mut r.c2, fixed, rng))),
+        ("basic tag", n, leakage::<Basic>(n, &expiring, |r: &mut lib_basic::Report, fixed, rng| shift(&mut r.sigma, fixed, rng))),
+        ("mod_priv commitment", n, leakage::<ModPriv>(n, &unlimited, |r: &mut lib_mod_priv::Report, fixed, rng| perturb(&mut r.c2, fixed, rng))),
+        ("mod_priv tag", n, leakage::<ModPriv>(n, &unlimited, |r: &mut lib_mod_priv::Report, fixed, rng| perturb(&mut r.sigma, fixed, rng))),
+        ("const_mod_priv commitment", n / 10, leakage::<ConstantModPriv>(n / 10, &unlimited, |r: &mut lib_constant_mod_priv::Report, fixed, rng| perturb(&mut r.c2, fixed, rng)))
+    ];
+
+    let mut leaky = false;
+    for (target, measurements, t) in targets {
+        let verdict = if t > T_THRESHOLD { "possible leak" } else { "no leak detected" };
+        println!("moderate() {}: max |t| = {:.2} over {} measurements ({})", target, t, measurements, verdict);
+        leaky |= t > T_THRESHOLD;
+    }
+
+    if leaky {
+        println!("moderate() may leak timing, see the targets above");
+        if fail_on_leak {
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,34 +1,45 @@
 use criterion::*;
-
-use third_party_reporting::lib_plain as plain;
+use rand::rngs::OsRng;
+use third_party_reporting::lib_plain::{Plain, SentMessage, ProcessState, Report};
+use third_party_reporting::lib_scheme::*;
 use third_party_reporting::lib_common::*;
+use third_party_reporting::lib_context::ExpiryPolicy;
 
 
 pub fn bench_plain_moderate(c: &mut Criterion) {
     // One time setup to generate client needed for message sending
-    let clients = plain::test_init_clients(1);
+    let clients = test_init_clients::<Plain, _>(1, &mut OsRng);
 
     // One time setup to generate messages of various sizes
-    let mut ms: Vec<Vec<String>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-    for msg_size in MSG_SIZE_SCALE.iter() {
-        ms.push(plain::test_init_messages(1, *msg_size));
-    }
+    let ms = test_init_messages_variable(&mut OsRng);
+
+    // Setup Moderator
+    let mut moderator = Plain::setup_platform();
+    let (moderators, pks) = Plain::setup_mod(&mut moderator, 1);
 
     // Send messages
-    let c1c2s = plain::test_send(&clients, &ms, false);
+    let mut c1c2s: Vec<SentMessage> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+    for m in ms.iter() {
+        c1c2s.push(Plain::send(&clients[0], &m[0], 0, &pks).unwrap());
+    }
 
     // Process messages
-    let moderator = plain::Moderator::new();
-    let sigmas = plain::test_process(&moderator, &c1c2s);
+    let mut sigma_st: Vec<(Vec<u8>, ProcessState)> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+    for c1c2 in c1c2s.iter() {
+        sigma_st.push(Plain::process(&mut moderator, c1c2, &CTX).unwrap());
+    }
 
     // Read messages
-    let reports = plain::test_read(&clients, &c1c2s, &sigmas, false);
+    let mut reports: Vec<Report> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+    for (j, (sigma, st)) in sigma_st.iter().enumerate() {
+        let (message, _moderator_id, rd) = Plain::read(&clients[0], &pks, &c1c2s[j], sigma, st).unwrap();
+        reports.push(Plain::report_gen(&message, &rd).unwrap());
+    }
 
     let mut group = c.benchmark_group("plain.moderate()");
     for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
         group.bench_with_input(format!("plain.moderate() message of size {}", msg_size), msg_size, |b, &_msg_size| {
-            let (m, ctx, rd, sigma) = &reports[j].clone();
-            b.iter(|| plain::Moderator::moderate(&moderator.k_m, &m, &ctx, rd.clone(), sigma.to_vec()))
+            b.iter(|| Plain::moderate(&moderators[0], &ms[j][0], &reports[j], &ExpiryPolicy::unlimited()))
         });
     }
     
@@ -1,32 +1,38 @@
 use criterion::*;
-use rand::distributions::Alphanumeric;
-use rand::distributions::DistString;
-use third_party_reporting::lib_plain as plain;
+use rand::rngs::OsRng;
+use third_party_reporting::lib_plain::{Plain, SentMessage};
+use third_party_reporting::lib_scheme::*;
 use third_party_reporting::lib_common::*;
-
+use third_party_reporting::lib_context::ReportContext;
 
 
 pub fn bench_plain_process(c: &mut Criterion) {
     // One time setup to generate client needed for message sending
-    let clients = plain::test_init_clients(1);
+    let clients = test_init_clients::<Plain, _>(1, &mut OsRng);
 
     // One time setup to generate messages of various sizes
-    let mut ms: Vec<Vec<String>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-    for msg_size in MSG_SIZE_SCALE.iter() {
-        ms.push(plain::test_init_messages(1, *msg_size));
-    }
-
-    // Send messages
-    let c1c2 = plain::test_send(&clients, &ms, false);
+    let ms = test_init_messages_variable(&mut OsRng);
 
     // Setup Moderator
-    let moderator = plain::Moderator::new();
+    let mut moderator = Plain::setup_platform();
+    let (_moderators, pks) = Plain::setup_mod(&mut moderator, 1);
+
+    // Send messages
+    let mut c1c2s: Vec<SentMessage> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+    for m in ms.iter() {
+        c1c2s.push(Plain::send(&clients[0], &m[0], 0, &pks).unwrap());
+    }
 
+    // A fresh conversation per iteration, the replay cache rejects the same
+    // commitment twice in one conversation
+    let mut conversation: u64 = 0;
     let mut group = c.benchmark_group("plain.process()");
     for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
         group.bench_with_input(format!("plain.process() message of size {}", msg_size), msg_size, |b, &_msg_size| {
-            let (c1, c2) = &c1c2[j];
-            b.iter(|| plain::Moderator::mod_process(&moderator.k_m, &c2, &CTX_STR))
+            b.iter(|| {
+                conversation += 1;
+                Plain::process(&mut moderator, &c1c2s[j], &ReportContext { conversation, ..CTX })
+            })
         });
     }
     
@@ -1,23 +1,28 @@
 use criterion::*;
-use third_party_reporting::lib_plain as plain;
+use rand::rngs::OsRng;
+use third_party_reporting::lib_plain::Plain;
+use third_party_reporting::lib_scheme::*;
 use third_party_reporting::lib_common::*;
 
+
 pub fn bench_plain_send(c: &mut Criterion) {
-    // One time setup to generate clients needed for message sending
-    let clients = plain::test_init_clients(1);
+    // One time setup to generate client needed for message sending
+    let clients = test_init_clients::<Plain, _>(1, &mut OsRng);
 
     // One time setup to generate messages of various sizes
-    let mut ms: Vec<Vec<String>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-    for msg_size in MSG_SIZE_SCALE.iter() {
-        ms.push(plain::test_init_messages(1, *msg_size));
-    }
+    let ms = test_init_messages_variable(&mut OsRng);
+
+    // Setup Moderator
+    let mut platform = Plain::setup_platform();
+    let (_moderators, pks) = Plain::setup_mod(&mut platform, 1);
 
     let mut group = c.benchmark_group("plain.send()");
     for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
         group.bench_with_input(format!("plain.send() message of size {}", msg_size), msg_size, |b, &_msg_size| {
-            b.iter(|| plain::Client::send(&ms[j][0], clients[0].k_r))
+            b.iter(|| Plain::send(&clients[0], &ms[j][0], 0, &pks))
         });
     }
+    
     group.finish();
 }
 
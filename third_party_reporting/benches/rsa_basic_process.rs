@@ -0,0 +1,40 @@
+use criterion::*;
+use rand::rngs::OsRng;
+use third_party_reporting::rsa_lib_basic::RsaBasic;
+use third_party_reporting::lib_scheme::*;
+use third_party_reporting::lib_common::*;
+use third_party_reporting::lib_context::ReportContext;
+
+pub fn bench_rsa_basic_process(c: &mut Criterion) {
+    // Setup platforms and moderators
+    let (mut platforms, _moderators, pks) = test_setup_with_scale::<RsaBasic, _>(&RSA_MOD_SCALE, &mut OsRng);
+
+    // One time setup to generate client needed for message sending
+    let clients = test_init_clients::<RsaBasic, _>(1, &mut OsRng);
+
+    // One time setup to generate messages of various sizes
+    let ms = test_init_messages_variable(&mut OsRng);
+
+    // Send messages
+    let c1c2ad = test_send_variable::<RsaBasic, _>(&pks, &clients, &ms, &mut OsRng);
+
+    // A fresh conversation per iteration, the replay cache rejects the same
+    // commitment twice in one conversation
+    let mut conversation: u64 = 0;
+    let mut group = c.benchmark_group("rsa-basic.process()");
+    for (i, num_moderators) in RSA_MOD_SCALE.iter().enumerate() {
+        for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
+            group.bench_with_input(format!("rsa-basic.process() message of size {} with {} moderators", msg_size, num_moderators), msg_size, |b, &_msg_size| {
+                b.iter(|| {
+                    conversation += 1;
+                    RsaBasic::process(&mut platforms[i], &c1c2ad[i][j][0], &ReportContext { conversation, ..CTX })
+                })
+            });
+        }
+    }
+    
+    group.finish();
+}
+
+criterion_group!(benches, bench_rsa_basic_process);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+use criterion::*;
+use rand::rngs::OsRng;
+use third_party_reporting::rsa_lib_basic::RsaBasic;
+use third_party_reporting::lib_scheme::*;
+use third_party_reporting::lib_common::*;
+
+pub fn bench_rsa_basic_read(c: &mut Criterion) {
+    // Setup platforms and moderators
+    let (mut platforms, _moderators, pks) = test_setup_with_scale::<RsaBasic, _>(&RSA_MOD_SCALE, &mut OsRng);
+
+    // One time setup to generate client needed for message sending
+    let clients = test_init_clients::<RsaBasic, _>(1, &mut OsRng);
+
+    // One time setup to generate messages of various sizes
+    let ms = test_init_messages_variable(&mut OsRng);
+
+    // Send messages
+    let c1c2ad = test_send_variable::<RsaBasic, _>(&pks, &clients, &ms, &mut OsRng);
+
+    // Process messages
+    let sigma_st = test_process_variable::<RsaBasic, _>(&c1c2ad, &mut platforms, &mut OsRng);
+
+    let mut group = c.benchmark_group("rsa-basic.read()");
+    for (i, num_moderators) in RSA_MOD_SCALE.iter().enumerate() {
+        for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
+            group.bench_with_input(format!("rsa-basic.read() message of size {} with {} moderators", msg_size, num_moderators), msg_size, |b, &_msg_size| {
+                let (sigma, st) = &sigma_st[i][j][0];
+                b.iter(|| RsaBasic::read(&clients[0], &pks[i], &c1c2ad[i][j][0], sigma, st))
+            });
+        }
+    }
+    
+    group.finish();
+}
+
+criterion_group!(benches, bench_rsa_basic_read);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+use criterion::*;
+use rand::rngs::OsRng;
+use third_party_reporting::rsa_lib_basic::RsaBasic;
+use third_party_reporting::lib_scheme::*;
+use third_party_reporting::lib_common::*;
+
+pub fn bench_rsa_basic_send(c: &mut Criterion) {
+    // Setup platforms and moderators
+    let (_platforms, _moderators, pks) = test_setup_with_scale::<RsaBasic, _>(&RSA_MOD_SCALE, &mut OsRng);
+
+    // One time setup to generate client needed for message sending
+    let clients = test_init_clients::<RsaBasic, _>(1, &mut OsRng);
+
+    // One time setup to generate messages of various sizes
+    let ms = test_init_messages_variable(&mut OsRng);
+
+    let mut group = c.benchmark_group("rsa-basic.send()");
+    for (i, num_moderators) in RSA_MOD_SCALE.iter().enumerate() {
+        for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
+            group.bench_with_input(format!("rsa-basic.send() message of size {} with {} moderators", msg_size, num_moderators), msg_size, |b, &_msg_size| {
+                b.iter(|| RsaBasic::send(&clients[0], &ms[j][0], 0, &pks[i]))
+            });
+        }
+    }
+    
+    group.finish();
+}
+
+criterion_group!(benches, bench_rsa_basic_send);
+criterion_main!(benches);
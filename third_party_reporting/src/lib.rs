@@ -1,7 +1,35 @@
-#![allow(warnings)]
 pub mod lib_common;
+pub mod lib_error;
+#[cfg(feature = "basic")]
 pub mod lib_basic;
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
 pub mod lib_gamal;
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub mod lib_nizk;
+#[cfg(feature = "basic")]
+pub mod lib_threshold;
+#[cfg(feature = "basic")]
+pub mod lib_dkg;
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv"))]
+pub mod lib_verdict;
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub mod lib_registration;
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub mod lib_replay;
+pub mod lib_context;
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub mod lib_window;
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub mod lib_merkle;
+#[cfg(feature = "mod_priv")]
 pub mod lib_mod_priv;
+#[cfg(feature = "constant_mod_priv")]
 pub mod lib_constant_mod_priv;
+#[cfg(feature = "plain")]
 pub mod lib_plain;
+#[cfg(feature = "rsa")]
+pub mod rsa_lib_basic;
+pub mod lib_scheme;
+pub mod lib_wire;
+pub mod lib_secret;
+pub mod lib_domain;
@@ -1,65 +1,655 @@
 use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit},
-    Aes256Gcm, Nonce, Key
+    aead::KeyInit,
+    Aes256Gcm, Key
 };
-use bincode;
 use crate::lib_common::*;
+use crate::lib_error::ReportingError;
 use crate::lib_gamal as gamal;
-use rand::distributions::Alphanumeric;
-use rand::thread_rng;
-use rand::distributions::DistString;
-use rand::Rng;
+use crate::lib_threshold::{self as threshold, CommitteeKey, KeyShare, PartialDecryption};
+use crate::lib_dkg::{self as dkg, DkgOutput};
+use crate::lib_nizk::{self as nizk, DleqProof, EncryptionProof, SchnorrProof};
+use crate::lib_registration::{self as registration, Revocations, RetiredKeys};
+use crate::lib_replay::ReplayCache;
+use crate::lib_context::{ReportContext, ExpiryPolicy};
+use crate::lib_merkle::Opening;
+use crate::lib_verdict::{self as verdict, SignedVerdict};
+use crate::lib_scheme::ReportingScheme;
+use crate::lib_secret::Secret;
+use crate::lib_wire::*;
+use crate::lib_domain::{Domain, Role, Usage};
 use curve25519_dalek::ristretto::RistrettoPoint;
 use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
 use std::mem;
 use group::*;
-use sha2::{Sha512, Digest};
+use sha2::Sha512;
+use serde::{Serialize, Deserialize};
+use rand::{CryptoRng, RngCore};
+use rand::rngs::OsRng;
 
 type Point = CompressedRistretto;
 type Ciphertext = (Point, Point);
-use generic_array::typenum::U12;
 
-type Report = ([u8; 32], Vec<u8>, Vec<u8>, Ciphertext);
+// Moderator public key (pk_enc, pk_tag, epoch of pk_enc)
+pub type PublicKey = (Point, Point, u32);
+
+// Domain separation labels (see lib_domain.rs)
+const COMMITMENT: Domain = Domain::new(SCHEME_BASIC, Role::Sender, Usage::Commitment);
+const PLATFORM_TAG: Domain = Domain::new(SCHEME_BASIC, Role::Platform, Usage::PlatformTag);
+const SHARE_PROOF: Domain = Domain::new(SCHEME_BASIC, Role::Moderator, Usage::Proof);
+const TAG_PROOF: Domain = Domain::new(SCHEME_BASIC, Role::Platform, Usage::Proof);
+const VERDICT: Domain = Domain::new(SCHEME_BASIC, Role::Moderator, Usage::Verdict);
+const POSSESSION: Domain = Domain::new(SCHEME_BASIC, Role::Moderator, Usage::Registration);
+const CERTIFICATE: Domain = Domain::new(SCHEME_BASIC, Role::Platform, Usage::Registration);
+const REVOCATION: Domain = Domain::new(SCHEME_BASIC, Role::Platform, Usage::Revocation);
+const ROTATION: Domain = Domain::new(SCHEME_BASIC, Role::Moderator, Usage::Rotation);
+
+// The platform tag for moderator i is the algebraic MAC
+//   sigma_pt = k_i * H(c2 || ctx)
+// with H hashing to Ristretto, k_i the tag key shared by the platform and the
+// moderator and pk_tag_i = k_i * G in the moderator directory. process proves
+// that sigma = (r * G, r * pk_enc_i + k_i * H(c2 || ctx)) without revealing
+// sigma_pt (lib_nizk.rs), and read rejects sigma unless the proof verifies, so
+// the platform cannot hand out unreportable messages.
+fn tag_base(c2: &[u8], ctx: &ReportContext) -> Result<RistrettoPoint, ReportingError> {
+    let mut input = FieldWriter::new();
+    input.bytes(&PLATFORM_TAG.label());
+    input.bytes(c2);
+    input.bytes(&ctx.to_bytes());
+
+    Ok(RistrettoPoint::hash_from_bytes::<Sha512>(&input.finish()?))
+}
+
+// Output of send: (c1, c2, ad)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub c1: Vec<u8>, // Encryption of (m, k_f) under the shared message key
+    pub c2: Vec<u8>, // Commitment to the message
+    pub moderator_id: u32 // ad, the moderator chosen by the sender
+}
+
+// State output by process alongside the encrypted tag
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessState {
+    pub ctx: ReportContext, // Platform context
+    pub moderator_id: u32,
+    pub epoch: u32, // Epoch of the moderator key sigma is encrypted under
+    pub proof: EncryptionProof // sigma encrypts the tag of (c2, ctx) under the moderator keys
+}
+
+// Report sent to the moderator (the report doc stored by the receiver is identical)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub opening: Opening, // Opens c2 to the message (k_f) or to some of its segments (lib_merkle.rs)
+    pub c2: Vec<u8>, // Commitment to the message
+    pub ctx: ReportContext, // Platform context
+    pub sigma: Ciphertext, // El Gamal encryption of the platform tag
+    pub epoch: u32 // Epoch of the key sigma is encrypted under
+}
+
+pub type ReportDoc = Report;
+
+// Registration request (lib_registration.rs), proves possession of sk_enc
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationRequest {
+    pub pk_enc: Point,
+    pub pop: SchnorrProof
+}
+
+// Platform signature on a moderator directory entry
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    pub moderator_id: u32,
+    pub public_key: PublicKey, // (pk_enc, pk_tag, epoch)
+    pub signature: SchnorrProof // Signature under k_reg
+}
+
+// Registration response, sent over a private channel
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationResponse {
+    pub sk_p: Secret<Scalar>, // Tag key issued by the Platform
+    pub certificate: Certificate
+}
+
+// Key rotation request (lib_registration.rs), pk_enc for the next epoch with
+// a proof of possession, authorized with the current sk_enc. The tag key does
+// not change
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RotationRequest {
+    pub moderator_id: u32,
+    pub pk_enc: Point,
+    pub epoch: u32,
+    pub pop: SchnorrProof,
+    pub authorization: SchnorrProof // Signature under the current pk_enc
+}
+
+// Revoked moderator ids published by the Platform
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub version: u32, // Number of revocations so far
+    pub revoked: Vec<u32>,
+    pub signature: SchnorrProof // Signature under k_reg
+}
+
+fn write_public_key(w: &mut FieldWriter, pk: &PublicKey) {
+    w.point(&pk.0);
+    w.point(&pk.1);
+    w.u32(pk.2);
+}
+
+fn read_public_key(r: &mut FieldReader) -> Result<PublicKey, ReportingError> {
+    Ok((r.point()?, r.point()?, r.u32()?))
+}
+
+fn public_key_bytes(pk: &PublicKey) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    write_public_key(&mut w, pk);
+
+    w.finish().expect("fixed-size fields")
+}
+
+impl Certificate {
+    // Checks the Platform signature, clients call it before using public_key
+    pub fn verify(&self, k_reg: &Point) -> Result<(), ReportingError> {
+        let message = registration::certificate_message(self.moderator_id, &public_key_bytes(&self.public_key));
+        if !nizk::schnorr_verify(&CERTIFICATE, &gamal::decompress(k_reg)?, &message, &self.signature) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(())
+    }
+}
+
+impl RevocationList {
+    pub fn verify(&self, k_reg: &Point) -> Result<(), ReportingError> {
+        let message = registration::revocation_message(self.version, &self.revoked);
+        if !nizk::schnorr_verify(&REVOCATION, &gamal::decompress(k_reg)?, &message, &self.signature) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(())
+    }
+}
+
+impl WireMessage for SentMessage {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::Send;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.c1);
+        w.bytes(&self.c2);
+        w.u32(self.moderator_id);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<SentMessage, ReportingError> {
+        Ok(SentMessage { c1: r.bytes()?.to_vec(), c2: r.bytes()?.to_vec(), moderator_id: r.u32()? })
+    }
+}
+
+// (sigma, st)
+impl WireMessage for (Ciphertext, ProcessState) {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::Process;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        let ((u, v), st) = self;
+        w.point(u);
+        w.point(v);
+        w.bytes(&st.ctx.to_bytes());
+        w.u32(st.moderator_id);
+        w.u32(st.epoch);
+        w.scalar(&st.proof.c);
+        w.scalar(&st.proof.z_r);
+        w.scalar(&st.proof.z_k);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<(Ciphertext, ProcessState), ReportingError> {
+        let sigma = (r.point()?, r.point()?);
+        let (ctx, moderator_id, epoch) = (ReportContext::from_bytes(r.bytes()?)?, r.u32()?, r.u32()?);
+        let proof = EncryptionProof { c: r.scalar()?, z_r: r.scalar()?, z_k: r.scalar()? };
+
+        Ok((sigma, ProcessState { ctx, moderator_id, epoch, proof }))
+    }
+}
+
+// Report docs are stored with the same encoding as reports
+impl WireMessage for Report {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::Report;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        self.opening.write_fields(w);
+        w.bytes(&self.c2);
+        w.bytes(&self.ctx.to_bytes());
+        w.point(&self.sigma.0);
+        w.point(&self.sigma.1);
+        w.u32(self.epoch);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Report, ReportingError> {
+        Ok(Report {
+            opening: Opening::read_fields(r)?,
+            c2: r.bytes()?.to_vec(),
+            ctx: ReportContext::from_bytes(r.bytes()?)?,
+            sigma: (r.point()?, r.point()?),
+            epoch: r.u32()?
+        })
+    }
+}
+
+// Shares are exchanged between committee members
+impl WireMessage for PartialDecryption {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::PartialDecryption;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.index);
+        w.point(&self.d);
+        w.scalar(&self.proof.c);
+        w.scalar(&self.proof.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<PartialDecryption, ReportingError> {
+        Ok(PartialDecryption { index: r.u32()?, d: r.point()?, proof: DleqProof { c: r.scalar()?, z: r.scalar()? } })
+    }
+}
+
+impl WireMessage for RegistrationRequest {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::RegistrationRequest;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.point(&self.pk_enc);
+        w.scalar(&self.pop.c);
+        w.scalar(&self.pop.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationRequest, ReportingError> {
+        Ok(RegistrationRequest { pk_enc: r.point()?, pop: SchnorrProof { c: r.scalar()?, z: r.scalar()? } })
+    }
+}
+
+impl WireMessage for RotationRequest {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::RotationRequest;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.moderator_id);
+        w.point(&self.pk_enc);
+        w.u32(self.epoch);
+        w.scalar(&self.pop.c);
+        w.scalar(&self.pop.z);
+        w.scalar(&self.authorization.c);
+        w.scalar(&self.authorization.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RotationRequest, ReportingError> {
+        Ok(RotationRequest {
+            moderator_id: r.u32()?,
+            pk_enc: r.point()?,
+            epoch: r.u32()?,
+            pop: SchnorrProof { c: r.scalar()?, z: r.scalar()? },
+            authorization: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+impl WireMessage for Certificate {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::Certificate;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.moderator_id);
+        write_public_key(w, &self.public_key);
+        w.scalar(&self.signature.c);
+        w.scalar(&self.signature.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Certificate, ReportingError> {
+        Ok(Certificate {
+            moderator_id: r.u32()?,
+            public_key: read_public_key(r)?,
+            signature: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+// The certificate fields follow the tag key
+impl WireMessage for RegistrationResponse {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::RegistrationResponse;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.scalar(&self.sk_p);
+        self.certificate.write_fields(w);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationResponse, ReportingError> {
+        Ok(RegistrationResponse { sk_p: Secret::new(r.scalar()?), certificate: Certificate::read_fields(r)? })
+    }
+}
+
+impl WireMessage for RevocationList {
+    const SCHEME_ID: u8 = SCHEME_BASIC;
+    const KIND: MessageKind = MessageKind::RevocationList;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.version);
+        registration::write_ids(w, &self.revoked);
+        w.scalar(&self.signature.c);
+        w.scalar(&self.signature.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RevocationList, ReportingError> {
+        Ok(RevocationList {
+            version: r.u32()?,
+            revoked: registration::read_ids(r)?,
+            signature: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+// Moderator keys before registration
+pub struct PendingModerator {
+    sk_enc: Secret<Scalar>, // Moderator private key
+    pub pk_enc: Point, // Moderator public key
+    pub k_reg: Point, // Registration key of the Platform
+    pub epoch: u32 // Epoch the key is for, 0 at registration
+}
+
+impl PendingModerator {
+    // SetupMod(pk_reg, 1^lambda)
+    pub fn new(k_reg: &Point) -> PendingModerator {
+        Self::new_with_rng(k_reg, &mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(k_reg: &Point, rng: &mut R) -> PendingModerator {
+        Self::for_epoch(k_reg, 0, rng)
+    }
+
+    fn for_epoch<R: CryptoRng + RngCore>(k_reg: &Point, epoch: u32, rng: &mut R) -> PendingModerator {
+        let keys = gamal::elgamal_keygen_with_rng(rng);
+        PendingModerator {
+            sk_enc: Secret::new(keys.0),
+            pk_enc: keys.1.compress(),
+            k_reg: *k_reg,
+            epoch
+        }
+    }
+
+    pub fn request(&self) -> RegistrationRequest {
+        self.request_with_rng(&mut OsRng)
+    }
+
+    pub fn request_with_rng<R: CryptoRng + RngCore>(&self, rng: &mut R) -> RegistrationRequest {
+        let message = registration::pop_message(self.k_reg.as_bytes(), self.pk_enc.as_bytes());
+        let pop = nizk::schnorr_prove(&POSSESSION, &self.sk_enc, &RistrettoPoint::mul_base(&self.sk_enc), &message, rng);
+
+        RegistrationRequest { pk_enc: self.pk_enc, pop }
+    }
+
+    // Checks the certificate and that it certifies pk_enc and the issued tag key
+    pub fn finish(self, response: RegistrationResponse) -> Result<Moderator, ReportingError> {
+        let RegistrationResponse { sk_p, certificate } = response;
+        certificate.verify(&self.k_reg)?;
+
+        let (pk_enc, pk_tag, epoch) = certificate.public_key;
+        if pk_enc != self.pk_enc || epoch != self.epoch || RistrettoPoint::mul_base(&sk_p).compress() != pk_tag {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(Moderator {
+            sk_p,
+            sk_enc: self.sk_enc,
+            pk_enc,
+            pk_tag,
+            k_reg: self.k_reg,
+            retired: RetiredKeys::new(),
+            grace_epochs: registration::GRACE_EPOCHS,
+            certificate
+        })
+    }
+}
 
 // Moderator Properties
 pub struct Moderator {
-    pub sk_p: [u8; 64], // Mac Key shared with the Platform
-    pub sk_enc: Scalar, // Moderator private key
-    pub pk_enc: Point // Moderator public key
+    sk_p: Secret<Scalar>, // Tag key shared with the Platform
+    sk_enc: Secret<Scalar>, // Moderator private key
+    pub pk_enc: Point, // Moderator public key
+    pub pk_tag: Point, // Tag verification key sk_p * G
+    pub k_reg: Point, // Registration key of the Platform
+    retired: RetiredKeys<Secret<Scalar>>, // sk_enc of the earlier epochs still in their grace period
+    pub grace_epochs: u32, // Number of earlier epochs whose reports are still moderated
+    pub certificate: Certificate // Issued at registration or at the last rotation
 }
 
 // Moderator Implementation 
 impl Moderator {
-    // SetupMod(pk_reg, 1^lambda)
-    pub fn new(_pk_reg: &Option<Vec<u8>>) -> Moderator {
-        let keys = gamal::elgamal_keygen();
-        Moderator {
-            sk_p: mac_64_keygen(),
-            sk_enc: keys.0,
-            pk_enc: keys.1.compress()
+    pub fn id(&self) -> u32 {
+        self.certificate.moderator_id
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.certificate.public_key.2
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        (self.pk_enc, self.pk_tag, self.epoch())
+    }
+
+    // Key for the next epoch and the request to send to the platform
+    pub fn rotate(&self) -> (PendingModerator, RotationRequest) {
+        self.rotate_with_rng(&mut OsRng)
+    }
+
+    pub fn rotate_with_rng<R: CryptoRng + RngCore>(&self, rng: &mut R) -> (PendingModerator, RotationRequest) {
+        let pending = PendingModerator::for_epoch(&self.k_reg, self.epoch() + 1, rng);
+        let RegistrationRequest { pk_enc, pop } = pending.request_with_rng(rng);
+
+        let public_key = (pk_enc, self.pk_tag, pending.epoch);
+        let message = registration::rotation_message(self.k_reg.as_bytes(), self.id(), &public_key_bytes(&public_key));
+        let authorization = nizk::schnorr_prove(&ROTATION, &self.sk_enc, &RistrettoPoint::mul_base(&self.sk_enc), &message, rng);
+
+        let request = RotationRequest { moderator_id: self.id(), pk_enc, epoch: pending.epoch, pop, authorization };
+
+        (pending, request)
+    }
+
+    // Switches to the key of pending once the platform certified it, the
+    // current sk_enc is kept for grace_epochs epochs
+    pub fn finish_rotation(&mut self, pending: PendingModerator, certificate: Certificate) -> Result<(), ReportingError> {
+        certificate.verify(&self.k_reg)?;
+        if certificate.moderator_id != self.id() || certificate.public_key != (pending.pk_enc, self.pk_tag, pending.epoch) {
+            return Err(ReportingError::BadCertificate);
         }
+
+        let epoch = self.epoch();
+        let sk_enc = mem::replace(&mut self.sk_enc, pending.sk_enc);
+        self.retired.retire(epoch, sk_enc, pending.epoch, self.grace_epochs);
+        self.pk_enc = pending.pk_enc;
+        self.certificate = certificate;
+
+        Ok(())
     }
 
-    pub fn moderate(sk_enc: &Scalar, sk_p: &[u8; 64], message: &str, report: &([u8; 32], Vec<u8>, Vec<u8>, Ciphertext)) -> String {
-        let (k_f, c2, ctx, ct) = report;
+    // sk_enc of the epoch a report was made under
+    fn epoch_key(&self, epoch: u32) -> Result<&Secret<Scalar>, ReportingError> {
+        self.retired.get(epoch, self.epoch(), &self.sk_enc)
+    }
 
-        let (u, v) = ct;
-        let sigma_pt = gamal::elgamal_dec(sk_enc, &(u.decompress().unwrap(), v.decompress().unwrap())).to_bytes();
+    pub fn moderate(&self, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        let (u, v) = &report.sigma;
+        let decrypted = self.epoch_key(report.epoch).and_then(|sk_enc| Ok(gamal::elgamal_dec(sk_enc, &(gamal::decompress(u)?, gamal::decompress(v)?))));
 
-        // Verify committment
-        assert!(com_open(&c2, message, k_f));
+        match decrypted {
+            Ok(sigma_pt) => verify_report(&self.sk_p, message, report, &sigma_pt, policy),
+            // The report names an epoch without a key or carries an invalid point
+            Err(e) => Ok(Verdict::Reject(e))
+        }
+    }
+
+    // moderate with a verdict anyone can check against pk_enc (lib_verdict.rs).
+    // A report that cannot be decrypted has no decryption to prove and yields
+    // an error
+    pub fn moderate_signed(&self, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<SignedVerdict, ReportingError> {
+        self.moderate_signed_with_rng(message, report, policy, &mut OsRng)
+    }
+
+    pub fn moderate_signed_with_rng<R: CryptoRng + RngCore>(&self, message: &str, report: &Report, policy: &ExpiryPolicy, rng: &mut R) -> Result<SignedVerdict, ReportingError> {
+        let sk_enc = self.epoch_key(report.epoch)?;
+        let (u, v) = (gamal::decompress(&report.sigma.0)?, gamal::decompress(&report.sigma.1)?);
+        let d = **sk_enc * u;
+        let sigma_pt = v - d;
 
-        // Verify Point
-        let mut maybe_sigma = mac_64_sign(&sk_p, &[&c2[..], &ctx[..]].concat());
-        // Point encrypt
-        let maybe_sigma = RistrettoPoint::from_uniform_bytes(&maybe_sigma).to_bytes();
+        let verdict = verify_report(&self.sk_p, message, report, &sigma_pt, policy)?;
 
-        // Verify Signature
-        assert!(maybe_sigma == sigma_pt);
+        // log_G(pk_enc) = log_u(v - sigma_pt), signing the verdict
+        let transcript = verdict::verdict_transcript(&verdict, &report.ctx, &report.c2)?;
+        let proof = nizk::dleq_prove_with_message(&VERDICT, sk_enc, &u, &RistrettoPoint::mul_base(sk_enc), &d, &transcript, rng);
 
-        let ctx_s = std::str::from_utf8(&ctx).unwrap();
-        return ctx_s.to_string();
+        Ok(SignedVerdict { verdict, ctx: report.ctx, decrypted: sigma_pt.compress(), proof })
+    }
+}
+
+// Checks a signed verdict of the moderator with public key pk, of the epoch
+// of the report, on report
+pub fn verify_verdict(pk: &PublicKey, message: &str, report: &Report, sv: &SignedVerdict) -> Result<(), ReportingError> {
+    let Report { opening, c2, ctx, sigma, epoch, .. } = report;
+    let (pk_enc, _pk_tag, pk_epoch) = pk;
+    registration::check_epoch(*epoch, *pk_epoch)?;
+
+    verdict::check_outcome(sv, ctx, opening.open(&COMMITMENT, c2, message))?;
+
+    let (u, v) = (gamal::decompress(&sigma.0)?, gamal::decompress(&sigma.1)?);
+    let d = v - gamal::decompress(&sv.decrypted)?;
+    let transcript = verdict::verdict_transcript(&sv.verdict, ctx, c2)?;
+    if !nizk::dleq_verify_with_message(&VERDICT, &u, &gamal::decompress(pk_enc)?, &d, &transcript, &sv.proof) {
+        return Err(ReportingError::BadProof);
+    }
+
+    Ok(())
+}
+
+// Checks the commitment and the decrypted platform tag of a report, then its
+// age against policy
+fn verify_report(sk_p: &Scalar, message: &str, report: &Report, sigma_pt: &RistrettoPoint, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+    let Report { opening, c2, ctx, .. } = report;
+    let sigma_pt = sigma_pt.to_bytes();
+
+    // Verify committment
+    if !opening.open(&COMMITMENT, c2, message) {
+        return Ok(Verdict::Reject(ReportingError::BadCommitment));
+    }
+
+    // Verify Point
+    let maybe_sigma = (sk_p * tag_base(c2, ctx)?).to_bytes();
+
+    // Verify Signature
+    if !ct_eq_bytes(&maybe_sigma, &sigma_pt) {
+        return Ok(Verdict::Reject(ReportingError::BadPlatformTag));
+    }
+
+    Ok(policy.verdict(ctx))
+}
+
+
+
+// Threshold moderation: a committee of n moderators registered under a single
+// moderator id. The platform encrypts sigma to the committee key as it would
+// to a single moderator, decrypting it takes t partial decryptions (see
+// lib_threshold.rs). Committee keys are not rotated: the committee stays at
+// epoch 0 and rejects reports of any other epoch, a new committee takes a new
+// moderator id
+pub struct CommitteeMember {
+    sk_p: Secret<Scalar>, // Tag key shared by the committee and the Platform
+    share: KeyShare, // Shamir share of the committee decryption key
+    pub key: CommitteeKey, // Committee public key and share verification keys
+    pub certificate: Certificate // Issued to the committee at registration
+}
+
+impl CommitteeMember {
+    // SetupMod for a committee of n members with threshold t registered with
+    // platform, keys are dealt by a trusted dealer
+    pub fn new_committee(t: u32, n: u32, platform: &mut Platform) -> Result<Vec<CommitteeMember>, ReportingError> {
+        Self::new_committee_with_rng(t, n, platform, &mut OsRng)
+    }
+
+    pub fn new_committee_with_rng<R: CryptoRng + RngCore>(t: u32, n: u32, platform: &mut Platform, rng: &mut R) -> Result<Vec<CommitteeMember>, ReportingError> {
+        let (key, shares) = threshold::threshold_keygen_with_rng(t, n, rng)?;
+        let response = platform.register_committee_with_rng(&key, rng)?;
+
+        shares.into_iter().map(|share| CommitteeMember::new(share, key.clone(), &response, &platform.k_reg)).collect()
+    }
+
+    // SetupMod for a committee of n members with threshold t registered with
+    // platform, keys come from a DKG run in process so no party learns the
+    // committee decryption key
+    pub fn new_committee_dkg(t: u32, n: u32, platform: &mut Platform) -> Result<Vec<CommitteeMember>, ReportingError> {
+        Self::new_committee_dkg_with_rng(t, n, platform, &mut OsRng)
+    }
+
+    pub fn new_committee_dkg_with_rng<R: CryptoRng + RngCore>(t: u32, n: u32, platform: &mut Platform, rng: &mut R) -> Result<Vec<CommitteeMember>, ReportingError> {
+        let outputs = dkg::run_dkg_with_rng(t, n, rng)?;
+        let response = platform.register_committee_with_rng(&outputs[0].key, rng)?;
+
+        outputs.into_iter().map(|output| CommitteeMember::from_dkg(output, &response, &platform.k_reg)).collect()
+    }
+
+    // Member holding share of the committee key, response is the registration
+    // response of the committee, checked against k_reg
+    pub fn new(share: KeyShare, key: CommitteeKey, response: &RegistrationResponse, k_reg: &Point) -> Result<CommitteeMember, ReportingError> {
+        let RegistrationResponse { sk_p, certificate } = response;
+        certificate.verify(k_reg)?;
+
+        let (pk_enc, pk_tag, _epoch) = &certificate.public_key;
+        if *pk_enc != key.pk || RistrettoPoint::mul_base(sk_p).compress() != *pk_tag {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(CommitteeMember { sk_p: sk_p.clone(), share, key, certificate: certificate.clone() })
+    }
+
+    // Member from its DKG output
+    pub fn from_dkg(output: DkgOutput, response: &RegistrationResponse, k_reg: &Point) -> Result<CommitteeMember, ReportingError> {
+        Self::new(output.share, output.key, response, k_reg)
+    }
+
+    // (committee pk_enc, pk_tag, epoch), the committee's entry in the moderator directory
+    pub fn public_key(&self) -> PublicKey {
+        self.certificate.public_key
+    }
+
+    pub fn index(&self) -> u32 {
+        self.share.index
+    }
+
+    // This member's share of the decryption of sigma
+    pub fn partial_decrypt<R: CryptoRng + RngCore>(&self, report: &Report, rng: &mut R) -> Result<PartialDecryption, ReportingError> {
+        let u = gamal::decompress(&report.sigma.0)?;
+
+        Ok(self.share.partial_decrypt_with_rng(&SHARE_PROOF, &u, rng))
+    }
+
+    // Combines the shares of the committee and verifies the report, rejects
+    // with InsufficientShares unless t of the shares carry a valid proof
+    pub fn moderate(&self, message: &str, report: &Report, shares: &[PartialDecryption], policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        if let Err(e) = registration::check_epoch(report.epoch, self.certificate.public_key.2) {
+            return Ok(Verdict::Reject(e));
+        }
+
+        let (u, v) = &report.sigma;
+        let ct = match (gamal::decompress(u), gamal::decompress(v)) {
+            (Ok(u), Ok(v)) => (u, v),
+            _ => return Ok(Verdict::Reject(ReportingError::InvalidCurvePoint))
+        };
+
+        let sigma_pt = match threshold::threshold_decrypt(&SHARE_PROOF, &self.key, &ct, shares) {
+            Ok(sigma_pt) => sigma_pt,
+            Err(e @ ReportingError::InsufficientShares(_)) => return Ok(Verdict::Reject(e)),
+            Err(e) => return Err(e)
+        };
+
+        verify_report(&self.sk_p, message, report, &sigma_pt, policy)
     }
 }
 
@@ -67,332 +657,652 @@ impl Moderator {
 
 // Platform Properties
 pub struct Platform {
-    pub k_p: Option<Vec<u8>>, // Platform key
-    pub k_reg: Option<Vec<u8>>, // Registration key
-    pub sk_p: Vec<([u8; 64], Point)> // Vector of Moderator keys accessible to the Platform
+    k_p: Secret<Scalar>, // Certificate signing key
+    pub k_reg: Point, // Registration key k_p * G
+    sk_p: Vec<Option<(Secret<Scalar>, PublicKey)>>, // Vector of Moderator keys accessible to the Platform, indexed by moderator id, None once revoked
+    revocations: RevocationList, // Signed list of the revoked ids
+    pub replay: ReplayCache // Commitments processed per conversation (lib_replay.rs)
 }
 
 // Platform Implementation
 impl Platform {
     pub fn new() -> Platform {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
+        let (k_p, k_reg) = gamal::elgamal_keygen_with_rng(rng);
+        let k_p = Secret::new(k_p);
+        let revocations = sign_revocations(&k_p, 0, Vec::new(), rng);
+
         Platform {
-            k_p: None,
-            k_reg: None,
-            sk_p: Vec::<([u8; 64], Point)>::new()
+            k_p,
+            k_reg: k_reg.compress(),
+            sk_p: Vec::<Option<(Secret<Scalar>, PublicKey)>>::new(),
+            revocations,
+            replay: ReplayCache::new()
+        }
+    }
+
+    // Removes moderator id from the directory and returns the new revocation
+    // list for clients. The slot stays empty so no other id changes
+    pub fn revoke(&mut self, moderator_id: u32) -> Result<RevocationList, ReportingError> {
+        self.revoke_with_rng(moderator_id, &mut OsRng)
+    }
+
+    pub fn revoke_with_rng<R: CryptoRng + RngCore>(&mut self, moderator_id: u32, rng: &mut R) -> Result<RevocationList, ReportingError> {
+        let slot = self.sk_p.get_mut(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
+        if slot.take().is_some() {
+            let mut revoked = self.revocations.revoked.clone();
+            revoked.push(moderator_id);
+            self.revocations = sign_revocations(&self.k_p, self.revocations.version + 1, revoked, rng);
         }
+
+        Ok(self.revocations.clone())
+    }
+
+    pub fn revocation_list(&self) -> &RevocationList {
+        &self.revocations
+    }
+
+    // Checks the proof of possession, issues the tag key and certifies the
+    // new directory entry
+    pub fn register(&mut self, request: &RegistrationRequest) -> Result<RegistrationResponse, ReportingError> {
+        self.register_with_rng(request, &mut OsRng)
+    }
+
+    pub fn register_with_rng<R: CryptoRng + RngCore>(&mut self, request: &RegistrationRequest, rng: &mut R) -> Result<RegistrationResponse, ReportingError> {
+        let message = registration::pop_message(self.k_reg.as_bytes(), request.pk_enc.as_bytes());
+        if !nizk::schnorr_verify(&POSSESSION, &gamal::decompress(&request.pk_enc)?, &message, &request.pop) {
+            return Err(ReportingError::BadProof);
+        }
+
+        Ok(self.issue(request.pk_enc, rng))
+    }
+
+    // Adds a moderator committee to the directory under a single moderator id.
+    // No member holds the committee key, so there is no proof of possession,
+    // the shares are checked by the dealer or the DKG instead
+    pub fn register_committee(&mut self, key: &CommitteeKey) -> Result<RegistrationResponse, ReportingError> {
+        self.register_committee_with_rng(key, &mut OsRng)
+    }
+
+    pub fn register_committee_with_rng<R: CryptoRng + RngCore>(&mut self, key: &CommitteeKey, rng: &mut R) -> Result<RegistrationResponse, ReportingError> {
+        gamal::decompress(&key.pk)?;
+
+        Ok(self.issue(key.pk, rng))
+    }
+
+    // Checks the new key, the authorization by the current one and certifies
+    // the new key, the tag key stays the same
+    pub fn rotate(&mut self, request: &RotationRequest) -> Result<Certificate, ReportingError> {
+        self.rotate_with_rng(request, &mut OsRng)
+    }
+
+    pub fn rotate_with_rng<R: CryptoRng + RngCore>(&mut self, request: &RotationRequest, rng: &mut R) -> Result<Certificate, ReportingError> {
+        let RotationRequest { moderator_id, pk_enc, epoch, pop, authorization } = request;
+        let slot = self.sk_p.get(*moderator_id as usize).ok_or(ReportingError::UnknownModerator(*moderator_id))?;
+        let (_, (current_pk_enc, pk_tag, current_epoch)) = slot.as_ref().ok_or(ReportingError::RevokedModerator(*moderator_id))?;
+
+        registration::check_epoch(*epoch, current_epoch + 1)?;
+        let message = registration::pop_message(self.k_reg.as_bytes(), pk_enc.as_bytes());
+        if !nizk::schnorr_verify(&POSSESSION, &gamal::decompress(pk_enc)?, &message, pop) {
+            return Err(ReportingError::BadProof);
+        }
+
+        let public_key = (*pk_enc, *pk_tag, *epoch);
+        let message = registration::rotation_message(self.k_reg.as_bytes(), *moderator_id, &public_key_bytes(&public_key));
+        if !nizk::schnorr_verify(&ROTATION, &gamal::decompress(current_pk_enc)?, &message, authorization) {
+            return Err(ReportingError::BadProof);
+        }
+
+        let certificate = self.certify(*moderator_id, public_key, rng);
+        if let Some((_, current)) = &mut self.sk_p[*moderator_id as usize] {
+            *current = public_key;
+        }
+
+        Ok(certificate)
+    }
+
+    fn issue<R: CryptoRng + RngCore>(&mut self, pk_enc: Point, rng: &mut R) -> RegistrationResponse {
+        let sk_p = Secret::new(Scalar::random(rng));
+        let public_key = (pk_enc, RistrettoPoint::mul_base(&sk_p).compress(), 0);
+        let moderator_id = self.sk_p.len() as u32;
+        let certificate = self.certify(moderator_id, public_key, rng);
+
+        self.sk_p.push(Some((sk_p.clone(), public_key)));
+
+        RegistrationResponse { sk_p, certificate }
     }
 
-    pub fn setup_platform(&mut self) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
-        self.k_p = None;
-        self.k_reg = None;
+    fn certify<R: CryptoRng + RngCore>(&self, moderator_id: u32, public_key: PublicKey, rng: &mut R) -> Certificate {
+        let message = registration::certificate_message(moderator_id, &public_key_bytes(&public_key));
+        let signature = nizk::schnorr_prove(&CERTIFICATE, &self.k_p, &RistrettoPoint::mul_base(&self.k_p), &message, rng);
 
-        (self.k_p.clone(), self.k_reg.clone())
+        Certificate { moderator_id, public_key, signature }
     }
 
-    pub fn process(_k_p: &Option<Vec<u8>>, ks: &Vec<([u8; 64], Point)>, _c1: &Vec<u8>, c2: &Vec<u8>, ad: u32, ctx: &Vec<u8>) -> (Ciphertext, (Vec<u8>, u32)) {
-        let moderator_id: usize = ad.try_into().unwrap();
-        let (mac_key_i, mod_pk_i) = &ks[moderator_id];
+    pub fn process<R: CryptoRng + RngCore>(&mut self, _c1: &[u8], c2: &[u8], ad: u32, ctx: &ReportContext, rng: &mut R) -> Result<(Ciphertext, ProcessState), ReportingError> {
+        let moderator_id = usize::try_from(ad).map_err(|_| ReportingError::MalformedEncoding)?;
+        let slot = self.sk_p.get(moderator_id).ok_or(ReportingError::UnknownModerator(ad))?;
+        let (tag_key_i, (mod_pk_i, tag_pk_i, epoch)) = slot.as_ref().ok_or(ReportingError::RevokedModerator(ad))?;
+        let (mod_pk_i, tag_pk_i) = (gamal::decompress(mod_pk_i)?, gamal::decompress(tag_pk_i)?);
 
-        let mut sigma_pt = mac_64_sign(&mac_key_i, &[&c2[..], &ctx[..]].concat());
+        // Only commitments that get a tag take up room in the replay cache
+        let ctx = self.replay.admit(ctx, c2)?;
+        let h = tag_base(c2, &ctx)?;
+        let sigma_point = Secret::new(**tag_key_i * h);
 
-        // Point encrypt
-        let sigma_point = RistrettoPoint::from_uniform_bytes(&sigma_pt);
-        let (u, v) = gamal::elgamal_enc(&mod_pk_i.decompress().unwrap(), &sigma_point);
+        // Point encrypt, keeping r for the proof
+        let r = Secret::new(Scalar::random(rng));
+        let u = RistrettoPoint::mul_base(&r);
+        let v = *r * mod_pk_i + *sigma_point;
 
+        let proof = nizk::encryption_prove(&TAG_PROOF, &r, tag_key_i, &mod_pk_i, &tag_pk_i, &h, &u, &v, rng);
 
-        ((u.compress(), v.compress()), (ctx.to_vec(), ad))
+        Ok(((u.compress(), v.compress()), ProcessState { ctx, moderator_id: ad, epoch: *epoch, proof }))
     }
 
 }
 
+impl Default for Platform {
+    fn default() -> Platform {
+        Platform::new()
+    }
+}
+
+fn sign_revocations<R: CryptoRng + RngCore>(k_p: &Scalar, version: u32, revoked: Vec<u32>, rng: &mut R) -> RevocationList {
+    let message = registration::revocation_message(version, &revoked);
+    let signature = nizk::schnorr_prove(&REVOCATION, k_p, &RistrettoPoint::mul_base(k_p), &message, rng);
+
+    RevocationList { version, revoked, signature }
+}
+
 
 // Client Properties
 pub struct Client {
-    pub msg_key: Key<Aes256Gcm> // Symmetric key used to encrypt messages between sender and
+    msg_key: Secret<Key<Aes256Gcm>>, // Symmetric key used to encrypt messages between sender and
+    revocations: Revocations // Newest revocation list verified by this client
 }
 
 // Client Implementation
 impl Client {
     pub fn new() -> Client {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
         Client {
-            msg_key: Aes256Gcm::generate_key(aes_gcm::aead::OsRng)
+            msg_key: Secret::new(Aes256Gcm::generate_key(rng)),
+            revocations: Revocations::default()
         }
     }
 
-    pub fn ccae_enc(msg_key: &Key<Aes256Gcm>, message: &str) -> (Vec<u8>, Vec<u8>) {
-        let k_f: [u8; 32] = mac_keygen(); // franking key or r in H(m, r) for committment
-        
-        let c2 = com_commit(&k_f, message);
+    // Accepts the revocation list of the platform with registration key k_reg
+    pub fn update_revocations(&mut self, list: &RevocationList, k_reg: &Point) -> Result<(), ReportingError> {
+        list.verify(k_reg)?;
 
-        let cipher = Aes256Gcm::new(&msg_key);
-        let nonce = Aes256Gcm::generate_nonce(&mut rand::rngs::OsRng);
-
-        let payload = bincode::serialize(&(message, k_f)).expect("");
-        let c1_obj = cipher.encrypt(&nonce, payload.as_slice()).unwrap();
-        let c1 = bincode::serialize::<(Vec<u8>, Vec<u8>)>(&(c1_obj, nonce.to_vec())).expect("");
+        self.revocations.update(list.version, &list.revoked)
+    }
 
-        (c1, c2)
+    pub fn revocations(&self) -> &Revocations {
+        &self.revocations
     }
 
-    pub fn ccae_dec(msg_key: &Key<Aes256Gcm>, c1: &Vec<u8>, c2: &Vec<u8>) -> (String, [u8; 32]) {
-        let c1_obj = bincode::deserialize::<(Vec<u8>, Vec<u8>)>(&c1).unwrap();
-        let ct = c1_obj.0;
-        let nonce = Nonce::from_slice(&c1_obj.1);
+    pub fn ccae_enc<R: CryptoRng + RngCore>(&self, message: &str, rng: &mut R) -> Result<(Vec<u8>, Vec<u8>), ReportingError> {
+        let k_f = Secret::new(mac_keygen_with_rng(rng)); // franking key or r in H(m, r) for committment
+        
+        let c2 = com_commit(&COMMITMENT, &k_f[..], message);
+
+        let mut payload = FieldWriter::new();
+        payload.bytes(message.as_bytes());
+        payload.bytes(&k_f[..]);
+        let payload = Secret::new(payload.finish()?);
+        let c1 = sym_enc(&self.msg_key, &payload, rng);
 
-        let cipher = Aes256Gcm::new(&msg_key);
-        let payload_bytes = cipher.decrypt(&nonce, ct.as_ref()).unwrap();
-        let payload = bincode::deserialize::<(&str, [u8; 32])>(&payload_bytes).unwrap();
+        Ok((c1, c2))
+    }
 
-        let (message, k_f) = payload;
+    pub fn ccae_dec(&self, c1: &[u8], c2: &[u8]) -> Result<(String, Secret<[u8; 32]>), ReportingError> {
+        let payload_bytes = Secret::new(sym_dec(&self.msg_key, c1)?);
+        let mut payload = FieldReader::new(&payload_bytes);
+        let message = payload.string()?;
+        let k_f = Secret::new(payload.array::<32>()?);
+        payload.finish()?;
 
         // Verify committment
-        assert!(com_open(&c2, message, &k_f));
+        if !com_open(&COMMITMENT, c2, &message, &k_f[..]) {
+            return Err(ReportingError::BadCommitment);
+        }
 
-        (message.to_string(), k_f)
+        Ok((message, k_f))
     }
 
-    pub fn send(msg_key: &Key<Aes256Gcm>, message: &str, moderator_id: u32) -> (Vec<u8>, Vec<u8>, u32) {
-        let (c1, c2) = Self::ccae_enc(msg_key, message);       
+    pub fn send<R: CryptoRng + RngCore>(&self, message: &str, moderator_id: u32, rng: &mut R) -> Result<SentMessage, ReportingError> {
+        self.revocations.check(moderator_id)?;
 
-        (c1, c2, moderator_id)
+        let (c1, c2) = self.ccae_enc(message, rng)?;       
+
+        Ok(SentMessage { c1, c2, moderator_id })
     }
     
-    pub fn read(msg_key: &Key<Aes256Gcm>, pks: &Vec<Point>, c1: &Vec<u8>, c2: &Vec<u8>, sigma: &Ciphertext, st: &(Vec<u8>, u32)) -> (String, u32, Report) {
-        let (ctx, ad) = st;
+    pub fn read(&self, pks: &[PublicKey], c1: &[u8], c2: &[u8], sigma: &Ciphertext, st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        let ProcessState { ctx, moderator_id: ad, epoch, proof } = st;
+        self.revocations.check(*ad)?;
+
+        let (message, k_f) = self.ccae_dec(c1, c2)?;
 
-        let (message, k_f) = Self::ccae_dec(msg_key, c1, c2);
+        // sigma must be encrypted under the key in the directory
+        let (mod_pk_i, tag_pk_i, pk_epoch) = pks.get(*ad as usize).ok_or(ReportingError::UnknownModerator(*ad))?;
+        registration::check_epoch(*epoch, *pk_epoch)?;
 
-        let mod_pk_i = pks[*ad as usize];
+        // Verify that sigma encrypts the tag of (c2, ctx) to the chosen moderator
+        let (u, v) = sigma;
+        let (u, v) = (gamal::decompress(u)?, gamal::decompress(v)?);
+        if !nizk::encryption_verify(&TAG_PROOF, &gamal::decompress(mod_pk_i)?, &gamal::decompress(tag_pk_i)?, &tag_base(c2, ctx)?, &u, &v, proof) {
+            return Err(ReportingError::BadProof);
+        }
 
-        let rd: Report = (k_f, c2.clone(), ctx.clone(), sigma.clone());
+        let rd = ReportDoc { opening: Opening::Message(k_f), c2: c2.to_vec(), ctx: *ctx, sigma: *sigma, epoch: *epoch };
 
 
-        (message, *ad, rd)
+        Ok((message, *ad, rd))
     }
 
-    pub fn report_gen(_msg: &String, rd: &Report) -> Report {
+    pub fn report_gen(_msg: &str, rd: &ReportDoc) -> Report {
         let report = rd;
 
         report.clone()
     }
+
+    // Report revealing only the segments reveal of msg (lib_merkle.rs)
+    pub fn report_gen_partial(msg: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        Ok(Report { opening: rd.opening.disclose(&COMMITMENT, msg, reveal)?, ..rd.clone() })
+    }
 }
 
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
 
-// SetupPlatform(1^lambda)
-pub fn test_basic_setup_platform() -> Platform {
-    let platform = Platform::new();
 
-    platform
-}
+// Basic scheme: the platform encrypts its tag to the chosen moderator
+pub struct Basic;
+
+impl ReportingScheme for Basic {
+    const NAME: &'static str = "Basic Scheme";
 
-// SetupMod(pk_reg, 1^lambda)
-pub fn test_basic_setup_mod(platform: &mut Platform, num_moderators: usize) -> (Vec<Moderator>, Vec<Point>) {
-    let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
-    let mut pks: Vec<Point> = Vec::with_capacity(num_moderators);
+    type Platform = Platform;
+    type Moderator = Moderator;
+    type Client = Client;
+    type PublicKey = PublicKey;
+    type Ciphertext = SentMessage;
+    type Sigma = Ciphertext;
+    type ProcessState = ProcessState;
+    type ReportDoc = ReportDoc;
+    type Report = Report;
 
-    for _i in 0..num_moderators {
-        let moderator = Moderator::new(&platform.k_reg);
-        platform.sk_p.push((moderator.sk_p.clone(), moderator.pk_enc.clone()));
-        pks.push(moderator.pk_enc.clone());
-        moderators.push(moderator);
+    fn setup_platform_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
+        Platform::new_with_rng(rng)
     }
 
+    fn setup_mod_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, num_moderators: usize, rng: &mut R) -> (Vec<Moderator>, Vec<PublicKey>) {
+        let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
+        let mut pks: Vec<PublicKey> = Vec::with_capacity(num_moderators);
 
-    (moderators, pks)
-}
+        for _i in 0..num_moderators {
+            let pending = PendingModerator::new_with_rng(&platform.k_reg, rng);
+            let response = platform.register_with_rng(&pending.request_with_rng(rng), rng).expect("registration of an honest moderator");
+            let moderator = pending.finish(response).expect("certificate of an honest platform");
+            pks.push(moderator.public_key());
+            moderators.push(moderator);
+        }
 
+        (moderators, pks)
+    }
 
-pub fn test_setup() -> (Vec<Platform>, Vec<Vec<Moderator>>, Vec<Vec<Point>>) {
-    let n: usize = usize::try_from(MOD_SCALE.len()).unwrap();
-    let mut platforms: Vec<Platform> = Vec::with_capacity(n);
-    
-    for _i in 0..n {
-        platforms.push(Platform::new());
+    fn init_client_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
+        Client::new_with_rng(rng)
     }
-    
-    let mut moderators: Vec<Vec<Moderator>> = Vec::with_capacity(n);
-    let mut pubs: Vec<Vec<Point>> = Vec::new();
 
-    for (i, num_moderators) in MOD_SCALE.iter().enumerate() {
-        let k = usize::try_from(*num_moderators).unwrap();
-        let (mods, pks) = test_basic_setup_mod(&mut platforms[i], k);
-        moderators.push(mods);
-        pubs.push(pks);
+    fn send_with_rng<R: CryptoRng + RngCore>(client: &Client, message: &str, moderator_id: u32, _pks: &[PublicKey], rng: &mut R) -> Result<SentMessage, ReportingError> {
+        client.send(message, moderator_id, rng)
     }
 
-    (platforms, moderators, pubs)
-}
+    fn process_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, ct: &SentMessage, ctx: &ReportContext, rng: &mut R) -> Result<(Ciphertext, ProcessState), ReportingError> {
+        platform.process(&ct.c1, &ct.c2, ct.moderator_id, ctx, rng)
+    }
 
-// Setup Clients
-pub fn test_basic_init_clients(num_clients: usize) -> Vec<Client> {
-    let mut clients: Vec<Client> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let client = Client::new();
-        clients.push(client);
+    fn read(client: &Client, pks: &[PublicKey], ct: &SentMessage, sigma: &Ciphertext, st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        client.read(pks, &ct.c1, &ct.c2, sigma, st)
     }
 
-    clients
-}
-// Setup Messages
-pub fn test_basic_init_messages(num_clients: usize, msg_size: usize) -> Vec<String> {
-    // Prepare messages
-    let mut ms: Vec<String> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let m = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        ms.push(m);
+    fn report_gen(message: &str, rd: &ReportDoc) -> Result<Report, ReportingError> {
+        Ok(Client::report_gen(message, rd))
     }
-    ms
-}
 
-// send(k, m, pk_i)
-pub fn test_basic_send(num_clients: usize, num_moderators: usize, clients: &Vec<Client>, ms: &Vec<String>, print: bool) -> Vec<(Vec<u8>, Vec<u8>, u32)> {
+    fn report_gen_partial(message: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        Client::report_gen_partial(message, rd, reveal)
+    }
 
-    let mut c1c2ad: Vec<(Vec<u8>, Vec<u8>, u32)> = Vec::with_capacity(num_clients);
-    // send message i to client i to be moderated by random mod
-    let mut rng = thread_rng();
-    for i in 0..num_clients {
-        let mod_i = rng.gen_range(0..num_moderators);
-        let (c1, c2, ad) = Client::send(&clients[i].msg_key, &ms[i], mod_i.try_into().unwrap());
+    fn moderate(moderator: &Moderator, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        moderator.moderate(message, report, policy)
+    }
 
-        if print {
-            // Additional Costs
-            // (1) Commitment randomness (32 bytes)
-            // (2) Commitment to the Message
-            // (3) Moderator id
-            let mut send_cost: usize = 32 + mem::size_of_val(&*c2) + mem::size_of_val(&ad);
+    // (1) Commitment randomness (32 bytes)
+    // (2) Commitment to the Message
+    // (3) Moderator id
+    fn send_cost(ct: &SentMessage) -> usize {
+        32 + mem::size_of_val(&*ct.c2) + mem::size_of_val(&ct.moderator_id)
+    }
 
-            println!("Sending communication cost: {} (bytes)", &send_cost);
-        }
-        c1c2ad.push((c1, c2, ad));
+    // (1) Moderator id
+    // (2) opening of the commitment
+    // (3) commitment
+    // (4) sigma
+    // (5) proof that sigma is well-formed
+    // (6) key epoch
+    // (7) message id
+    // (8) sequence number
+    fn receive_cost(_sigma: &Ciphertext, st: &ProcessState, rd: &ReportDoc) -> usize {
+        let ReportDoc { opening, c2, sigma: (u, v), .. } = rd;
+
+        mem::size_of_val(&st.moderator_id) + opening.cost() + mem::size_of_val(&**c2) + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(&st.proof) + mem::size_of_val(&st.epoch) + mem::size_of_val(&st.ctx.message_id) + mem::size_of_val(&st.ctx.sequence)
     }
 
-    c1c2ad
+    // (1) Moderator id (4 bytes)
+    // (2) opening of the commitment
+    // (3) commitment
+    // (4) sigma
+    // (5) key epoch
+    // (6) message id
+    // (7) sequence number
+    fn storage_cost(rd: &ReportDoc) -> usize {
+        let ReportDoc { opening, c2, sigma: (u, v), epoch, ctx } = rd;
+
+        4 + opening.cost() + mem::size_of_val(&**c2) + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(epoch) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
+
+    // (1) opening of the commitment (k_f, 32 bytes, or the disclosed segments)
+    // (2) commitment
+    // (3) Moderator id (4 bytes)
+    // (4) sigma
+    // (5) key epoch
+    // (6) message id
+    // (7) sequence number
+    fn moderation_cost(report: &Report) -> usize {
+        let Report { opening, c2, sigma: (u, v), epoch, ctx } = report;
+
+        opening.cost() + mem::size_of_val(&**c2) + 4 + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(epoch) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::lib_scheme::Fixture;
+    use crate::lib_wire::check_encoding;
+    use crate::lib_verdict::check_signed_verdict;
+    use crate::lib_replay::{PER_CONVERSATION, MAX_CONVERSATIONS};
+    use crate::lib_window::{window_gen, moderate_window};
+
+    #[test]
+    fn tampered_tag_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<Basic>::new(&mut rng);
+
+        // The tag is bound to the context
+        let mut report = f.report.clone();
+        report.ctx.sequence += 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+
+        // Encryption of a point chosen without sk_p
+        let pk_enc = gamal::decompress(&f.pks[0].0).unwrap();
+        let (u, v) = gamal::elgamal_enc_with_rng(&pk_enc, &RistrettoPoint::random(&mut rng), &mut rng);
+        let mut report = f.report.clone();
+        report.sigma = (u.compress(), v.compress());
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+    }
 
-// Send messages of sizes in MSG_SIZE_SCALE
-// to platforms with num moderators in MOD_SCALE
-pub fn test_send_variable(clients: &Vec<Client>, ms: &Vec<Vec<String>>) -> 
-Vec<Vec<Vec<(Vec<u8>, Vec<u8>, u32)>>> {
-    // Send messages
-    let mut c1c2ad: Vec<Vec<Vec<(Vec<u8>, Vec<u8>, u32)>>> = Vec::new();
-    // c1c2ad[i][j] = Encryption of message j to moderator i
-    for (_i, num_moderators) in MOD_SCALE.iter().enumerate() {
-        let mut tmp: Vec<Vec<(Vec<u8>, Vec<u8>, u32)>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-        for (j, _msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
-            tmp.push(test_basic_send(1, *num_moderators, clients, &ms[j], false));
-        }
-        c1c2ad.push(tmp);
+    #[test]
+    fn read_checks_sigma_proof() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let f = Fixture::<Basic>::new(&mut rng);
+
+        // sigma encrypts the tag of another context
+        let mut st = f.st.clone();
+        st.ctx.sequence += 1;
+        assert_eq!(f.read(&f.sigma, &st).err(), Some(ReportingError::BadProof));
+
+        // sigma is re-randomized without a new proof
+        let pk_enc = gamal::decompress(&f.pks[0].0).unwrap();
+        let (u, v) = gamal::elgamal_enc_with_rng(&pk_enc, &RistrettoPoint::identity(), &mut rng);
+        let sigma = ((gamal::decompress(&f.sigma.0).unwrap() + u).compress(), (gamal::decompress(&f.sigma.1).unwrap() + v).compress());
+        assert_eq!(f.read(&sigma, &f.st).err(), Some(ReportingError::BadProof));
     }
 
-    c1c2ad
-}
+    #[test]
+    fn undecryptable_report_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let f = Fixture::<Basic>::new(&mut rng);
+
+        let mut report = f.report.clone();
+        report.epoch = 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::UnknownEpoch(1)));
 
-// process(k_p, ks, c1, c2, ad, ctx)
-pub fn test_basic_process(num_clients: usize, msg_size: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, u32)>, platform: &Platform, print: bool) -> Vec<(Ciphertext, (Vec<u8>, u32))> {
+        let mut report = f.report.clone();
+        report.sigma.1 = CompressedRistretto([0xff; 32]);
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::InvalidCurvePoint));
 
-    let mut sigma_st: Vec<(Ciphertext, (Vec<u8>, u32))> = Vec::with_capacity(num_clients);
-    // Platform processes message
-    for i in 0..num_clients {
-        let (c1, c2, ad) = &c1c2ad[i];
-        let ctx = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        let (sigma, st) = Platform::process(&platform.k_p, &platform.sk_p, &c1, &c2, *ad, &(ctx.as_bytes().to_vec()));
+        // and does not abort the window it is part of
+        let mut window = window_gen::<Basic>(&[(f.message.clone(), f.rd.clone())]).unwrap();
+        window.reports[0] = report;
+        let verdict = moderate_window::<Basic>(&f.moderators[0], &window, &ExpiryPolicy::default()).unwrap();
+        assert_eq!(verdict.window, Verdict::Reject(ReportingError::InvalidCurvePoint));
+    }
 
+    #[test]
+    fn registration_checks_proof_of_possession() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut platform = Platform::new_with_rng(&mut rng);
+        let pending = PendingModerator::new_with_rng(&platform.k_reg, &mut rng);
+        let other = PendingModerator::new_with_rng(&platform.k_reg, &mut rng);
 
-        sigma_st.push((sigma, st));
+        // A proof for other keys
+        let request = RegistrationRequest { pop: other.request_with_rng(&mut rng).pop, ..pending.request_with_rng(&mut rng) };
+        assert_eq!(platform.register_with_rng(&request, &mut rng).err(), Some(ReportingError::BadProof));
+
+        // A proof made for another platform
+        let elsewhere = PendingModerator::new_with_rng(&Platform::new_with_rng(&mut rng).k_reg, &mut rng);
+        assert_eq!(platform.register_with_rng(&elsewhere.request_with_rng(&mut rng), &mut rng).err(), Some(ReportingError::BadProof));
     }
 
-    sigma_st
-}
+    #[test]
+    fn revoked_moderator_is_refused() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut f = Fixture::<Basic>::new(&mut rng);
+        let ct = Basic::send_with_rng(&f.client, "m", 0, &f.pks, &mut rng).unwrap();
+        let list = f.platform.revoke_with_rng(0, &mut rng).unwrap();
 
+        let result = Basic::process_with_rng(&mut f.platform, &ct, &ReportContext::new(1, 2, 3), &mut rng);
+        assert_eq!(result.err(), Some(ReportingError::RevokedModerator(0)));
+
+        f.client.update_revocations(&list, &f.platform.k_reg).unwrap();
+        assert_eq!(Basic::send_with_rng(&f.client, "m", 0, &f.pks, &mut rng).err(), Some(ReportingError::RevokedModerator(0)));
+        assert_eq!(f.read(&f.sigma, &f.st).err(), Some(ReportingError::RevokedModerator(0)));
+    }
+
+    #[test]
+    fn expired_report_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut f = Fixture::<Basic>::new(&mut rng);
+        let later = ExpiryPolicy::with_clock(10, || u64::MAX);
+
+        // The platform stamps the time of its own clock
+        f.platform.replay = ReplayCache::with_clock(PER_CONVERSATION, MAX_CONVERSATIONS, || 1000).unwrap();
+        let (sigma, st) = Basic::process_with_rng(&mut f.platform, &f.ct, &ReportContext::new(1, 2, 4), &mut rng).unwrap();
+        let (message, _, rd) = f.read(&sigma, &st).unwrap();
+        let report = Basic::report_gen(&message, &rd).unwrap();
+        assert_eq!(report.ctx.timestamp, 1000);
+
+        assert_eq!(f.moderators[0].moderate(&message, &report, &later).unwrap(), Verdict::Reject(ReportingError::ExpiredReport(1000)));
+        assert!(f.moderators[0].moderate(&message, &report, &ExpiryPolicy::with_clock(10, || 1010)).unwrap().is_accept());
+
+        // A forged report is not reported as expired
+        let mut forged = report.clone();
+        forged.ctx.timestamp = 0;
+        assert_eq!(f.moderators[0].moderate(&message, &forged, &later).unwrap(), Verdict::Reject(ReportingError::BadPlatformTag));
+    }
 
-// Process messages of sizes in MSG_SIZE_SCALE
-// and encrypt them to moderators in MOD_SCALE
-pub fn test_process_variable(moderators: &Vec<Vec<Moderator>>, c1c2ad: &Vec<Vec<Vec<(Vec<u8>, Vec<u8>, u32)>>>, platforms: &Vec<Platform>) -> Vec<Vec<Vec<(Ciphertext, (Vec<u8>, u32))>>> {
-    // Process messages
-    let mut sigma_st: Vec<Vec<Vec<(Ciphertext, (Vec<u8>, u32))>>> = Vec::new();
-    // sigma_st[i][j] = encrypted signature on message commitmment j to moderator i
-    for i in 0..moderators.len() {
-        let mut tmp: Vec<Vec<(Ciphertext, (Vec<u8>, u32))>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-        for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
-            tmp.push(test_basic_process(1, *msg_size, &c1c2ad[i][j], &platforms[i], false));
+    #[test]
+    fn expired_epoch_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut f = Fixture::<Basic>::new(&mut rng);
+
+        // The epoch 0 key is kept for one rotation, then dropped
+        for epoch in 1..=2 {
+            let (pending, request) = f.moderators[0].rotate_with_rng(&mut rng);
+            let certificate = f.platform.rotate_with_rng(&request, &mut rng).unwrap();
+            f.moderators[0].finish_rotation(pending, certificate).unwrap();
+            assert_eq!(f.moderators[0].epoch(), epoch);
         }
-        sigma_st.push(tmp);
+        assert_eq!(f.moderate(&f.report), Verdict::Reject(ReportingError::ExpiredEpoch(0)));
+
+        // Receivers with the new directory entry refuse messages under the old key
+        f.pks[0] = f.moderators[0].public_key();
+        assert_eq!(f.read(&f.sigma, &f.st).err(), Some(ReportingError::ExpiredEpoch(0)));
     }
 
-    sigma_st
-}
+    // A report to a committee of n = 3 with t = 2, dealt or generated with the DKG
+    fn committee_report(dkg: bool, rng: &mut StdRng) -> (Vec<CommitteeMember>, String, Report) {
+        let mut platform = Platform::new_with_rng(rng);
+        let committee = match dkg {
+            true => CommitteeMember::new_committee_dkg_with_rng(2, 3, &mut platform, rng),
+            false => CommitteeMember::new_committee_with_rng(2, 3, &mut platform, rng)
+        }.unwrap();
+        let pks = vec![committee[0].public_key()];
+        let client = Client::new_with_rng(rng);
+
+        let ct = Basic::send_with_rng(&client, "m", 0, &pks, rng).unwrap();
+        let (sigma, st) = Basic::process_with_rng(&mut platform, &ct, &ReportContext::new(1, 2, 3), rng).unwrap();
+        let (message, _, rd) = Basic::read(&client, &pks, &ct, &sigma, &st).unwrap();
+        let report = Basic::report_gen(&message, &rd).unwrap();
+
+        (committee, message, report)
+    }
 
-// read(k, pks, c1, c2, sigma, st)
-pub fn test_basic_read(num_clients: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, u32)>, sigma_st: &Vec<(Ciphertext, (Vec<u8>, u32))>, clients: &Vec<Client>, pks: &Vec<Point>, print: bool) -> Vec<(String, u32, Report)> {
-    
-    // Receive messages
-    let mut rds: Vec<(String, u32, Report)> = Vec::with_capacity(num_clients);
-    // Receive message i from client i to be moderated by randomly selected moderator mod_i
-    for i in 0..num_clients {
-        let (c1, c2, _ad) = &c1c2ad[i];
-        let (sigma, st) = &sigma_st[i];
-
-        let (message, ad, report) = Client::read(&clients[i].msg_key, &pks, &c1, &c2, &sigma, &st);
-
-        if print {
-            let (k_f, c2, _ctx, ct): ([u8; 32], Vec<u8>, Vec<u8>, Ciphertext) = report.clone();
-            let (u, v) = ct.clone();
-            // Receiving cost is the inputs to read
-            // (1) Moderator id
-            // (2) randomness for commitment
-            // (3) commitment 
-            // (4) sigma
-            let mut recv_cost: usize = mem::size_of_val(&ad) + mem::size_of_val(&k_f) + mem::size_of_val(&*c2) + mem::size_of_val(&u) + mem::size_of_val(&v);
-            println!("Receiving communication cost: {} (bytes)", &recv_cost);
-
-            // Storage cost is output of read
-            // (1) Moderator id
-            // (2) randomness for commitment
-            // (3) commitment 
-            // (4) sigma
-            let mut storage_cost: usize = mem::size_of_val(&ad) + mem::size_of_val(&k_f) + mem::size_of_val(&*c2) + mem::size_of_val(&u) + mem::size_of_val(&v);
-            println!("Storage communication cost: {} (bytes)", &storage_cost);
+    #[test]
+    fn committee_needs_threshold_of_shares() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for dkg in [false, true] {
+            let (committee, message, report) = committee_report(dkg, &mut rng);
+            let shares: Vec<_> = committee.iter().map(|member| member.partial_decrypt(&report, &mut rng).unwrap()).collect();
+            let moderate = |shares: &[PartialDecryption]| committee[0].moderate(&message, &report, shares, &ExpiryPolicy::default()).unwrap();
+
+            assert!(moderate(&shares[..2]).is_accept());
+            assert!(moderate(&shares[1..]).is_accept());
+            assert_eq!(moderate(&shares[..1]), Verdict::Reject(ReportingError::InsufficientShares(1)));
         }
-        rds.push((message, ad, report));
     }
 
-    rds
-}
+    #[test]
+    fn committee_ignores_invalid_shares() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let (committee, message, report) = committee_report(false, &mut rng);
+        let shares: Vec<_> = committee.iter().map(|member| member.partial_decrypt(&report, &mut rng).unwrap()).collect();
+        let moderate = |shares: &[PartialDecryption]| committee[0].moderate(&message, &report, shares, &ExpiryPolicy::default()).unwrap();
+
+        // Only the first share is valid: a wrong proof, a repeated index and
+        // indices outside 1..=n do not make up the second one
+        let bad_proof = PartialDecryption { proof: shares[0].proof, ..shares[1].clone() };
+        let invalid = [
+            bad_proof,
+            shares[0].clone(),
+            PartialDecryption { index: 0, ..shares[1].clone() },
+            PartialDecryption { index: 4, ..shares[1].clone() }
+        ];
+        assert_eq!(moderate(&[&shares[..1], &invalid[..]].concat()), Verdict::Reject(ReportingError::InsufficientShares(1)));
+        assert!(moderate(&[&shares[..1], &invalid[..], &shares[2..]].concat()).is_accept());
+    }
 
-pub fn test_report(num_clients: usize, rds: &Vec<(String, u32, Report)>, print: bool) -> Vec<(String, u32, Report)> {
-    let mut reports: Vec<(String, u32, Report)> = Vec::with_capacity(num_clients);
+    #[test]
+    fn committee_stays_at_epoch_zero() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let (committee, message, mut report) = committee_report(false, &mut rng);
+        assert_eq!(report.epoch, 0);
 
-    for i in 0..num_clients {
-        let (msg, mod_id, rd) = &rds[i];
-        let report = Client::report_gen(&msg, &rd);
+        report.epoch = 1;
+        let shares: Vec<_> = committee.iter().map(|member| member.partial_decrypt(&report, &mut rng).unwrap()).collect();
+        let verdict = committee[0].moderate(&message, &report, &shares, &ExpiryPolicy::unlimited()).unwrap();
+        assert_eq!(verdict, Verdict::Reject(ReportingError::UnknownEpoch(1)));
+    }
 
+    #[test]
+    fn non_adjacent_window_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(10);
+        let mut f = Fixture::<Basic>::new(&mut rng);
+
+        // Sequences 1, 2 and 3 of conversation 5, and 4 of conversation 6 (a
+        // conversation starts at the message id, the fixture took id 0)
+        let mut docs = Vec::new();
+        for conversation in [5, 5, 5, 6] {
+            let ct = Basic::send_with_rng(&f.client, "m", 0, &f.pks, &mut rng).unwrap();
+            let (sigma, st) = Basic::process_with_rng(&mut f.platform, &ct, &ReportContext::new(1, 2, conversation), &mut rng).unwrap();
+            let (message, _, rd) = Basic::read(&f.client, &f.pks, &ct, &sigma, &st).unwrap();
+            docs.push((message, rd));
+        }
+        let moderate = |window: &[usize]| {
+            let window: Vec<_> = window.iter().map(|i| docs[*i].clone()).collect();
+            moderate_window::<Basic>(&f.moderators[0], &window_gen::<Basic>(&window).unwrap(), &ExpiryPolicy::default()).unwrap().window
+        };
+
+        assert!(moderate(&[0, 1, 2]).is_accept());
+        assert_eq!(moderate(&[0, 2]), Verdict::Reject(ReportingError::NotAdjacent(3)));
+        assert_eq!(moderate(&[1, 0]), Verdict::Reject(ReportingError::NotAdjacent(1)));
+        assert_eq!(moderate(&[2, 3]), Verdict::Reject(ReportingError::NotAdjacent(4)));
+    }
 
-        reports.push((msg.clone(), *mod_id, report));
+    #[test]
+    fn process_rejects_unknown_moderator() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut f = Fixture::<Basic>::new(&mut rng);
+
+        let ct = SentMessage { moderator_id: 1, ..Basic::send_with_rng(&f.client, "m", 0, &f.pks, &mut rng).unwrap() };
+        let result = Basic::process_with_rng(&mut f.platform, &ct, &ReportContext::new(1, 2, 3), &mut rng);
+        assert_eq!(result.err(), Some(ReportingError::UnknownModerator(1)));
     }
 
-    reports
-}
+    #[test]
+    fn wire_encoding_round_trips() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<Basic>::new(&mut rng);
 
-// moderate(sk_mod, sk_p, m, report)
-pub fn test_basic_moderate(num_clients: usize, reports: &Vec<(String, u32, ([u8; 32], Vec<u8>, Vec<u8>, Ciphertext))>, moderators: &Vec<Moderator>, print: bool) {
-    // Moderate messages
-    for i in 0..num_clients {
-        let (message, ad, report) = &reports[i];
-        let ad = usize::try_from(*ad).unwrap();
-        let ctx = Moderator::moderate(&moderators[ad].sk_enc, &moderators[ad].sk_p, &message, &report);
-        if print {
-            // Moderation communication cost is inputs to moderate
-            // (1) commitment randomness (32 bytes)
-            // (2) commitment
-            // (3) Moderator id
-            // (4) sigma
-            let (k_f, c2, ctx, sigma) = report.clone();
-            let (u, v) = sigma.clone();
-            let judging_cost: usize = 32 + mem::size_of_val(&ad) + mem::size_of_val(&c2) + mem::size_of_val(&u) + mem::size_of_val(&v);
-            println!("Judging communication cost: {} (bytes)", &judging_cost);
-        }
+        check_encoding(&f.ct);
+        check_encoding(&(f.sigma, f.st.clone()));
+        check_encoding(&f.rd); // Same type as the report
+        check_encoding(&f.report);
     }
 
-}
+    #[test]
+    fn signed_verdicts_verify() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let mut f = Fixture::<Basic>::new(&mut rng);
+        let policy = ExpiryPolicy::default();
 
+        let accepted = f.moderators[0].moderate_signed_with_rng(&f.message, &f.report, &policy, &mut rng).unwrap();
+        assert!(accepted.verdict.is_accept());
+        check_signed_verdict(&accepted, |sv| verify_verdict(&f.pks[0], &f.message, &f.report, sv));
+
+        let rejected = f.moderators[0].moderate_signed_with_rng("other", &f.report, &policy, &mut rng).unwrap();
+        assert_eq!(rejected.verdict, Verdict::Reject(ReportingError::BadCommitment));
+        check_signed_verdict(&rejected, |sv| verify_verdict(&f.pks[0], "other", &f.report, sv));
+
+        // Signed by the key of another moderator
+        let (_moderators, pks) = Basic::setup_mod_with_rng(&mut f.platform, 1, &mut rng);
+        assert_eq!(verify_verdict(&pks[0], &f.message, &f.report, &accepted), Err(ReportingError::BadProof));
+    }
+}
@@ -1,35 +1,54 @@
 use hmac::{Hmac, Mac};
 use sha2::{Sha256, Sha512, Digest};
 use rand::rngs::OsRng;
-type HmacSha256 = Hmac<Sha256>;
+use rand::CryptoRng;
+pub(crate) type HmacSha256 = Hmac<Sha256>;
 type HmacSha512 = Hmac<Sha512>;
+#[cfg(feature = "constant_mod_priv")]
 use blstrs as blstrs;
-use blstrs::Scalar;
-use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+use subtle::ConstantTimeEq;
 
+#[cfg(feature = "constant_mod_priv")]
 use rand_chacha::ChaCha20Rng;
-use rand_chacha::rand_core::SeedableRng;
-use rand_chacha::rand_core::RngCore;
+#[cfg(feature = "constant_mod_priv")]
+use rand_chacha::rand_core::{SeedableRng, RngCore};
+#[cfg(feature = "constant_mod_priv")]
 use ff::PrimeField;
-
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit},
+    Aes256Gcm, Nonce, Key
+};
+use crate::lib_error::ReportingError;
+use crate::lib_domain::Domain;
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+use crate::lib_merkle as merkle;
+use crate::lib_context::ReportContext;
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "constant_mod_priv")]
 const REPR_SHAVE_BITS: usize = 256 - blstrs::Scalar::NUM_BITS as usize;
 
-pub const CTX_LEN: usize = 100;
-pub const CTX_STR: &str = "Lorem ipsum dolor sit amet, consectetuer adipiscing elit. Aenean commodo ligula eget dolor. Aenean m";
-pub const CTX: [u8; CTX_LEN] = *b"Lorem ipsum dolor sit amet, consectetuer adipiscing elit. Aenean commodo ligula eget dolor. Aenean m";
+// Context of the messages processed by the benchmarks
+pub const CTX: ReportContext = ReportContext { sender: 0, recipient: 1, conversation: 0, timestamp: 0, message_id: 0, sequence: 0 };
 pub const MOD_SCALE: [usize; 13] = [1, 2, 64, 100, 200, 300, 400, 500, 600, 700, 800, 900, 1000];
+// RSA key generation is too slow to set up every MOD_SCALE platform, the RSA
+// benches stop at 100 moderators
+pub const RSA_MOD_SCALE: [usize; 4] = [1, 2, 64, 100];
 pub const MSG_SIZE_SCALE: [usize; 1] = [100];
 
 // Hash Sha256
-pub fn hash(X: &Vec<u8>) -> Vec<u8> {
+pub fn hash(x: &[u8]) -> Vec<u8> {
     let mut hasher = sha2::Sha256::new();
-    hasher.update(X);
+    hasher.update(x);
     let res = hasher.finalize();
 
     res.to_vec()
 }
 
 // Method for generating blstrs::Scalar from seed
+#[cfg(feature = "constant_mod_priv")]
 pub fn new_blstrs_scalar(seed: [u8; 32]) -> blstrs::Scalar {
         // Generate randomness for blstrs::Scalar
         let mut rng = ChaCha20Rng::from_seed(seed);
@@ -42,87 +61,149 @@ pub fn new_blstrs_scalar(seed: [u8; 32]) -> blstrs::Scalar {
             // Mask away the unused most-significant bits.
             raw[3] &= 0xffffffffffffffff >> REPR_SHAVE_BITS;
 
-            if let Ok(Some(scalar)) = blstrs::Scalar::from_u64s_le(&raw).try_into() {
+            if let Some(scalar) = Option::from(blstrs::Scalar::from_u64s_le(&raw)) {
                 return scalar;
             }
         }
 }
 
 
-// Committment Scheme
-pub(crate) fn com_commit(r: &[u8], m: &str) -> Vec<u8> {
-    let mut com = <HmacSha256 as Mac>::new_from_slice(r).expect("");
-    com.update(m.as_bytes());
-    let out = com.finalize();
+// Constant-time equality of tags, commitments and point encodings
+// (only the lengths, which are public, may leak)
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub(crate) fn ct_eq_bytes(a: &[u8], b: &[u8]) -> bool {
+    bool::from(a.ct_eq(b))
+}
+
+
+// Mac keyed with k that has already absorbed len(label) || label
+pub(crate) fn domain_mac<M: Mac + hmac::digest::KeyInit>(domain: &Domain, k: &[u8]) -> M {
+    let mut mac = <M as Mac>::new_from_slice(k).expect("");
+    let label = domain.label();
+    mac.update(&(label.len() as u32).to_be_bytes());
+    mac.update(&label);
 
-    out.into_bytes().to_vec()
+    mac
 }
 
-pub(crate) fn com_open(c: &Vec<u8>, m: &str, r: &[u8]) -> bool {
-    let mut com = <HmacSha256 as Mac>::new_from_slice(r).expect("");
-    com.update(m.as_bytes());
-    let t = com.finalize();
 
-    t.into_bytes().to_vec() == *c
+// Committment Scheme, a Merkle tree over the segments of m (lib_merkle.rs)
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub(crate) fn com_commit(domain: &Domain, r: &[u8], m: &str) -> Vec<u8> {
+    merkle::commit(domain, r, m).to_vec()
+}
+
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub(crate) fn com_open(domain: &Domain, c: &[u8], m: &str, r: &[u8]) -> bool {
+    ct_eq_bytes(&merkle::commit(domain, r, m), c)
 }
 
 // Mac Scheme
 pub fn mac_keygen() -> [u8; 32] {
+    mac_keygen_with_rng(&mut OsRng)
+}
+
+pub fn mac_keygen_with_rng<R: CryptoRng + rand::RngCore>(rng: &mut R) -> [u8; 32] {
     let mut k: [u8; 32] = [0; 32];
-    let mut rng = ChaCha20Rng::from_os_rng();
     rng.fill_bytes(&mut k);
 
     k
 }
 
 pub fn mac_64_keygen() -> [u8; 64] {
+    mac_64_keygen_with_rng(&mut OsRng)
+}
+
+pub fn mac_64_keygen_with_rng<R: CryptoRng + rand::RngCore>(rng: &mut R) -> [u8; 64] {
     let mut k: [u8; 64] = [0; 64];
-    let mut rng = ChaCha20Rng::from_os_rng();
     rng.fill_bytes(&mut k);
 
     k
 }
 
-pub fn mac_sign(k: &[u8; 32], m: &Vec<u8>) -> Vec<u8> {
-    let mut mac = <HmacSha256 as Mac>::new_from_slice(k).expect("");
-    mac.update(&m);
-    let sigma = mac.finalize().into_bytes().to_vec();
+pub fn mac_sign(domain: &Domain, k: &[u8; 32], m: &[u8]) -> Vec<u8> {
+    let mut mac = domain_mac::<HmacSha256>(domain, k);
+    mac.update(m);
 
-    sigma
+    mac.finalize().into_bytes().to_vec()
 }
 
-pub fn mac_64_sign(k: &[u8; 64], m: &Vec<u8>) -> [u8; 64] {
-    let mut mac = <HmacSha512 as Mac>::new_from_slice(k).expect("");
-    mac.update(&m);
+pub fn mac_64_sign(domain: &Domain, k: &[u8; 64], m: &[u8]) -> [u8; 64] {
+    let mut mac = domain_mac::<HmacSha512>(domain, k);
+    mac.update(m);
     let sigma = mac.finalize().into_bytes();
 
     sigma.into()
 }
 
 
-pub(crate) fn mac_verify(k: &[u8; 32], m: &Vec<u8>, sigma: &Vec<u8>) -> bool {
-    let mut mac = <HmacSha256 as Mac>::new_from_slice(k).expect("");
-    mac.update(&m);
-    let t = mac.finalize().into_bytes().to_vec();
+#[cfg(any(feature = "plain", feature = "mod_priv", feature = "rsa"))]
+pub(crate) fn mac_verify(domain: &Domain, k: &[u8; 32], m: &[u8], sigma: &[u8]) -> bool {
+    let mut mac = domain_mac::<HmacSha256>(domain, k);
+    mac.update(m);
+    let t = mac.finalize().into_bytes();
+
+    ct_eq_bytes(&t, sigma)
+}
 
-    let valid = *sigma == t;
 
-    valid
+
+// Outcome of moderating a report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    Accept(ReportContext), // Report verified, carries the platform context
+    Reject(ReportingError) // Report did not verify, carries the failed check
+}
+
+impl Verdict {
+    pub fn is_accept(&self) -> bool {
+        matches!(self, Verdict::Accept(_))
+    }
 }
 
 
 
-// MAC prg
-pub const MAC_PRG_CONST_1: &str = "MAC_PRG_CONSTANT_1";
-pub const MAC_PRG_CONST_2: &str = "MAC_PRG_CONSTANT_2";
-pub(crate) fn mac_prg(seed: &[u8; 32]) -> [u8; 64] {
-    let s = mac_sign(seed, &MAC_PRG_CONST_1.as_bytes().to_vec());
-    let r = mac_sign(seed, &MAC_PRG_CONST_2.as_bytes().to_vec());
+// Symmetric encryption of a payload between sender and receiver
+// c1 = nonce (12 bytes) || AES-GCM ciphertext
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub(crate) fn sym_enc<R: CryptoRng + rand::RngCore>(msg_key: &Key<Aes256Gcm>, payload: &[u8], rng: &mut R) -> Vec<u8> {
+    let cipher = Aes256Gcm::new(msg_key);
+    let nonce = Aes256Gcm::generate_nonce(&mut *rng);
+
+    let c1_obj = cipher.encrypt(&nonce, payload).expect("");
+
+    [&nonce[..], &c1_obj[..]].concat()
+}
+
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub(crate) fn sym_dec(msg_key: &Key<Aes256Gcm>, c1: &[u8]) -> Result<Vec<u8>, ReportingError> {
+    if c1.len() < 12 {
+        return Err(ReportingError::MalformedEncoding);
+    }
+    let (nonce, ct) = c1.split_at(12);
+    let nonce = Nonce::from_slice(nonce);
+
+    let cipher = Aes256Gcm::new(msg_key);
+    cipher.decrypt(nonce, ct.as_ref()).map_err(|_| ReportingError::DecryptionFailure)
+}
+
+
 
+// Serde helper for fixed size byte arrays longer than 32 bytes
+// (used with #[serde(with = "byte_array")])
+#[cfg(feature = "constant_mod_priv")]
+pub(crate) mod byte_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
 
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
 
-    let mut ret: [u8; 64] = [0u8; 64];
-    ret.copy_from_slice(&[&s[..], &r[..]].concat());
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        let len = bytes.len();
 
-    ret
+        bytes.try_into().map_err(|_| D::Error::invalid_length(len, &"a fixed size byte array"))
+    }
 }
@@ -1,508 +1,1464 @@
 use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit},
-    Aes256Gcm, Nonce, Key
+    aead::KeyInit,
+    Aes256Gcm, Key
 };
 use std::mem;
-use bincode;
 use crate::lib_common::*;
 use crate::lib_gamal as gamal;
-use rand::distributions::Alphanumeric;
-use rand::thread_rng;
-use rand::distributions::DistString;
-use rand::Rng;
+use crate::lib_nizk::{self as nizk, DleqProof, SchnorrProof};
+use crate::lib_registration::{self as registration, Revocations, RetiredKeys};
+use crate::lib_replay::ReplayCache;
+use crate::lib_context::{ReportContext, ExpiryPolicy};
+use crate::lib_merkle::Opening;
+use crate::lib_verdict::{self as verdict, SignedVerdict};
+use crate::lib_error::ReportingError;
+use crate::lib_scheme::ReportingScheme;
+use crate::lib_secret::Secret;
+use crate::lib_wire::*;
+use crate::lib_domain::{Domain, Role, Usage};
 use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
 use curve25519_dalek::scalar::Scalar;
 use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 
-pub use blstrs::{G1Affine, G2Affine, Gt, Compress, GtCompressed};
-use group::{Curve, GroupEncoding};
+pub use blstrs::{G1Affine, G2Affine, Gt, Compress};
+use group::Curve;
 use group::prime::PrimeCurveAffine;
 use ff::Field;
-use serde::ser::Serialize;
+use serde::{Serialize, Deserialize};
+use subtle::ConstantTimeEq;
 
 
 type Point = CompressedRistretto;
-type PublicKey = (Point, Point, Scalar, G2Compressed);
+// (pk_enc_1, pk_enc_2, k1_2, pk_proc, k_reg the moderator registered under, epoch of the keys)
+pub type PublicKey = (Point, Point, Scalar, G2Compressed, G2Compressed, u32);
 type Ciphertext = (Point, Point);
-use generic_array::typenum::U12;
 
-type Report = (Vec<u8>, [u8; 32], Vec<u8>, blstrs::GtCompressed, Ciphertext);
-type ReportDoc = (Vec<u8>, [u8; 32], Vec<u8>, G1Compressed, G2Compressed, Scalar, Ciphertext);
-type State = (Ciphertext, Point, Vec<u8>);
-
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct G1Compressed {
+    #[serde(with = "byte_array")]
     point: [u8; 48]
 }
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct G2Compressed {
+    #[serde(with = "byte_array")]
     point: [u8; 96]
 }
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GtCompressed {
+    #[serde(with = "byte_array")]
+    point: [u8; 288]
+}
 
+// Domain separation labels (see lib_domain.rs)
+const COMMITMENT: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Sender, Usage::Commitment);
+const HASH_TO_CURVE: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Platform, Usage::HashToCurve);
+const C3_PROOF: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Platform, Usage::Proof);
+const VERDICT: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Moderator, Usage::Verdict);
+const POSSESSION: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Moderator, Usage::Registration);
+const CERTIFICATE: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Platform, Usage::Registration);
+const REVOCATION: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Platform, Usage::Revocation);
+const ROTATION: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Moderator, Usage::Rotation);
+const KEY_ROTATION: Domain = Domain::new(SCHEME_CONSTANT_MOD_PRIV, Role::Platform, Usage::Rotation);
+
+// Registration request (lib_registration.rs). pop proves possession of
+// sk_enc (log_G(pk_enc_2)) and pop_proc = H(msg)^k possession of k, checked
+// with e(pop_proc, k_reg) = e(H(msg), pk_proc)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationRequest {
+    pub public_key: PublicKey,
+    pub pop: SchnorrProof,
+    pub pop_proc: G1Compressed
+}
 
+// Key rotation request (lib_registration.rs), the keys of the next epoch
+// with the same proofs of possession as a registration, authorized with the
+// current sk_enc
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RotationRequest {
+    pub moderator_id: u32,
+    pub public_key: PublicKey,
+    pub pop: SchnorrProof,
+    pub pop_proc: G1Compressed,
+    pub authorization: SchnorrProof // Signature under the current pk_enc_2
+}
 
-// Moderator Properties
-pub struct Moderator {
-    pub sk_p: [u8; 32], // Mac Key shared with the Platform
-    pub sk_enc: Scalar, // Moderator private key
+// Platform signature on a moderator directory entry, the BLS signature
+// H(msg)^(1/k_p) verified with e(signature, g2) = e(H(msg), k_reg)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    pub moderator_id: u32,
+    pub public_key: PublicKey,
+    pub signature: G1Compressed
+}
+
+// Registration response, sent over a private channel
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationResponse {
+    pub sk_p: Secret<[u8; 32]>, // MAC key issued by the Platform
+    pub certificate: Certificate
+}
+
+// Revoked moderator ids published by the Platform
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub version: u32, // Number of revocations so far
+    pub revoked: Vec<u32>,
+    pub signature: G1Compressed // H(msg)^(1/k_p), verifies under k_reg
+}
+
+// New platform key published by Platform::rotate_key, endorsed by the
+// previous one, with the directory entries re-certified under it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub version: u32, // Key version, 0 for the key the platform was set up with
+    pub k_reg: G2Compressed, // New registration key
+    pub endorsement: G1Compressed, // Signature under the previous k_reg
+    pub certificates: Vec<Certificate> // Every entry not revoked, pk_proc derived from the new k_reg
+}
+
+// Output of send: (c1, c2, ad)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub c1: Vec<u8>, // Encryption of (m, moderator id, k_f, ke_2) under the shared message key
+    pub c2: Vec<u8>, // Commitment to the message
+    pub epk: Point // ad, masked public key of the chosen moderator
+}
+
+// State output by process alongside the platform signature
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessState {
+    pub c3: Ciphertext, // PRE encryption of r' under epk
+    pub epk: Point,
+    pub ctx: ReportContext, // Platform context, bound into sigma
+    pub r_prime: Point, // r', lets the receiver check sigma against k_reg
+    pub proof: DleqProof, // c3 encrypts r' under epk
+    pub sigma_previous: Option<G1Compressed> // sigma under the previous key while a key rotation is in progress
+}
+
+// Report doc stored by the receiver
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReportDoc {
+    pub c2: Vec<u8>, // Commitment to the message
+    pub k_f: Secret<[u8; 32]>, // Commitment randomness
+    pub ctx: ReportContext, // Platform context
+    pub sigma: G1Compressed, // Platform signature H(c2, r', ctx)^k_p
+    pub pk_proc: G2Compressed, // Chosen moderator's k_reg^k
+    pub ke_2: Secret<Scalar>, // Re-encryption key from epk to the chosen moderator
+    pub c3: Ciphertext,
+    pub epoch: u32 // Epoch of the moderator key the message was sent under
+}
+
+// Report sent to the moderator
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub c2: Vec<u8>,
+    pub opening: Opening, // Opens c2 to the message (k_f) or to some of its segments (lib_merkle.rs)
+    pub ctx: ReportContext,
+    pub sigma_prime: GtCompressed, // e(sigma, pk_proc)
+    pub c3_prime: Ciphertext, // c3 re-encrypted to the chosen moderator
+    pub epoch: u32 // Epoch of the keys c3_prime and sigma_prime are for
+}
+
+impl WireMessage for SentMessage {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Send;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.c1);
+        w.bytes(&self.c2);
+        w.point(&self.epk);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<SentMessage, ReportingError> {
+        Ok(SentMessage { c1: r.bytes()?.to_vec(), c2: r.bytes()?.to_vec(), epk: r.point()? })
+    }
+}
+
+// (sigma, st)
+impl WireMessage for (G1Compressed, ProcessState) {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Process;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        let (sigma, st) = self;
+        w.bytes(&sigma.point);
+        w.point(&st.c3.0);
+        w.point(&st.c3.1);
+        w.point(&st.epk);
+        w.bytes(&st.ctx.to_bytes());
+        w.point(&st.r_prime);
+        w.scalar(&st.proof.c);
+        w.scalar(&st.proof.z);
+        w.bytes(st.sigma_previous.as_ref().map_or(&[][..], |sigma| &sigma.point[..]));
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<(G1Compressed, ProcessState), ReportingError> {
+        let sigma = G1Compressed { point: r.array()? };
+        let st = ProcessState {
+            c3: (r.point()?, r.point()?),
+            epk: r.point()?,
+            ctx: ReportContext::from_bytes(r.bytes()?)?,
+            r_prime: r.point()?,
+            proof: DleqProof { c: r.scalar()?, z: r.scalar()? },
+            sigma_previous: match r.bytes()? {
+                [] => None,
+                point => Some(G1Compressed { point: point.try_into().map_err(|_| ReportingError::MalformedEncoding)? })
+            }
+        };
+
+        Ok((sigma, st))
+    }
+}
+
+impl WireMessage for ReportDoc {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::ReportDoc;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.c2);
+        w.bytes(&self.k_f[..]);
+        w.bytes(&self.ctx.to_bytes());
+        w.bytes(&self.sigma.point);
+        w.bytes(&self.pk_proc.point);
+        w.scalar(&self.ke_2);
+        w.point(&self.c3.0);
+        w.point(&self.c3.1);
+        w.u32(self.epoch);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<ReportDoc, ReportingError> {
+        Ok(ReportDoc {
+            c2: r.bytes()?.to_vec(),
+            k_f: Secret::new(r.array()?),
+            ctx: ReportContext::from_bytes(r.bytes()?)?,
+            sigma: G1Compressed { point: r.array()? },
+            pk_proc: G2Compressed { point: r.array()? },
+            ke_2: Secret::new(r.scalar()?),
+            c3: (r.point()?, r.point()?),
+            epoch: r.u32()?
+        })
+    }
+}
+
+impl WireMessage for Report {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Report;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.c2);
+        self.opening.write_fields(w);
+        w.bytes(&self.ctx.to_bytes());
+        w.bytes(&self.sigma_prime.point);
+        w.point(&self.c3_prime.0);
+        w.point(&self.c3_prime.1);
+        w.u32(self.epoch);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Report, ReportingError> {
+        Ok(Report {
+            c2: r.bytes()?.to_vec(),
+            opening: Opening::read_fields(r)?,
+            ctx: ReportContext::from_bytes(r.bytes()?)?,
+            sigma_prime: GtCompressed { point: r.array()? },
+            c3_prime: (r.point()?, r.point()?),
+            epoch: r.u32()?
+        })
+    }
+}
+
+impl WireMessage for RegistrationRequest {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RegistrationRequest;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        write_public_key(w, &self.public_key);
+        w.scalar(&self.pop.c);
+        w.scalar(&self.pop.z);
+        w.bytes(&self.pop_proc.point);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationRequest, ReportingError> {
+        Ok(RegistrationRequest {
+            public_key: read_public_key(r)?,
+            pop: SchnorrProof { c: r.scalar()?, z: r.scalar()? },
+            pop_proc: G1Compressed { point: r.array()? }
+        })
+    }
+}
+
+impl WireMessage for Certificate {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Certificate;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.moderator_id);
+        write_public_key(w, &self.public_key);
+        w.bytes(&self.signature.point);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Certificate, ReportingError> {
+        Ok(Certificate { moderator_id: r.u32()?, public_key: read_public_key(r)?, signature: G1Compressed { point: r.array()? } })
+    }
+}
+
+// The certificate fields follow the MAC key
+impl WireMessage for RegistrationResponse {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RegistrationResponse;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.sk_p[..]);
+        self.certificate.write_fields(w);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationResponse, ReportingError> {
+        Ok(RegistrationResponse { sk_p: Secret::new(r.array()?), certificate: Certificate::read_fields(r)? })
+    }
+}
+
+impl WireMessage for RotationRequest {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RotationRequest;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.moderator_id);
+        write_public_key(w, &self.public_key);
+        w.scalar(&self.pop.c);
+        w.scalar(&self.pop.z);
+        w.bytes(&self.pop_proc.point);
+        w.scalar(&self.authorization.c);
+        w.scalar(&self.authorization.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RotationRequest, ReportingError> {
+        Ok(RotationRequest {
+            moderator_id: r.u32()?,
+            public_key: read_public_key(r)?,
+            pop: SchnorrProof { c: r.scalar()?, z: r.scalar()? },
+            pop_proc: G1Compressed { point: r.array()? },
+            authorization: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+impl WireMessage for RevocationList {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RevocationList;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.version);
+        registration::write_ids(w, &self.revoked);
+        w.bytes(&self.signature.point);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RevocationList, ReportingError> {
+        Ok(RevocationList { version: r.u32()?, revoked: registration::read_ids(r)?, signature: G1Compressed { point: r.array()? } })
+    }
+}
+
+impl WireMessage for KeyRotation {
+    const SCHEME_ID: u8 = SCHEME_CONSTANT_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::KeyRotation;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.version);
+        w.bytes(&self.k_reg.point);
+        w.bytes(&self.endorsement.point);
+        w.u32(self.certificates.len() as u32);
+        for certificate in &self.certificates {
+            certificate.write_fields(w);
+        }
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<KeyRotation, ReportingError> {
+        let (version, k_reg, endorsement) = (r.u32()?, G2Compressed { point: r.array()? }, G1Compressed { point: r.array()? });
+        let count = r.u32()?;
+        let certificates = (0..count).map(|_i| Certificate::read_fields(r)).collect::<Result<Vec<Certificate>, ReportingError>>()?;
+
+        Ok(KeyRotation { version, k_reg, endorsement, certificates })
+    }
+}
+
+fn write_public_key(w: &mut FieldWriter, pk: &PublicKey) {
+    let (pk_enc_1, pk_enc_2, k1_2, pk_proc, k_reg, epoch) = pk;
+    w.point(pk_enc_1);
+    w.point(pk_enc_2);
+    w.scalar(k1_2);
+    w.bytes(&pk_proc.point);
+    w.bytes(&k_reg.point);
+    w.u32(*epoch);
+}
+
+fn read_public_key(r: &mut FieldReader) -> Result<PublicKey, ReportingError> {
+    Ok((r.point()?, r.point()?, r.scalar()?, G2Compressed { point: r.array()? }, G2Compressed { point: r.array()? }, r.u32()?))
+}
+
+fn public_key_bytes(pk: &PublicKey) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    write_public_key(&mut w, pk);
+
+    w.finish().expect("fixed-size fields")
+}
+
+// H(c2, r', ctx), signed with k_p by process
+fn sigma_base(c2: &[u8], r_prime: &[u8], ctx: &ReportContext) -> blstrs::G1Projective {
+    blstrs::G1Projective::hash_to_curve(&[c2, r_prime, &ctx.to_bytes()].concat(), &HASH_TO_CURVE.label(), &[])
+}
+
+// Platform signature H(msg)^(1/k_p), the counterpart of k_reg = g2^(1/k_p)
+fn platform_sign(k_p: &blstrs::Scalar, domain: &Domain, message: &[u8]) -> G1Compressed {
+    let hashed_g1 = blstrs::G1Projective::hash_to_curve(message, &domain.label(), &[]);
+    let k_p_inv = Secret::new(k_p.invert().unwrap());
+
+    G1Compressed { point: (hashed_g1 * *k_p_inv).to_compressed() }
+}
+
+// e(signature, g2) = e(H(msg), k_reg)
+fn platform_verify(k_reg: &G2Compressed, domain: &Domain, message: &[u8], signature: &G1Compressed) -> Result<(), ReportingError> {
+    let hashed_g1 = blstrs::G1Projective::hash_to_curve(message, &domain.label(), &[]);
+
+    let signature = signature.uncompress().ok_or(ReportingError::BadCertificate)?;
+    let k_reg = k_reg.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+    if blstrs::pairing(&signature, &blstrs::G2Affine::generator()) != blstrs::pairing(&hashed_g1.to_affine(), &k_reg) {
+        return Err(ReportingError::BadCertificate);
+    }
+
+    Ok(())
+}
+
+impl Certificate {
+    // Checks the Platform signature, clients call it before using public_key
+    pub fn verify(&self, k_reg: &G2Compressed) -> Result<(), ReportingError> {
+        let message = registration::certificate_message(self.moderator_id, &public_key_bytes(&self.public_key));
+
+        platform_verify(k_reg, &CERTIFICATE, &message, &self.signature)
+    }
+}
+
+impl RevocationList {
+    pub fn verify(&self, k_reg: &G2Compressed) -> Result<(), ReportingError> {
+        let message = registration::revocation_message(self.version, &self.revoked);
+
+        platform_verify(k_reg, &REVOCATION, &message, &self.signature)
+    }
+}
+
+impl KeyRotation {
+    // Checks the endorsement against the previous k_reg and every certificate
+    // against the new one, clients then replace their directory entries
+    pub fn verify(&self, k_reg: &G2Compressed) -> Result<(), ReportingError> {
+        let message = registration::key_rotation_message(self.version, &self.k_reg.point);
+        platform_verify(k_reg, &KEY_ROTATION, &message, &self.endorsement)?;
+
+        for certificate in &self.certificates {
+            if certificate.public_key.4.point != self.k_reg.point {
+                return Err(ReportingError::BadCertificate);
+            }
+            certificate.verify(&self.k_reg)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl G1Compressed {
+    pub fn uncompress(&self) -> Option<G1Affine> {
+        Option::from(G1Affine::from_compressed(&self.point))
+    }
+}
+
+impl G2Compressed {
+    pub fn uncompress(&self) -> Option<G2Affine> {
+        Option::from(G2Affine::from_compressed(&self.point))
+    }
+}
+
+impl GtCompressed {
+    // Torus-based compression of a target group element
+    pub fn compress(gt: &Gt) -> GtCompressed {
+        let mut point = [0u8; 288];
+        gt.write_compressed(&mut point[..]).expect("");
+
+        GtCompressed { point }
+    }
+
+    pub fn uncompress(&self) -> Option<Gt> {
+        Gt::read_compressed(&self.point[..]).ok()
+    }
+}
+
+
+
+// Moderator keys before registration
+pub struct PendingModerator {
+    sk_enc: Secret<Scalar>, // Moderator private key
     pub pk_enc_1: Point, // Moderator public key
     pub pk_enc_2: Point, // Moderator public key 2
-    pub k1_2: Scalar, // Moderator re-encryption key
-    pub k: blstrs::Scalar, // Moderator group scalar secret key
-    pub pk_proc: G2Compressed // Moderator group 2 public key
+    k1_2: Secret<Scalar>, // Moderator re-encryption key
+    k: Secret<blstrs::Scalar>, // Moderator group scalar secret key
+    pub pk_proc: G2Compressed, // Moderator group 2 public key
+    pub k_reg: G2Compressed, // Platform registration key pk_proc is derived from
+    pub epoch: u32 // Epoch the keys are for, 0 at registration
 }
 
-// Moderator Implementation 
-impl Moderator {
+impl PendingModerator {
     // SetupMod(pk_reg, 1^lambda)
-    pub fn new(pk_reg: &blstrs::G2Affine) -> Moderator {
-        let keys = gamal::elgamal_keygen();
-        let keys2 = gamal::elgamal_keygen();
+    pub fn new(pk_reg: &blstrs::G2Affine) -> PendingModerator {
+        Self::new_with_rng(pk_reg, &mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(pk_reg: &blstrs::G2Affine, rng: &mut R) -> PendingModerator {
+        Self::for_epoch(pk_reg, 0, rng)
+    }
+
+    fn for_epoch<R: CryptoRng + RngCore>(pk_reg: &blstrs::G2Affine, epoch: u32, rng: &mut R) -> PendingModerator {
+        let keys = gamal::elgamal_keygen_with_rng(rng);
+        let keys2 = gamal::elgamal_keygen_with_rng(rng);
 
-        let rng = thread_rng();
         // k <- R
-        let sk = blstrs::Scalar::random(rng);
+        let sk = blstrs::Scalar::random(&mut *rng);
 
         // k_reg^k
         let pk: G2Compressed = G2Compressed {
             point: (pk_reg * sk).to_compressed()
         };
-        Moderator {
-            sk_p: mac_keygen(),
-            sk_enc: keys2.0,
+        PendingModerator {
+            sk_enc: Secret::new(keys2.0),
             pk_enc_1: keys.1.compress(),
             pk_enc_2: keys2.1.compress(),
-            k1_2: keys2.0 * keys.0.invert(), // sk2 / sk1
-            k: sk,
-            pk_proc: pk
+            k1_2: Secret::new(keys2.0 * keys.0.invert()), // sk2 / sk1
+            k: Secret::new(sk),
+            pk_proc: pk,
+            k_reg: G2Compressed { point: pk_reg.to_compressed() },
+            epoch
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        (self.pk_enc_1, self.pk_enc_2, *self.k1_2, self.pk_proc.clone(), self.k_reg.clone(), self.epoch)
+    }
+
+    pub fn request(&self) -> RegistrationRequest {
+        self.request_with_rng(&mut OsRng)
+    }
+
+    pub fn request_with_rng<R: CryptoRng + RngCore>(&self, rng: &mut R) -> RegistrationRequest {
+        let public_key = self.public_key();
+        let message = registration::pop_message(&self.k_reg.point, &public_key_bytes(&public_key));
+
+        let pop = nizk::schnorr_prove(&POSSESSION, &self.sk_enc, &RistrettoPoint::mul_base(&self.sk_enc), &message, rng);
+        let hashed_g1 = blstrs::G1Projective::hash_to_curve(&message, &POSSESSION.label(), &[]);
+        let pop_proc = G1Compressed { point: (hashed_g1 * *self.k).to_compressed() };
+
+        RegistrationRequest { public_key, pop, pop_proc }
+    }
+
+    // Checks the certificate and that it certifies this moderator's keys
+    pub fn finish(self, response: RegistrationResponse) -> Result<Moderator, ReportingError> {
+        let RegistrationResponse { sk_p, certificate } = response;
+        certificate.verify(&self.k_reg)?;
+
+        if public_key_bytes(&certificate.public_key) != public_key_bytes(&self.public_key()) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(Moderator {
+            sk_p,
+            sk_mod: (self.sk_enc, self.k),
+            pk_enc_1: self.pk_enc_1,
+            pk_enc_2: self.pk_enc_2,
+            k1_2: self.k1_2,
+            pk_proc: self.pk_proc,
+            k_reg: self.k_reg,
+            retired: RetiredKeys::new(),
+            grace_epochs: registration::GRACE_EPOCHS,
+            certificate
+        })
+    }
+}
+
+// Moderator Properties
+pub struct Moderator {
+    #[allow(dead_code)] // The tag is checked with a pairing, not with sk_p
+    sk_p: Secret<[u8; 32]>, // Mac Key shared with the Platform
+    sk_mod: (Secret<Scalar>, Secret<blstrs::Scalar>), // (sk_enc, k), moderator private key and group scalar secret key
+    pub pk_enc_1: Point, // Moderator public key
+    pub pk_enc_2: Point, // Moderator public key 2
+    k1_2: Secret<Scalar>, // Moderator re-encryption key
+    pub pk_proc: G2Compressed, // Moderator group 2 public key
+    pub k_reg: G2Compressed, // Platform registration key pk_proc is derived from
+    retired: RetiredKeys<(Secret<Scalar>, Secret<blstrs::Scalar>)>, // sk_mod of the earlier epochs still in their grace period
+    pub grace_epochs: u32, // Number of earlier epochs whose reports are still moderated
+    pub certificate: Certificate // Issued at registration or at the last rotation
+}
+
+// Moderator Implementation 
+impl Moderator {
+    pub fn id(&self) -> u32 {
+        self.certificate.moderator_id
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.certificate.public_key.5
+    }
+
+    // (pk_enc_1, pk_enc_2, k1_2, pk_proc, k_reg, epoch) handed to clients
+    pub fn public_key(&self) -> PublicKey {
+        (self.pk_enc_1, self.pk_enc_2, *self.k1_2, self.pk_proc.clone(), self.k_reg.clone(), self.epoch())
+    }
+
+    // Keys for the next epoch and the request to send to the platform
+    pub fn rotate(&self) -> (PendingModerator, RotationRequest) {
+        self.rotate_with_rng(&mut OsRng)
+    }
+
+    pub fn rotate_with_rng<R: CryptoRng + RngCore>(&self, rng: &mut R) -> (PendingModerator, RotationRequest) {
+        let k_reg = self.k_reg.uncompress().expect("registration key checked at registration");
+        let pending = PendingModerator::for_epoch(&k_reg, self.epoch() + 1, rng);
+        let RegistrationRequest { public_key, pop, pop_proc } = pending.request_with_rng(rng);
+
+        let (sk_enc, _k) = &self.sk_mod;
+        let message = registration::rotation_message(&self.k_reg.point, self.id(), &public_key_bytes(&public_key));
+        let authorization = nizk::schnorr_prove(&ROTATION, sk_enc, &RistrettoPoint::mul_base(sk_enc), &message, rng);
+
+        (pending, RotationRequest { moderator_id: self.id(), public_key, pop, pop_proc, authorization })
+    }
+
+    // Switches to the keys of pending once the platform certified them, the
+    // current (sk_enc, k) is kept for grace_epochs epochs
+    pub fn finish_rotation(&mut self, pending: PendingModerator, certificate: Certificate) -> Result<(), ReportingError> {
+        certificate.verify(&self.k_reg)?;
+        if certificate.moderator_id != self.id() || public_key_bytes(&certificate.public_key) != public_key_bytes(&pending.public_key()) {
+            return Err(ReportingError::BadCertificate);
         }
+
+        let epoch = self.epoch();
+        let sk_mod = mem::replace(&mut self.sk_mod, (pending.sk_enc, pending.k));
+        self.retired.retire(epoch, sk_mod, pending.epoch, self.grace_epochs);
+        self.pk_enc_1 = pending.pk_enc_1;
+        self.pk_enc_2 = pending.pk_enc_2;
+        self.k1_2 = pending.k1_2;
+        self.pk_proc = pending.pk_proc;
+        self.certificate = certificate;
+
+        Ok(())
+    }
+
+    // Takes the certificate re-issued by a platform key rotation. The keys do
+    // not change except pk_proc = k_reg^k for the new k_reg, so reports made
+    // before the rotation are still moderated
+    pub fn update_platform_key(&mut self, rotation: &KeyRotation) -> Result<(), ReportingError> {
+        rotation.verify(&self.k_reg)?;
+        let certificate = rotation.certificates.iter().find(|c| c.moderator_id == self.id()).ok_or(ReportingError::BadCertificate)?;
+
+        let k_reg = rotation.k_reg.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+        let (_sk_enc, k) = &self.sk_mod;
+        let pk_proc = G2Compressed { point: (k_reg * **k).to_affine().to_compressed() };
+        let expected = (self.pk_enc_1, self.pk_enc_2, *self.k1_2, pk_proc.clone(), rotation.k_reg.clone(), self.epoch());
+        if public_key_bytes(&certificate.public_key) != public_key_bytes(&expected) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        self.pk_proc = pk_proc;
+        self.k_reg = rotation.k_reg.clone();
+        self.certificate = certificate.clone();
+
+        Ok(())
+    }
+
+    // sk_mod of the epoch a report was made under
+    fn epoch_key(&self, epoch: u32) -> Result<&(Secret<Scalar>, Secret<blstrs::Scalar>), ReportingError> {
+        self.retired.get(epoch, self.epoch(), &self.sk_mod)
     }
 
     // sk_mod = (sk_enc, k)
-    pub fn moderate(sk_enc: &Scalar, k: &blstrs::Scalar, _sk_p: &[u8; 32], message: &str, report: &Report) -> String {
-        let (c2, r, ctx, sigma_prime, c3_prime) = report;
-        let (u, v) = c3_prime;
+    pub fn moderate(&self, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        let (u, v) = &report.c3_prime;
+        let decrypted = self.epoch_key(report.epoch).and_then(|(sk_enc, k)| Ok((Secret::new(gamal::pre_elgamal_dec(sk_enc, &(gamal::decompress(u)?, gamal::decompress(v)?))), k)));
+
+        match decrypted {
+            Ok((r_prime, k)) => self.verify_report(message, report, &r_prime, k, policy),
+            // The report names an epoch without a key or carries an invalid point
+            Err(e) => Ok(Verdict::Reject(e))
+        }
+    }
+
+    // moderate with a verdict anyone can check against pk_enc_2 (lib_verdict.rs)
+    pub fn moderate_signed(&self, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<SignedVerdict, ReportingError> {
+        self.moderate_signed_with_rng(message, report, policy, &mut OsRng)
+    }
+
+    pub fn moderate_signed_with_rng<R: CryptoRng + RngCore>(&self, message: &str, report: &Report, policy: &ExpiryPolicy, rng: &mut R) -> Result<SignedVerdict, ReportingError> {
+        let (sk_enc, k) = self.epoch_key(report.epoch)?;
+        let (u, v) = (gamal::decompress(&report.c3_prime.0)?, gamal::decompress(&report.c3_prime.1)?);
+        let d = v * sk_enc.invert();
+        let r_prime = Secret::new(u - d);
 
-        let r_prime = gamal::pre_elgamal_dec(sk_enc, &(u.decompress().unwrap(), v.decompress().unwrap()));
+        let verdict = self.verify_report(message, report, &r_prime, k, policy)?;
+
+        // log_G(pk_enc_2) = log_(u - r')(v), signing the verdict
+        let transcript = verdict::verdict_transcript(&verdict, &report.ctx, &report.c2)?;
+        let proof = nizk::dleq_prove_with_message(&VERDICT, sk_enc, &d, &RistrettoPoint::mul_base(sk_enc), &v, &transcript, rng);
+
+        Ok(SignedVerdict { verdict, ctx: report.ctx, decrypted: r_prime.compress(), proof })
+    }
+
+    // Checks the commitment and the BLS tag of a report given r' and the k of
+    // its epoch, then its age against policy
+    fn verify_report(&self, message: &str, report: &Report, r_prime: &RistrettoPoint, k: &blstrs::Scalar, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        let Report { c2, opening, ctx, sigma_prime, .. } = report;
 
         // Compute H(c2, r', ctx)
-        let hashed_g1 = blstrs::G1Projective::hash_to_curve(&[&c2[..], &(r_prime.to_bytes().to_vec()[..]), &ctx[..]].concat(), &[], &[]);
+        let hashed_g1 = sigma_base(c2, &r_prime.compress().to_bytes(), ctx);
         // H(c2, r', ctx)^k
-        let hashed_g1 = hashed_g1 * (*k);
+        let hashed_g1 = hashed_g1 * k;
 
         let maybe_sigma = blstrs::pairing(&hashed_g1.to_affine(), &blstrs::G2Affine::generator());
 
         // Verify committment
-        let k_f = r;
-        assert!(com_open(&c2, message, k_f));
+        if !opening.open(&COMMITMENT, c2, message) {
+            return Ok(Verdict::Reject(ReportingError::BadCommitment));
+        }
 
         // Verify signature
-        assert!(sigma_prime.uncompress().unwrap() == maybe_sigma);
+        // Gt has no constant-time equality, compare canonical compressed encodings instead.
+        // A sigma_prime that is not in Gt is a bad tag like one that does not match
+        let valid = sigma_prime.uncompress().is_some() && ct_eq_bytes(&GtCompressed::compress(&maybe_sigma).point, &sigma_prime.point);
+        if !valid {
+            return Ok(Verdict::Reject(ReportingError::BadPlatformTag));
+        }
 
-        let ctx_s = std::str::from_utf8(&ctx).unwrap();
-        return ctx_s.to_string();
+        Ok(policy.verdict(ctx))
     }
 }
 
+// Checks a signed verdict of the moderator with public key pk, of the epoch
+// of the report, on report
+pub fn verify_verdict(pk: &PublicKey, message: &str, report: &Report, sv: &SignedVerdict) -> Result<(), ReportingError> {
+    let Report { c2, opening, ctx, c3_prime, epoch, .. } = report;
+    let (_pk_enc_1, pk_enc_2, _k1_2, _pk_proc, _k_reg, pk_epoch) = pk;
+    registration::check_epoch(*epoch, *pk_epoch)?;
+
+    verdict::check_outcome(sv, ctx, opening.open(&COMMITMENT, c2, message))?;
+
+    let (u, v) = (gamal::decompress(&c3_prime.0)?, gamal::decompress(&c3_prime.1)?);
+    let d = u - gamal::decompress(&sv.decrypted)?;
+    let transcript = verdict::verdict_transcript(&sv.verdict, ctx, c2)?;
+    if !nizk::dleq_verify_with_message(&VERDICT, &d, &gamal::decompress(pk_enc_2)?, &v, &transcript, &sv.proof) {
+        return Err(ReportingError::BadProof);
+    }
+
+    Ok(())
+}
+
 
 
 // Platform Properties
 pub struct Platform {
-    pub k_p: blstrs::Scalar, // Platform key
+    k_p: Secret<blstrs::Scalar>, // Platform key
     pub k_reg: G2Compressed, // Registration key
-    pub sk_p: Vec<([u8; 32], PublicKey)> // Vector of Moderator keys accessible to the Platform
+    pub key_version: u32, // Version of k_p, incremented by rotate_key
+    previous: Option<Secret<blstrs::Scalar>>, // k_p of key_version - 1 until finish_key_rotation
+    sk_p: Vec<Option<(Secret<[u8; 32]>, PublicKey)>>, // Vector of Moderator keys accessible to the Platform, indexed by moderator id, None once revoked
+    revocations: RevocationList, // Signed list of the revoked ids
+    pub replay: ReplayCache // Commitments processed per conversation (lib_replay.rs)
 }
 
 // Platform Implementation
 impl Platform {
     pub fn new() -> Platform {
-        let rng = thread_rng();
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
         // k_p <- R
-        let sk = blstrs::Scalar::random(rng);
+        let sk = blstrs::Scalar::random(&mut *rng);
 
         // Get inverse for registration key
         // 1/k_p
-        let sk_inv = sk.invert().unwrap();
+        let sk_inv = Secret::new(sk.invert().unwrap());
 
         // g2^(1/k_p)
         let g2 = blstrs::G2Affine::generator();
-        let pk = (g2 * sk_inv).to_affine();
+        let pk = (g2 * *sk_inv).to_affine();
+
+        let revocations = RevocationList { version: 0, revoked: Vec::new(), signature: platform_sign(&sk, &REVOCATION, &registration::revocation_message(0, &[])) };
 
         Platform {
-            k_p: sk,
+            k_p: Secret::new(sk),
             k_reg: G2Compressed {
                 point: pk.to_compressed()
             },
-            sk_p: Vec::<([u8; 32], PublicKey)>::new()
+            key_version: 0,
+            previous: None,
+            sk_p: Vec::<Option<(Secret<[u8; 32]>, PublicKey)>>::new(),
+            revocations,
+            replay: ReplayCache::new()
+        }
+    }
+
+    // Removes moderator id from the directory and returns the new revocation
+    // list for clients. The slot stays empty so no other id changes
+    pub fn revoke(&mut self, moderator_id: u32) -> Result<RevocationList, ReportingError> {
+        let slot = self.sk_p.get_mut(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
+        if slot.take().is_some() {
+            let version = self.revocations.version + 1;
+            let mut revoked = self.revocations.revoked.clone();
+            revoked.push(moderator_id);
+            let signature = platform_sign(&self.k_p, &REVOCATION, &registration::revocation_message(version, &revoked));
+            self.revocations = RevocationList { version, revoked, signature };
+        }
+
+        Ok(self.revocations.clone())
+    }
+
+    pub fn revocation_list(&self) -> &RevocationList {
+        &self.revocations
+    }
+
+    // Replaces k_p, re-derives every pk_proc = g2^(k/k_p) as
+    // pk_proc^(k_p/k_p') and re-certifies the directory and the revocation
+    // list. process also signs with the previous key until
+    // finish_key_rotation, so receivers whose directory still has the
+    // previous k_reg can read in the meantime
+    pub fn rotate_key(&mut self) -> KeyRotation {
+        self.rotate_key_with_rng(&mut OsRng)
+    }
+
+    pub fn rotate_key_with_rng<R: CryptoRng + RngCore>(&mut self, rng: &mut R) -> KeyRotation {
+        let k_p = Secret::new(blstrs::Scalar::random(&mut *rng));
+        let k_p_inv = Secret::new(k_p.invert().unwrap());
+        let k_reg = G2Compressed { point: (blstrs::G2Affine::generator() * *k_p_inv).to_affine().to_compressed() };
+        let ratio = Secret::new(*self.k_p * *k_p_inv);
+
+        let version = self.key_version + 1;
+        let endorsement = platform_sign(&self.k_p, &KEY_ROTATION, &registration::key_rotation_message(version, &k_reg.point));
+
+        for (_, public_key) in self.sk_p.iter_mut().flatten() {
+            let pk_proc = public_key.3.uncompress().expect("pk_proc checked at registration");
+            public_key.3 = G2Compressed { point: (pk_proc * *ratio).to_affine().to_compressed() };
+            public_key.4 = k_reg.clone();
         }
+
+        self.previous = Some(mem::replace(&mut self.k_p, k_p));
+        self.k_reg = k_reg.clone();
+        self.key_version = version;
+
+        let RevocationList { version: list_version, revoked, .. } = &self.revocations;
+        self.revocations.signature = platform_sign(&self.k_p, &REVOCATION, &registration::revocation_message(*list_version, revoked));
+
+        let certificates = self.sk_p.iter().enumerate()
+            .filter_map(|(id, slot)| slot.as_ref().map(|(_, public_key)| self.certify(id as u32, public_key)))
+            .collect();
+
+        KeyRotation { version, k_reg, endorsement, certificates }
+    }
+
+    // Ends the transition window of the last key rotation, the previous key
+    // is dropped and process signs with the current one only
+    pub fn finish_key_rotation(&mut self) {
+        self.previous = None;
     }
 
 
 
 
-    pub fn process(k_p: &blstrs::Scalar, _ks: &Vec<([u8; 32], PublicKey)>, _c1: &Vec<u8>, c2: &Vec<u8>, ad: &Point, ctx: &Vec<u8>) -> (G1Compressed, State) {
+    // Checks the proofs of possession, issues the MAC key and certifies the
+    // new directory entry
+    pub fn register(&mut self, request: &RegistrationRequest) -> Result<RegistrationResponse, ReportingError> {
+        self.register_with_rng(request, &mut OsRng)
+    }
+
+    pub fn register_with_rng<R: CryptoRng + RngCore>(&mut self, request: &RegistrationRequest, rng: &mut R) -> Result<RegistrationResponse, ReportingError> {
+        let RegistrationRequest { public_key, pop, pop_proc } = request;
+        registration::check_epoch(public_key.5, 0)?;
+        self.check_keys(public_key, pop, pop_proc)?;
+
+        let sk_p = Secret::new(mac_keygen_with_rng(rng));
+        let moderator_id = self.sk_p.len() as u32;
+        let certificate = self.certify(moderator_id, public_key);
+
+        self.sk_p.push(Some((sk_p.clone(), public_key.clone())));
+
+        Ok(RegistrationResponse { sk_p, certificate })
+    }
+
+    // Checks the new keys, the authorization by the current ones and
+    // certifies the new keys, the MAC key stays the same
+    pub fn rotate(&mut self, request: &RotationRequest) -> Result<Certificate, ReportingError> {
+        let RotationRequest { moderator_id, public_key, pop, pop_proc, authorization } = request;
+        let slot = self.sk_p.get(*moderator_id as usize).ok_or(ReportingError::UnknownModerator(*moderator_id))?;
+        let (_, current) = slot.as_ref().ok_or(ReportingError::RevokedModerator(*moderator_id))?;
+
+        registration::check_epoch(public_key.5, current.5 + 1)?;
+        self.check_keys(public_key, pop, pop_proc)?;
+
+        let message = registration::rotation_message(&self.k_reg.point, *moderator_id, &public_key_bytes(public_key));
+        if !nizk::schnorr_verify(&ROTATION, &gamal::decompress(&current.1)?, &message, authorization) {
+            return Err(ReportingError::BadProof);
+        }
+
+        let certificate = self.certify(*moderator_id, public_key);
+        if let Some((_, current)) = &mut self.sk_p[*moderator_id as usize] {
+            *current = public_key.clone();
+        }
+
+        Ok(certificate)
+    }
+
+    fn certify(&self, moderator_id: u32, public_key: &PublicKey) -> Certificate {
+        let message = registration::certificate_message(moderator_id, &public_key_bytes(public_key));
+        let signature = platform_sign(&self.k_p, &CERTIFICATE, &message);
+
+        Certificate { moderator_id, public_key: public_key.clone(), signature }
+    }
+
+    // k_reg, k1_2 and the proofs of possession of sk_enc and k
+    fn check_keys(&self, public_key: &PublicKey, pop: &SchnorrProof, pop_proc: &G1Compressed) -> Result<(), ReportingError> {
+        let (pk_enc_1, pk_enc_2, k1_2, pk_proc, k_reg, _epoch) = public_key;
+        let pk_enc_2_pt = gamal::decompress(pk_enc_2)?;
+
+        // pk_proc must be derived from this platform's k_reg, and k1_2 must
+        // re-encrypt from pk_enc_1 to pk_enc_2
+        if k_reg.point != self.k_reg.point || !bool::from((k1_2 * gamal::decompress(pk_enc_1)?).ct_eq(&pk_enc_2_pt)) {
+            return Err(ReportingError::BadModeratorKey);
+        }
+
+        let message = registration::pop_message(&self.k_reg.point, &public_key_bytes(public_key));
+        if !nizk::schnorr_verify(&POSSESSION, &pk_enc_2_pt, &message, pop) {
+            return Err(ReportingError::BadProof);
+        }
+
+        let hashed_g1 = blstrs::G1Projective::hash_to_curve(&message, &POSSESSION.label(), &[]);
+        let pop_proc = pop_proc.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+        let pk_proc = pk_proc.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+        let k_reg = k_reg.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+        if blstrs::pairing(&pop_proc, &k_reg) != blstrs::pairing(&hashed_g1.to_affine(), &pk_proc) {
+            return Err(ReportingError::BadProof);
+        }
+
+        Ok(())
+    }
+
+    // sigma does not depend on the chosen moderator and the platform cannot
+    // tell who it is, so revocation is enforced by clients (send and read)
+    // rather than here
+    pub fn process<R: CryptoRng + RngCore>(&mut self, _c1: &[u8], c2: &[u8], ad: &Point, ctx: &ReportContext, rng: &mut R) -> Result<(G1Compressed, ProcessState), ReportingError> {
         let epk = ad;
+        let ctx = self.replay.admit(ctx, c2)?;
         
         // Make RistrettoPoint to encrypt with elgamal
-        let r_prime = RistrettoPoint::random(&mut OsRng);
+        let r_prime = Secret::new(RistrettoPoint::random(rng));
 
         // Compute H(c2, r', ctx)
-        let hashed_g1 = blstrs::G1Projective::hash_to_curve(&[&c2[..], &(r_prime.to_bytes().to_vec()[..]), &ctx[..]].concat(), &[], &[]);
+        let hashed_g1 = sigma_base(c2, &r_prime.compress().to_bytes(), &ctx);
 
-        // H(c2, r', ctx)^k_p
-        let sigma = hashed_g1 * k_p;
+        // H(c2, r', ctx)^k_p, and under the previous key during a key rotation
+        let sigma = hashed_g1 * *self.k_p;
+        let sigma_previous = self.previous.as_ref().map(|k_p| G1Compressed { point: (hashed_g1 * **k_p).to_compressed() });
 
         // PRE Scheme
-        let c3 = gamal::pre_elgamal_enc(&epk.decompress().unwrap(), &r_prime);
+        let epk_pt = gamal::decompress(epk)?;
+        let r = Secret::new(Scalar::random(rng));
+        let c3 = gamal::pre_elgamal_enc_with_r(&epk_pt, &r_prime, &r);
         let (u, v) = c3;
 
-        (G1Compressed { point : sigma.to_compressed() }, ((u.compress(), v.compress()), *epk, ctx.clone()))
+        // log_G(u - r') = log_epk(v) = r
+        let proof = nizk::dleq_prove(&C3_PROOF, &r, &epk_pt, &(u - *r_prime), &v, rng);
+
+        let st = ProcessState { c3: (u.compress(), v.compress()), epk: *epk, ctx, r_prime: r_prime.compress(), proof, sigma_previous };
+
+        Ok((G1Compressed { point : sigma.to_compressed() }, st))
     }
 
 }
 
+impl Default for Platform {
+    fn default() -> Platform {
+        Platform::new()
+    }
+}
+
 // Client Properties
 pub struct Client {
-    pub msg_key: Key<Aes256Gcm> // Symmetric key used to encrypt messages between sender and
+    msg_key: Secret<Key<Aes256Gcm>>, // Symmetric key used to encrypt messages between sender and
+    revocations: Revocations // Newest revocation list verified by this client
 }
 
 // Client Implementation
 impl Client {
     pub fn new() -> Client {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
         Client {
-            msg_key: Aes256Gcm::generate_key(aes_gcm::aead::OsRng)
+            msg_key: Secret::new(Aes256Gcm::generate_key(rng)),
+            revocations: Revocations::default()
         }
     }
 
+    // Accepts the revocation list of the platform with registration key k_reg
+    pub fn update_revocations(&mut self, list: &RevocationList, k_reg: &G2Compressed) -> Result<(), ReportingError> {
+        list.verify(k_reg)?;
 
-    pub fn ccae_enc(msg_key: &Key<Aes256Gcm>, message: &str, moderator_id: u32, ke_2: Scalar) -> (Vec<u8>, Vec<u8>) {
-        let k_f: [u8; 32] = mac_keygen(); // franking key or r in H(m, r) for committment
-        
-        let c2 = com_commit(&k_f, message);
-
-        let cipher = Aes256Gcm::new(&msg_key);
-        let nonce = Aes256Gcm::generate_nonce(&mut rand::rngs::OsRng);
-
-        let payload = bincode::serialize(&(message, moderator_id, k_f, ke_2.to_bytes())).expect("");
-        let c1_obj = cipher.encrypt(&nonce, payload.as_slice()).unwrap();
-        let c1 = bincode::serialize::<(Vec<u8>, Vec<u8>)>(&(c1_obj, nonce.to_vec())).expect("");
+        self.revocations.update(list.version, &list.revoked)
+    }
 
-        (c1, c2)
+    pub fn revocations(&self) -> &Revocations {
+        &self.revocations
     }
 
 
-    pub fn ccae_dec(msg_key: &Key<Aes256Gcm>, c1: &Vec<u8>, c2: &Vec<u8>) -> (String, u32, Scalar, [u8; 32]) {
-        let c1_obj = bincode::deserialize::<(Vec<u8>, Vec<u8>)>(&c1).unwrap();
-        let ct = c1_obj.0;
-        let nonce = Nonce::from_slice(&c1_obj.1);
+    pub fn ccae_enc<R: CryptoRng + RngCore>(&self, message: &str, moderator_id: u32, epoch: u32, ke_2: Scalar, rng: &mut R) -> Result<(Vec<u8>, Vec<u8>), ReportingError> {
+        let k_f = Secret::new(mac_keygen_with_rng(rng)); // franking key or r in H(m, r) for committment
+        
+        let c2 = com_commit(&COMMITMENT, &k_f[..], message);
+
+        let mut payload = FieldWriter::new();
+        payload.bytes(message.as_bytes());
+        payload.u32(moderator_id);
+        payload.u32(epoch);
+        payload.bytes(&k_f[..]);
+        payload.scalar(&ke_2);
+        let payload = Secret::new(payload.finish()?);
+        let c1 = sym_enc(&self.msg_key, &payload, rng);
+
+        Ok((c1, c2))
+    }
 
-        let cipher = Aes256Gcm::new(&msg_key);
-        let payload_bytes = cipher.decrypt(&nonce, ct.as_ref()).unwrap();
-        let payload = bincode::deserialize::<(&str, u32, [u8; 32], [u8; 32])>(&payload_bytes).unwrap();
 
-        let (message, moderator_id, k_f, ke_2) = payload;
-        let ke_2 = Scalar::from_bytes_mod_order(ke_2);
+    #[allow(clippy::type_complexity)]
+    pub fn ccae_dec(&self, c1: &[u8], c2: &[u8]) -> Result<(String, u32, u32, Secret<Scalar>, Secret<[u8; 32]>), ReportingError> {
+        let payload_bytes = Secret::new(sym_dec(&self.msg_key, c1)?);
+        let mut payload = FieldReader::new(&payload_bytes);
+        let message = payload.string()?;
+        let moderator_id = payload.u32()?;
+        let epoch = payload.u32()?;
+        let k_f = Secret::new(payload.array::<32>()?);
+        let ke_2 = Secret::new(payload.scalar()?);
+        payload.finish()?;
 
         // Verify committment
-        assert!(com_open(&c2, message, &k_f));
+        if !com_open(&COMMITMENT, c2, &message, &k_f[..]) {
+            return Err(ReportingError::BadCommitment);
+        }
 
-        (message.to_string(), moderator_id, ke_2, k_f)
+        Ok((message, moderator_id, epoch, ke_2, k_f))
     }
 
-    pub fn send(msg_key: &Key<Aes256Gcm>, message: &str, moderator_id: u32, pk_i: &PublicKey) -> (Vec<u8>, Vec<u8>, Point) {
-        let (pk1, pk2, k1_2, pk_proc) = pk_i;
+    pub fn send<R: CryptoRng + RngCore>(&self, message: &str, moderator_id: u32, pk_i: &PublicKey, rng: &mut R) -> Result<SentMessage, ReportingError> {
+        self.revocations.check(moderator_id)?;
+
+        let (pk1, _pk2, k1_2, _pk_proc, _k_reg, epoch) = pk_i;
 
         // El gamal proxy re-encryption
-        let x = Scalar::random(&mut OsRng);
-        let epk: Point = (&x * pk1.decompress().unwrap()).compress();
+        let x = Scalar::random(rng);
+        let epk: Point = (x * gamal::decompress(pk1)?).compress();
         let ke_2: Scalar = k1_2 * x.invert();
 
 
-        let (c1, c2) = Self::ccae_enc(msg_key, message, moderator_id, ke_2);
+        let (c1, c2) = self.ccae_enc(message, moderator_id, *epoch, ke_2, rng)?;
 
-        (c1, c2, (epk))
+        Ok(SentMessage { c1, c2, epk })
     }
   
 
 
-    pub fn read(msg_key: &Key<Aes256Gcm>, pks: &Vec<PublicKey>, c1: &Vec<u8>, c2: &Vec<u8>, sigma: &G1Compressed, st: &(Ciphertext, Point, Vec<u8>)) -> (String, u32, ReportDoc) {
-        let (c3, epk, ctx) = st;
-        let (message, moderator_id, ke_2, k_f) = Self::ccae_dec(msg_key, c1, c2);
+    pub fn read(&self, pks: &[PublicKey], c1: &[u8], c2: &[u8], sigma: &G1Compressed, st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        let ProcessState { c3, epk, ctx, r_prime, proof, sigma_previous, .. } = st;
+        let (message, moderator_id, epoch, ke_2, k_f) = self.ccae_dec(c1, c2)?;
+        self.revocations.check(moderator_id)?;
 
-        let (_pk1, pk2, _k1_2, pk_proc) = &pks[usize::try_from(moderator_id).unwrap()];
+        // The message must be sent under the keys in the directory
+        let (_pk1, pk2, _k1_2, pk_proc, k_reg, pk_epoch) = pks.get(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
+        registration::check_epoch(epoch, *pk_epoch)?;
 
 
         // Ensure this message is reportable
-        assert!((&ke_2 * epk.decompress().unwrap()) == pk2.decompress().unwrap());
+        let epk = gamal::decompress(epk)?;
+        if !bool::from((*ke_2 * epk).ct_eq(&gamal::decompress(pk2)?)) {
+            return Err(ReportingError::BadModeratorKey);
+        }
+
+        // c3 must carry r' to the moderator
+        let r_prime_pt = gamal::decompress(r_prime)?;
+        let (u, v) = (gamal::decompress(&c3.0)?, gamal::decompress(&c3.1)?);
+        if !nizk::dleq_verify(&C3_PROOF, &epk, &(u - r_prime_pt), &v, proof) {
+            return Err(ReportingError::BadProof);
+        }
+
+        // e(sigma, k_reg) = e(H(c2, r', ctx)^k_p, g2^(1/k_p)) = e(H(c2, r', ctx), g2)
+        let k_reg = k_reg.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+        let hashed_g1 = sigma_base(c2, &r_prime.to_bytes(), ctx);
+        let expected = blstrs::pairing(&hashed_g1.to_affine(), &blstrs::G2Affine::generator());
+        let verifies = |sigma: &G1Compressed| -> Result<bool, ReportingError> {
+            let sigma_pt = sigma.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+            Ok(blstrs::pairing(&sigma_pt, &k_reg) == expected)
+        };
+
+        // During a key rotation the entry may still be under the previous
+        // k_reg, the report doc keeps the signature that matches its pk_proc
+        let sigma = match sigma_previous {
+            _ if verifies(sigma)? => sigma,
+            Some(previous) if verifies(previous)? => previous,
+            _ => return Err(ReportingError::BadPlatformTag)
+        };
 
         // Generate report documentation
-        let rd: ReportDoc = (c2.clone(), k_f.clone(), ctx.clone(), sigma.clone(), 
-            pk_proc.clone(), ke_2, c3.clone());
+        let rd = ReportDoc { c2: c2.to_vec(), k_f, ctx: *ctx, sigma: sigma.clone(),
+            pk_proc: pk_proc.clone(), ke_2, c3: *c3, epoch };
 
 
-        (message, moderator_id, rd)
+        Ok((message, moderator_id, rd))
     }
 
 
-    // type Report = (Vec<u8>, [u8; 32], Vec<u8>, blstrs::Gt, Ciphertext);
-    pub fn report_gen(msg: &String, rd: &ReportDoc) -> Report {
-        let (c2, k_f, ctx, sigma, pk_proc, ke_2, c3) = rd;
-        let sigma_prime: blstrs::Gt = blstrs::pairing(&blstrs::G1Affine::from_compressed(&sigma.point).unwrap(), &blstrs::G2Affine::from_compressed(&pk_proc.point).unwrap());
+    pub fn report_gen(_msg: &str, rd: &ReportDoc) -> Result<Report, ReportingError> {
+        let ReportDoc { c2, k_f, ctx, sigma, pk_proc, ke_2, c3, epoch } = rd;
+        let sigma = sigma.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+        let pk_proc = pk_proc.uncompress().ok_or(ReportingError::InvalidCurvePoint)?;
+        let sigma_prime: blstrs::Gt = blstrs::pairing(&sigma, &pk_proc);
 
         // PRE Re-Encryption
         let (u, v) = c3;
-        let c3_prime = gamal::pre_re_enc(&(u.decompress().unwrap(), v.decompress().unwrap()), &ke_2);
+        let c3_prime = gamal::pre_re_enc(&(gamal::decompress(u)?, gamal::decompress(v)?), ke_2);
         let (u, v) = c3_prime;
 
 
-        let report: Report = (c2.clone(), *k_f, ctx.to_vec(), 
-            sigma_prime.compress().unwrap()
-                , (u.compress(), v.compress()));
+        let report = Report { c2: c2.clone(), opening: Opening::Message(k_f.clone()), ctx: *ctx,
+            sigma_prime: GtCompressed::compress(&sigma_prime),
+            c3_prime: (u.compress(), v.compress()), epoch: *epoch };
 
 
-        report
+        Ok(report)
 
     }
 
-}
+    // Report revealing only the segments reveal of msg (lib_merkle.rs)
+    pub fn report_gen_partial(msg: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        let opening = Opening::Message(rd.k_f.clone()).disclose(&COMMITMENT, msg, reveal)?;
+
+        Ok(Report { opening, ..Self::report_gen(msg, rd)? })
+    }
 
-pub fn test_setup_platform() -> Platform {
-    let platform = Platform::new();
+}
 
-    platform
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
 }
 
 
-// SetupMod(pk_reg, 1^lambda)
-pub fn test_setup_mod(platform: &mut Platform, num_moderators: usize) -> (Vec<Moderator>, Vec<PublicKey>) {
-    let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
-    let mut pks: Vec<PublicKey> = Vec::with_capacity(num_moderators);
+// Constant moderator privacy scheme: the platform signs once with its BLS key
+// and the receiver converts the signature for the chosen moderator at report time
+pub struct ConstantModPriv;
+
+impl ReportingScheme for ConstantModPriv {
+    const NAME: &'static str = "Constant Moderator Privacy Scheme";
+
+    type Platform = Platform;
+    type Moderator = Moderator;
+    type Client = Client;
+    type PublicKey = PublicKey;
+    type Ciphertext = SentMessage;
+    type Sigma = G1Compressed;
+    type ProcessState = ProcessState;
+    type ReportDoc = ReportDoc;
+    type Report = Report;
 
-    for _i in 0..num_moderators {
-        let moderator = Moderator::new(&blstrs::G2Affine::from_compressed(&platform.k_reg.point).unwrap());
-        platform.sk_p.push((moderator.sk_p.clone(), (moderator.pk_enc_1.clone(), moderator.pk_enc_2.clone(), moderator.k1_2.clone(), moderator.pk_proc.clone())));
-        pks.push((moderator.pk_enc_1.clone(), moderator.pk_enc_2.clone(), moderator.k1_2.clone(), moderator.pk_proc.clone()));
-        moderators.push(moderator);
+    fn setup_platform_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
+        Platform::new_with_rng(rng)
     }
 
+    fn setup_mod_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, num_moderators: usize, rng: &mut R) -> (Vec<Moderator>, Vec<PublicKey>) {
+        let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
+        let mut pks: Vec<PublicKey> = Vec::with_capacity(num_moderators);
 
-    (moderators, pks)
-}
+        for _i in 0..num_moderators {
+            let pending = PendingModerator::new_with_rng(&blstrs::G2Affine::from_compressed(&platform.k_reg.point).unwrap(), rng);
+            let response = platform.register_with_rng(&pending.request_with_rng(rng), rng).expect("registration of an honest moderator");
+            let moderator = pending.finish(response).expect("certificate of an honest platform");
+            pks.push(moderator.public_key());
+            moderators.push(moderator);
+        }
 
-pub fn test_setup() -> (Vec<Platform>, Vec<Vec<Moderator>>, Vec<Vec<PublicKey>>) {
-    let n: usize = usize::try_from(MOD_SCALE.len()).unwrap();
-    let mut platforms: Vec<Platform> = Vec::with_capacity(n);
-    
-    for _i in 0..n {
-        platforms.push(Platform::new());
+        (moderators, pks)
     }
-    
-    let mut moderators: Vec<Vec<Moderator>> = Vec::with_capacity(n);
-    let mut pubs: Vec<Vec<PublicKey>> = Vec::new();
 
-    for (i, num_moderators) in MOD_SCALE.iter().enumerate() {
-        let k = usize::try_from(*num_moderators).unwrap();
-        let (mods, pks) = test_setup_mod(&mut platforms[i], k);
-        moderators.push(mods);
-        pubs.push(pks);
+    fn init_client_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
+        Client::new_with_rng(rng)
     }
 
-    (platforms, moderators, pubs)
-}
+    fn send_with_rng<R: CryptoRng + RngCore>(client: &Client, message: &str, moderator_id: u32, pks: &[PublicKey], rng: &mut R) -> Result<SentMessage, ReportingError> {
+        let pk_i = pks.get(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
 
-// Setup Clients
-pub fn test_init_clients(num_clients: usize) -> Vec<Client> {
-    let mut clients: Vec<Client> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let client = Client::new();
-        clients.push(client);
+        client.send(message, moderator_id, pk_i, rng)
     }
 
-    clients
-}
-
+    fn process_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, ct: &SentMessage, ctx: &ReportContext, rng: &mut R) -> Result<(G1Compressed, ProcessState), ReportingError> {
+        platform.process(&ct.c1, &ct.c2, &ct.epk, ctx, rng)
+    }
 
-// Setup Messages
-pub fn test_init_messages(num_clients: usize, msg_size: usize) -> Vec<String> {
-    // Prepare messages
-    let mut ms: Vec<String> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let m = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        ms.push(m);
+    fn read(client: &Client, pks: &[PublicKey], ct: &SentMessage, sigma: &G1Compressed, st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        client.read(pks, &ct.c1, &ct.c2, sigma, st)
     }
-    ms
-}
 
+    fn report_gen(message: &str, rd: &ReportDoc) -> Result<Report, ReportingError> {
+        Client::report_gen(message, rd)
+    }
 
-// send(k, m, pk_i)
-pub fn test_send(num_clients: usize, moderators: &Vec<Moderator>, clients: &Vec<Client>, ms: &Vec<String>, print: bool) -> Vec<(Vec<u8>, Vec<u8>, Point)> {
-    let mut c1c2ad: Vec<(Vec<u8>, Vec<u8>, Point)> = Vec::with_capacity(num_clients);
-    let num_moderators = moderators.len();
+    fn report_gen_partial(message: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        Client::report_gen_partial(message, rd, reveal)
+    }
 
-    // send message i to client i to be moderated by random mod
-    let mut rng = thread_rng();
-    for i in 0..num_clients {
-        let mod_i = rng.gen_range(0..num_moderators);
-        let mod_ref = &moderators[usize::try_from(mod_i).unwrap()];
-        let pki: PublicKey = (mod_ref.pk_enc_1.clone(), mod_ref.pk_enc_2.clone(), mod_ref.k1_2.clone(), mod_ref.pk_proc.clone());
-        let (c1, c2, ad) = Client::send(&clients[i].msg_key, &ms[i], mod_i.try_into().unwrap(), &pki);
-        
-        if print {
-            // Additional Costs
-            // (1) Commitment to the Message
-            // (2) Moderator masked public key (element of G)
-            // (3) 32 byte commitment randomness (k_f)
-            // (4) Scalar in G (32 bytes) (ke_2)
-            // (5) Moderator id (4 bytes)
-            let mut cost: usize = mem::size_of_val(&*c2) + mem::size_of_val(&ad) + 32 + 32 + 4;
-
-            println!("Sending communication cost: {} (bytes)", &cost);
-        }
-        c1c2ad.push((c1, c2, ad));
+    fn moderate(moderator: &Moderator, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        moderator.moderate(message, report, policy)
     }
 
-    c1c2ad
-}
+    // (1) Commitment to the Message
+    // (2) Moderator masked public key (element of G)
+    // (3) 32 byte commitment randomness (k_f)
+    // (4) Scalar in G (32 bytes) (ke_2)
+    // (5) Moderator id (4 bytes)
+    // (6) Key epoch (4 bytes)
+    fn send_cost(ct: &SentMessage) -> usize {
+        mem::size_of_val(&*ct.c2) + mem::size_of_val(&ct.epk) + 32 + 32 + 4 + 4
+    }
 
+    // (1) Moderator epk
+    // (2) randomness for commitment
+    // (3) commitment
+    // (4) platform signature (element of G1)
+    // (5) ke_2 (Scalar)
+    // (6) Moderator id (4 bytes)
+    // (7) c3 (proxy re-encryption of randonness)
+    // (8) r' and the proof that c3 encrypts it
+    // (9) Key epoch
+    // (10) sigma under the previous key during a key rotation
+    // (11) Message id
+    // (12) Sequence number
+    fn receive_cost(sigma: &G1Compressed, st: &ProcessState, rd: &ReportDoc) -> usize {
+        let ReportDoc { c2, k_f, ke_2, c3: (u, v), epoch, .. } = rd;
+
+        mem::size_of_val(&st.epk) + mem::size_of_val(k_f) + mem::size_of_val(&**c2) + mem::size_of_val(sigma) + mem::size_of_val(ke_2) + 4 + mem::size_of_val(u) + mem::size_of_val(v)
+            + mem::size_of_val(&st.r_prime) + mem::size_of_val(&st.proof) + mem::size_of_val(epoch)
+            + st.sigma_previous.as_ref().map_or(0, mem::size_of_val) + mem::size_of_val(&st.ctx.message_id) + mem::size_of_val(&st.ctx.sequence)
+    }
 
-// Send messages of sizes in MSG_SIZE_SCALE
-// to platforms with num moderators in MOD_SCALE
-pub fn test_send_variable(moderators: &Vec<Vec<Moderator>>, clients: &Vec<Client>, ms: &Vec<Vec<String>>) -> 
-Vec<Vec<Vec<(Vec<u8>, Vec<u8>, Point)>>> {
-    // Send messages
-    let mut c1c2ad: Vec<Vec<Vec<(Vec<u8>, Vec<u8>, Point)>>> = Vec::new();
-    // c1c2ad[i][j] = Encryption of message j to moderator i
-    for i in 0..moderators.len() {
-        let mut tmp: Vec<Vec<(Vec<u8>, Vec<u8>, Point)>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-        for (j, _msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
-            tmp.push(test_send(1, &moderators[i], clients, &ms[j], false));
-        }
-        c1c2ad.push(tmp);
+    // (1) Moderator id (4 bytes)
+    // (2) randomness for commitment
+    // (3) commitment
+    // (4) platform signature (element of G1)
+    // (5) ke_2 (Scalar)
+    // (6) c3 (proxy re-encryption of randonness)
+    // (7) Key epoch
+    // (8) Message id
+    // (9) Sequence number
+    fn storage_cost(rd: &ReportDoc) -> usize {
+        let ReportDoc { c2, k_f, sigma, ke_2, c3: (u, v), epoch, ctx, .. } = rd;
+
+        4 + mem::size_of_val(k_f) + mem::size_of_val(&**c2) + mem::size_of_val(sigma) + mem::size_of_val(ke_2) + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(epoch) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
     }
 
-    c1c2ad
+    // (1) opening of the commitment (k_f, 32 bytes, or the disclosed segments)
+    // (2) commitment
+    // (3) el gamal ct
+    // (4) sigma
+    // (5) Key epoch
+    // (6) Message id
+    // (7) Sequence number
+    fn moderation_cost(report: &Report) -> usize {
+        let Report { c2, opening, sigma_prime, c3_prime: (u, v), epoch, ctx } = report;
+
+        opening.cost() + mem::size_of_val(&**c2) + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(sigma_prime) + mem::size_of_val(epoch) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::lib_scheme::Fixture;
+    use crate::lib_wire::check_encoding;
+    use crate::lib_verdict::check_signed_verdict;
+
+    #[test]
+    fn registration_checks_proof_of_possession() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut platform = Platform::new_with_rng(&mut rng);
+        let k_reg = platform.k_reg.uncompress().unwrap();
+        let pending = PendingModerator::new_with_rng(&k_reg, &mut rng);
+        let other = PendingModerator::new_with_rng(&k_reg, &mut rng);
+
+        // A proof of possession of sk_enc for other keys
+        let request = RegistrationRequest { pop: other.request_with_rng(&mut rng).pop, ..pending.request_with_rng(&mut rng) };
+        assert_eq!(platform.register_with_rng(&request, &mut rng).err(), Some(ReportingError::BadProof));
+
+        // and of k
+        let request = RegistrationRequest { pop_proc: other.request_with_rng(&mut rng).pop_proc, ..pending.request_with_rng(&mut rng) };
+        assert_eq!(platform.register_with_rng(&request, &mut rng).err(), Some(ReportingError::BadProof));
+    }
 
-// process(k_p, ks, c1, c2, ad, ctx) -> (G1, State)
-pub fn test_process(num_clients: usize, msg_size: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, Point)>, platform: &Platform, print: bool) -> Vec<(G1Compressed, State)> {
-    let mut sigma_st: Vec<(G1Compressed, State)> = Vec::with_capacity(num_clients);
-    // Platform processes message
-    for i in 0..num_clients {
-        let (c1, c2, ad) = &c1c2ad[i];
-        let ctx = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        let (sigma, st) = Platform::process(&platform.k_p, &platform.sk_p, &c1, &c2, ad, &ctx.as_bytes().to_vec());
+    #[test]
+    fn tampered_tag_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<ConstantModPriv>::new(&mut rng);
+
+        // The tag is bound to the context
+        let mut report = f.report.clone();
+        report.ctx.recipient += 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+
+        // An element of Gt chosen without the platform key
+        let mut report = f.report.clone();
+        report.sigma_prime = GtCompressed::compress(&blstrs::pairing(&blstrs::G1Affine::generator(), &blstrs::G2Affine::generator()));
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+
+        // An encoding outside Gt
+        let mut report = f.report.clone();
+        report.sigma_prime.point[0] ^= 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+    }
 
+    #[test]
+    fn read_checks_sigma_and_c3() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let f = Fixture::<ConstantModPriv>::new(&mut rng);
 
+        // A signature that does not verify under k_reg
+        let sigma = G1Compressed { point: blstrs::G1Affine::generator().to_compressed() };
+        assert_eq!(f.read(&sigma, &f.st).err(), Some(ReportingError::BadPlatformTag));
 
-        sigma_st.push((sigma, st));
+        // c3 must encrypt the r' sigma is checked with
+        let mut st = f.st.clone();
+        st.r_prime = RistrettoPoint::random(&mut rng).compress();
+        assert_eq!(f.read(&f.sigma, &st).err(), Some(ReportingError::BadProof));
     }
 
-    sigma_st
-}
+    #[test]
+    fn revoked_moderator_is_refused() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut f = Fixture::<ConstantModPriv>::new(&mut rng);
+        let list = f.platform.revoke(0).unwrap();
+        f.client.update_revocations(&list, &f.platform.k_reg).unwrap();
 
+        assert_eq!(ConstantModPriv::send_with_rng(&f.client, "m", 0, &f.pks, &mut rng).err(), Some(ReportingError::RevokedModerator(0)));
+        assert_eq!(f.read(&f.sigma, &f.st).err(), Some(ReportingError::RevokedModerator(0)));
+    }
 
-// Process messages of sizes in MSG_SIZE_SCALE
-// and encrypt them to moderators in MOD_SCALE
-pub fn test_process_variable(moderators: &Vec<Vec<Moderator>>, c1c2ad: &Vec<Vec<Vec<(Vec<u8>, Vec<u8>, Point)>>>, platforms: &Vec<Platform>) -> Vec<Vec<Vec<(G1Compressed, State)>>> {
-    // Process messages
-    let mut sigma_st: Vec<Vec<Vec<(G1Compressed, State)>>> = Vec::new();
-    // sigma_st[i][j] = encrypted signature on message commitmment j to moderator i
-    for i in 0..moderators.len() {
-        let mut tmp: Vec<Vec<(G1Compressed, State)>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-        for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
-            tmp.push(test_process(1, *msg_size, &c1c2ad[i][j], &platforms[i], false));
-        }
-        sigma_st.push(tmp);
-    }
-
-    sigma_st
-}
-
-
-
-// read(k, pks, c1, c2, sigma, st)
-pub fn test_read(num_clients: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, Point)>, sigma_st: &Vec<(G1Compressed, State)>, clients: &Vec<Client>, pks: &Vec<PublicKey>, print: bool) -> Vec<(String, u32, ReportDoc)> {
-    // Receive messages
-    let mut reports: Vec<(String, u32, ReportDoc)> = Vec::with_capacity(num_clients);
-    // Receive message i from client i to be moderated by randomly selected moderator mod_i
-    for i in 0..num_clients {
-        let (c1, c2, _ad) = &c1c2ad[i];
-        let (sigma, st) = &sigma_st[i];
-        let (message, ad, report) = Client::read(&clients[i].msg_key, &pks, &c1, &c2, &sigma, &st);
-
-        if print {
-            // Receiving Costs
-            // (1) Moderator epk
-            // (2) randomness for commitment
-            // (3) commitment
-            // (4) platform signature (element of G1)
-            // (5) ke_2 (Scalar)
-            // (6) Moderator id (4 bytes)
-            // (7) c3 (proxy re-encryption of randonness)
-            
-            let (_, epk, __) = st.clone();
-            let (c2, k_f, ctx, sigma, pk_proc, ke_2, c3) = report.clone();
-            let (u, v) = c3.clone();
-            
-            let mut recv_cost: usize = mem::size_of_val(&epk) + mem::size_of_val(&k_f) + mem::size_of_val(&*c2) + mem::size_of_val(&sigma) + mem::size_of_val(&ke_2) + mem::size_of_val(&ad) + mem::size_of_val(&u) + mem::size_of_val(&v);
-
-
-            println!("Receiving communication cost: {} (bytes)", &recv_cost);
-
-            // Receiving Costs
-            // (1) Moderator id
-            // (2) randomness for commitment
-            // (3) commitment
-            // (4) platform signature (element of G1)
-            // (5) ke_2 (Scalar)
-            // (6) c3 (proxy re-encryption of randonness)
-            
-            let mut storage_cost: usize = mem::size_of_val(&ad) + mem::size_of_val(&k_f) + mem::size_of_val(&*c2) + mem::size_of_val(&sigma) + mem::size_of_val(&ke_2) + mem::size_of_val(&u) + mem::size_of_val(&v);
-            println!("Storage communication cost: {} (bytes)", &storage_cost);
-        }
+    #[test]
+    fn undecryptable_report_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let f = Fixture::<ConstantModPriv>::new(&mut rng);
 
-        reports.push((message, ad, report));
+        let mut report = f.report.clone();
+        report.epoch = 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::UnknownEpoch(1)));
+
+        let mut report = f.report.clone();
+        report.c3_prime.1 = CompressedRistretto([0xff; 32]);
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::InvalidCurvePoint));
     }
 
-    reports
-}
+    #[test]
+    fn wire_encoding_round_trips() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<ConstantModPriv>::new(&mut rng);
 
-pub fn test_report(num_clients: usize, report_docs: &Vec<(String, u32, ReportDoc)>, print: bool) -> Vec<(String, u32, Report)> {
-    let mut reports: Vec<(String, u32, Report)> = Vec::with_capacity(num_clients);
-    for i in 0..num_clients {
-        let (message, moderator_id, rd) = &report_docs[i];
-        let report = Client::report_gen(message, rd);
+        check_encoding(&f.ct);
+        check_encoding(&(f.sigma.clone(), f.st.clone()));
+        check_encoding(&f.rd);
+        check_encoding(&f.report);
+    }
 
+    #[test]
+    fn signed_verdicts_verify() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let mut f = Fixture::<ConstantModPriv>::new(&mut rng);
+        let policy = ExpiryPolicy::default();
 
-        reports.push((message.clone(), *moderator_id, report));
-    }
+        let accepted = f.moderators[0].moderate_signed_with_rng(&f.message, &f.report, &policy, &mut rng).unwrap();
+        assert!(accepted.verdict.is_accept());
+        check_signed_verdict(&accepted, |sv| verify_verdict(&f.pks[0], &f.message, &f.report, sv));
 
-    reports
-}
+        let rejected = f.moderators[0].moderate_signed_with_rng("other", &f.report, &policy, &mut rng).unwrap();
+        assert_eq!(rejected.verdict, Verdict::Reject(ReportingError::BadCommitment));
+        check_signed_verdict(&rejected, |sv| verify_verdict(&f.pks[0], "other", &f.report, sv));
 
+        // Signed by the key of another moderator
+        let (_moderators, pks) = ConstantModPriv::setup_mod_with_rng(&mut f.platform, 1, &mut rng);
+        assert_eq!(verify_verdict(&pks[0], &f.message, &f.report, &accepted), Err(ReportingError::BadProof));
+    }
 
-// moderate(sk_mod, sk_p, m, report)
-pub fn test_moderate(num_clients: usize, reports: &Vec<(String, u32, Report)>, moderators: &Vec<Moderator>, print: bool) {
-    // Moderate messages
-    for i in 0..num_clients {
-        let (message, moderator_id, report) = &reports[i];
-        let j = usize::try_from(*moderator_id).unwrap();
-        let ctx = Moderator::moderate(&moderators[j].sk_enc, &moderators[j].k, &moderators[j].sk_p, &message, &report);
-        if print {
+    // Send, process and read of a new message with the directory pks
+    fn exchange(f: &mut Fixture<ConstantModPriv>, pks: &[PublicKey], rng: &mut StdRng) -> (SentMessage, G1Compressed, ProcessState) {
+        let ct = ConstantModPriv::send_with_rng(&f.client, "m", 0, pks, rng).unwrap();
+        let (sigma, st) = ConstantModPriv::process_with_rng(&mut f.platform, &ct, &ReportContext::new(1, 2, 3), rng).unwrap();
 
-            let (c2, r, ctx, sigma_prime, c3_prime) = report.clone();
-            let (u, v) = c3_prime.clone();
+        (ct, sigma, st)
+    }
 
-            // Judging communication cost
-            // (1) randomness for commitment
-            // (2) commitment
-            // (3) el gamal ct
-            // (4) sigma
-            let mut judging_cost: usize = mem::size_of_val(&r) + mem::size_of_val(&*c2) + mem::size_of_val(&u) + mem::size_of_val(&v) + mem::size_of_val(&sigma_prime);
-            println!("Moderation communication cost: {} (bytes)", &judging_cost);
+    #[test]
+    fn platform_key_rotation_keeps_reports_readable() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let mut f = Fixture::<ConstantModPriv>::new(&mut rng);
+        let rotation = f.platform.rotate_key_with_rng(&mut rng);
+        rotation.verify(&f.pks[0].4).unwrap();
+        let old_pks = f.pks.clone();
+        let new_pks: Vec<PublicKey> = rotation.certificates.iter().map(|certificate| certificate.public_key.clone()).collect();
+
+        // A receiver still on the old directory reads with sigma_previous
+        let (ct, sigma, st) = exchange(&mut f, &old_pks, &mut rng);
+        let previous = st.sigma_previous.clone().unwrap();
+        let (message, _, rd) = ConstantModPriv::read(&f.client, &f.pks, &ct, &sigma, &st).unwrap();
+        assert_eq!(rd.sigma.point, previous.point);
+        let (_, _, rd_new) = ConstantModPriv::read(&f.client, &new_pks, &ct, &sigma, &st).unwrap();
+        assert_eq!(rd_new.sigma.point, sigma.point);
+
+        // Reports from before and during the rotation moderate after the update
+        f.moderators[0].update_platform_key(&rotation).unwrap();
+        assert!(f.moderate(&f.report).is_accept());
+        for rd in [rd, rd_new] {
+            let report = ConstantModPriv::report_gen(&message, &rd).unwrap();
+            assert!(ConstantModPriv::moderate(&f.moderators[0], &message, &report, &ExpiryPolicy::default()).unwrap().is_accept());
         }
+
+        // Once the rotation is finished only the new key signs
+        f.platform.finish_key_rotation();
+        let (ct, sigma, st) = exchange(&mut f, &new_pks, &mut rng);
+        assert!(st.sigma_previous.is_none());
+        assert_eq!(ConstantModPriv::read(&f.client, &f.pks, &ct, &sigma, &st).err(), Some(ReportingError::BadPlatformTag));
+        assert!(ConstantModPriv::read(&f.client, &new_pks, &ct, &sigma, &st).is_ok());
+
+        // and a signature under the previous key no longer reads
+        let old = ProcessState { sigma_previous: Some(previous.clone()), ..st.clone() };
+        assert_eq!(ConstantModPriv::read(&f.client, &new_pks, &ct, &previous, &old).err(), Some(ReportingError::BadPlatformTag));
     }
 
+    #[test]
+    fn forged_key_rotation_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(22);
+        let mut f = Fixture::<ConstantModPriv>::new(&mut rng);
+        ConstantModPriv::setup_mod_with_rng(&mut f.platform, 1, &mut rng);
+        let k_reg = f.pks[0].4.clone();
+        let rotation = f.platform.rotate_key_with_rng(&mut rng);
+
+        // Endorsed by a key other than the previous one
+        let mut other = Platform::new_with_rng(&mut rng);
+        let forged = KeyRotation { endorsement: other.rotate_key_with_rng(&mut rng).endorsement, ..rotation.clone() };
+        assert_eq!(forged.verify(&k_reg).err(), Some(ReportingError::BadCertificate));
+        assert_eq!(f.moderators[0].update_platform_key(&forged).err(), Some(ReportingError::BadCertificate));
+
+        // Certificate of moderator 0 carrying the keys of moderator 1
+        let mut swapped = rotation.clone();
+        swapped.certificates[0].public_key = rotation.certificates[1].public_key.clone();
+        assert_eq!(swapped.verify(&k_reg).err(), Some(ReportingError::BadCertificate));
+        assert_eq!(f.moderators[0].update_platform_key(&swapped).err(), Some(ReportingError::BadCertificate));
+
+        f.moderators[0].update_platform_key(&rotation).unwrap();
+    }
 }
-
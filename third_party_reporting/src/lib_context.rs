@@ -0,0 +1,130 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
+use crate::lib_common::Verdict;
+use crate::lib_error::ReportingError;
+use crate::lib_wire::{FieldWriter, FieldReader};
+
+// Platform context of a message
+//
+// ctx = sender || recipient || conversation || timestamp || message id || sequence
+//
+// each an 8 byte big endian field (lib_wire.rs). The platform fills it in
+// process and every scheme binds this canonical encoding into its platform
+// tag, so a report whose context was changed fails with BadPlatformTag and
+// moderate hands the moderator the context it verified. The sequence number
+// orders the messages of a conversation, so the moderator can check that the
+// reports of a window (lib_window.rs) are consecutive.
+//
+// moderate also takes an ExpiryPolicy: once the tag verified, a report whose
+// timestamp is more than max_age seconds behind the clock is rejected with
+// ExpiredReport rather than accepted. The age is only checked on reports that
+// are otherwise valid, so ExpiredReport never hides a forged report.
+
+// Default retention window of ExpiryPolicy, 90 days in seconds
+pub const MAX_AGE: u64 = 90 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportContext {
+    pub sender: u64, // User id of the sender
+    pub recipient: u64, // User id of the receiver
+    pub conversation: u64, // Conversation the message was sent in
+    pub timestamp: u64, // Time the platform processed the message, seconds since the Unix epoch, stamped by Platform::process
+    pub message_id: u64, // Assigned by Platform::process (lib_replay.rs)
+    pub sequence: u64 // Position in the conversation, assigned by Platform::process (lib_replay.rs)
+}
+
+impl ReportContext {
+    // Context of a message before process, which stamps the timestamp and
+    // assigns the message id and the sequence number
+    pub fn new(sender: u64, recipient: u64, conversation: u64) -> ReportContext {
+        ReportContext { sender, recipient, conversation, timestamp: 0, message_id: 0, sequence: 0 }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = FieldWriter::new();
+        w.u64(self.sender);
+        w.u64(self.recipient);
+        w.u64(self.conversation);
+        w.u64(self.timestamp);
+        w.u64(self.message_id);
+        w.u64(self.sequence);
+
+        w.finish().expect("fixed-size fields")
+    }
+
+    // Inverse of to_bytes, rejects any other encoding
+    pub fn from_bytes(bytes: &[u8]) -> Result<ReportContext, ReportingError> {
+        let mut r = FieldReader::new(bytes);
+        let ctx = ReportContext {
+            sender: r.u64()?,
+            recipient: r.u64()?,
+            conversation: r.u64()?,
+            timestamp: r.u64()?,
+            message_id: r.u64()?,
+            sequence: r.u64()?
+        };
+        r.finish()?;
+
+        Ok(ctx)
+    }
+}
+
+// Current time in seconds since the Unix epoch, for ReportContext::timestamp
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("system clock before the Unix epoch").as_secs()
+}
+
+// How long reports stay valid, against the moderator's clock
+#[derive(Debug, Clone, Copy)]
+pub struct ExpiryPolicy {
+    pub max_age: u64, // Seconds a report stays valid after its platform timestamp
+    pub clock: fn() -> u64 // Current time in seconds since the Unix epoch
+}
+
+impl ExpiryPolicy {
+    pub fn new(max_age: u64) -> ExpiryPolicy {
+        Self::with_clock(max_age, unix_time)
+    }
+
+    pub fn with_clock(max_age: u64, clock: fn() -> u64) -> ExpiryPolicy {
+        ExpiryPolicy { max_age, clock }
+    }
+
+    // Reports never expire
+    pub fn unlimited() -> ExpiryPolicy {
+        Self::new(u64::MAX)
+    }
+
+    // Verdict on a report whose context verified. Timestamps ahead of the
+    // clock count as age 0, the platform attests them
+    pub fn verdict(&self, ctx: &ReportContext) -> Verdict {
+        if (self.clock)().saturating_sub(ctx.timestamp) > self.max_age {
+            return Verdict::Reject(ReportingError::ExpiredReport(ctx.timestamp));
+        }
+
+        Verdict::Accept(*ctx)
+    }
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> ExpiryPolicy {
+        Self::new(MAX_AGE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_expires_after_max_age() {
+        let ctx = ReportContext { timestamp: 100, ..ReportContext::new(1, 2, 3) };
+
+        assert_eq!(ExpiryPolicy::with_clock(10, || 110).verdict(&ctx), Verdict::Accept(ctx));
+        assert_eq!(ExpiryPolicy::with_clock(10, || 111).verdict(&ctx), Verdict::Reject(ReportingError::ExpiredReport(100)));
+
+        // A timestamp ahead of the clock counts as age 0
+        assert_eq!(ExpiryPolicy::with_clock(10, || 0).verdict(&ctx), Verdict::Accept(ctx));
+        assert_eq!(ExpiryPolicy::unlimited().verdict(&ReportContext::new(1, 2, 3)), Verdict::Accept(ReportContext::new(1, 2, 3)));
+    }
+}
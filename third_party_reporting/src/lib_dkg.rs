@@ -0,0 +1,378 @@
+use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use serde::{Serialize, Deserialize};
+use crate::lib_error::ReportingError;
+use crate::lib_gamal as gamal;
+use crate::lib_secret::Secret;
+use crate::lib_threshold::{poly_eval, CommitteeKey, KeyShare};
+
+type Point = RistrettoPoint;
+
+// Distributed key generation for a committee (joint Feldman VSS, Pedersen's DKG)
+//
+// Every participant i deals a random degree t - 1 polynomial f_i:
+//   round 1: broadcast the Feldman commitments C_ik = a_ik * G, send
+//            s_ij = f_i(j) to participant j over a private channel
+//   round 2: j checks s_ij * G = sum(j^k * C_ik) and broadcasts a complaint
+//            against every dealer whose share is missing or invalid
+//   round 3: each accused dealer broadcasts the disputed shares
+// A dealer is disqualified when its commitments are malformed or when it does
+// not answer a complaint with a share that verifies. Complaints from outside
+// 1..=n or from the dealer itself are ignored, answering one would reveal
+// f_i(0) or more points of f_i than the complainers are entitled to. Everything used to decide
+// this is broadcast, so every honest participant ends with the same set QUAL of
+// qualified dealers and
+//   x_j = sum_{i in QUAL} s_ij,  pk = sum_{i in QUAL} C_i0
+// No participant learns x = sum_{i in QUAL} f_i(0) as long as fewer than t
+// participants collude. As with any joint Feldman DKG, a rushing adversary can
+// bias the distribution of pk (Gennaro et al.), which does not affect El Gamal
+// encryption to the committee.
+
+// Round 1 broadcast of dealer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DealerCommitments {
+    pub dealer: u32,
+    pub commitments: Vec<CompressedRistretto> // C_i0, .., C_i(t-1)
+}
+
+// Round 1 private message from dealer to recipient
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DealtShare {
+    pub dealer: u32,
+    pub recipient: u32,
+    pub share: Secret<Scalar> // s_ij = f_i(j)
+}
+
+// Round 2 broadcast, complainer did not receive a valid share from dealer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Complaint {
+    pub dealer: u32,
+    pub complainer: u32
+}
+
+// Round 3 broadcast, dealer reveals the share disputed by complainer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComplaintResponse {
+    pub dealer: u32,
+    pub complainer: u32,
+    pub share: Scalar
+}
+
+// Result of the DKG for one participant
+pub struct DkgOutput {
+    pub key: CommitteeKey, // Joint public key and share verification keys
+    pub share: KeyShare, // x_j
+    pub qualified: Vec<u32> // QUAL, in increasing order
+}
+
+// State of participant index during the DKG
+pub struct DkgParticipant {
+    pub index: u32, // 1 <= index <= n
+    threshold: u32,
+    num_participants: u32,
+    coefficients: Vec<Secret<Scalar>>, // a_i0, .., a_i(t-1) of this participant's polynomial
+    commitments: Vec<Option<Vec<Point>>>, // Commitments of each dealer (index - 1), None if missing or malformed
+    shares: Vec<Option<Secret<Scalar>>> // Verified share from each dealer (index - 1)
+}
+
+impl DkgParticipant {
+    // Fails with InvalidThreshold unless 1 <= t <= n and with
+    // InvalidParticipant unless 1 <= index <= n
+    pub fn new(index: u32, t: u32, n: u32) -> Result<DkgParticipant, ReportingError> {
+        Self::new_with_rng(index, t, n, &mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(index: u32, t: u32, n: u32, rng: &mut R) -> Result<DkgParticipant, ReportingError> {
+        if t == 0 || t > n {
+            return Err(ReportingError::InvalidThreshold(t));
+        }
+        if index == 0 || index > n {
+            return Err(ReportingError::InvalidParticipant(index));
+        }
+
+        Ok(DkgParticipant {
+            index,
+            threshold: t,
+            num_participants: n,
+            coefficients: (0..t).map(|_i| Secret::new(Scalar::random(rng))).collect(),
+            commitments: vec![None; n as usize],
+            shares: (0..n).map(|_i| None).collect()
+        })
+    }
+
+    // Round 1: commitments to broadcast and one share for every participant
+    // (including this one)
+    pub fn deal(&self) -> (DealerCommitments, Vec<DealtShare>) {
+        let commitments = self.coefficients.iter().map(|a| RistrettoPoint::mul_base(a).compress()).collect();
+        let shares = (1..=self.num_participants).map(|j| DealtShare {
+            dealer: self.index,
+            recipient: j,
+            share: Secret::new(poly_eval(&self.coefficients, &Scalar::from(j)))
+        }).collect();
+
+        (DealerCommitments { dealer: self.index, commitments }, shares)
+    }
+
+    // Round 2: records the broadcast commitments and the shares sent to this
+    // participant, returns the complaints to broadcast
+    pub fn receive(&mut self, commitments: &[DealerCommitments], shares: &[DealtShare]) -> Vec<Complaint> {
+        for dc in commitments {
+            let Some(slot) = self.dealer_slot(dc.dealer) else { continue };
+            // A dealer broadcasting twice is treated as malformed
+            if self.commitments[slot].is_some() {
+                self.commitments[slot] = Some(Vec::new());
+                continue;
+            }
+            let points: Result<Vec<Point>, ReportingError> = dc.commitments.iter().map(gamal::decompress).collect();
+            self.commitments[slot] = Some(points.unwrap_or_default());
+        }
+
+        for s in shares.iter().filter(|s| s.recipient == self.index) {
+            let Some(slot) = self.dealer_slot(s.dealer) else { continue };
+            if self.shares[slot].is_none() && self.verify_share(s.dealer, self.index, &s.share) {
+                self.shares[slot] = Some(s.share.clone());
+            }
+        }
+
+        // Dealers with malformed commitments are disqualified by everyone,
+        // complaints are only needed for the shares
+        (1..=self.num_participants)
+            .filter(|i| self.well_formed(*i) && self.shares[(*i - 1) as usize].is_none())
+            .map(|i| Complaint { dealer: i, complainer: self.index })
+            .collect()
+    }
+
+    // Round 3: reveals the shares disputed by complaints against this participant
+    pub fn respond(&self, complaints: &[Complaint]) -> Vec<ComplaintResponse> {
+        complaints.iter()
+            .filter(|c| c.dealer == self.index && valid_complaint(c, self.num_participants))
+            .map(|c| ComplaintResponse {
+                dealer: self.index,
+                complainer: c.complainer,
+                share: poly_eval(&self.coefficients, &Scalar::from(c.complainer))
+            })
+            .collect()
+    }
+
+    // Computes QUAL from the broadcast complaints and responses, then the
+    // joint key and this participant's share
+    pub fn finish(mut self, complaints: &[Complaint], responses: &[ComplaintResponse]) -> Result<DkgOutput, ReportingError> {
+        let mut qualified: Vec<u32> = Vec::new();
+        let n = self.num_participants;
+        for i in 1..=n {
+            if !self.well_formed(i) {
+                continue;
+            }
+
+            let mut answered = true;
+            for c in complaints.iter().filter(|c| c.dealer == i && valid_complaint(c, n)) {
+                let response = responses.iter().find(|r| r.dealer == i && r.complainer == c.complainer);
+                match response {
+                    Some(r) if self.verify_share(i, c.complainer, &r.share) => {
+                        // Take the revealed share in place of the missing one
+                        if c.complainer == self.index {
+                            self.shares[(i - 1) as usize] = Some(Secret::new(r.share));
+                        }
+                    },
+                    _ => answered = false
+                }
+            }
+
+            if answered {
+                qualified.push(i);
+            }
+        }
+
+        if (qualified.len() as u32) < self.threshold {
+            return Err(ReportingError::TooFewDealers(qualified.len() as u32));
+        }
+
+        let mut x_j = Secret::new(Scalar::ZERO);
+        for i in qualified.iter() {
+            // Every dealer in QUAL either sent a valid share or revealed it
+            let s_ij = self.shares[(i - 1) as usize].as_ref().expect("share of a qualified dealer, the complaints returned by receive must be broadcast");
+            *x_j += **s_ij;
+        }
+
+        let mut pk = RistrettoPoint::default();
+        let mut share_pks: Vec<CompressedRistretto> = Vec::with_capacity(self.num_participants as usize);
+        for i in qualified.iter() {
+            pk += self.dealer_commitments(*i)[0];
+        }
+        for j in 1..=self.num_participants {
+            let pk_j: Point = qualified.iter().map(|i| commitment_eval(self.dealer_commitments(*i), &Scalar::from(j))).sum();
+            share_pks.push(pk_j.compress());
+        }
+
+        Ok(DkgOutput {
+            key: CommitteeKey { threshold: self.threshold, pk: pk.compress(), share_pks },
+            share: KeyShare::new(self.index, x_j),
+            qualified
+        })
+    }
+
+    fn dealer_slot(&self, dealer: u32) -> Option<usize> {
+        (1..=self.num_participants).contains(&dealer).then(|| (dealer - 1) as usize)
+    }
+
+    // The dealer broadcast exactly t valid points
+    fn well_formed(&self, dealer: u32) -> bool {
+        match self.dealer_slot(dealer).and_then(|slot| self.commitments[slot].as_ref()) {
+            Some(points) => points.len() == self.threshold as usize,
+            None => false
+        }
+    }
+
+    fn dealer_commitments(&self, dealer: u32) -> &Vec<Point> {
+        self.commitments[(dealer - 1) as usize].as_ref().expect("commitments of a qualified dealer")
+    }
+
+    // s * G = sum(j^k * C_k) for the commitments of dealer
+    fn verify_share(&self, dealer: u32, j: u32, s: &Scalar) -> bool {
+        if !self.well_formed(dealer) {
+            return false;
+        }
+
+        RistrettoPoint::mul_base(s) == commitment_eval(self.dealer_commitments(dealer), &Scalar::from(j))
+    }
+}
+
+// The complainer is one of the n participants other than the dealer
+fn valid_complaint(c: &Complaint, n: u32) -> bool {
+    (1..=n).contains(&c.complainer) && c.complainer != c.dealer
+}
+
+// f(x) * G from the Feldman commitments of f
+fn commitment_eval(commitments: &[Point], x: &Scalar) -> Point {
+    let mut y = RistrettoPoint::default();
+    for c in commitments.iter().rev() {
+        y = y * x + c;
+    }
+
+    y
+}
+
+// Runs the three rounds for n honest participants in process
+pub fn run_dkg(t: u32, n: u32) -> Result<Vec<DkgOutput>, ReportingError> {
+    run_dkg_with_rng(t, n, &mut OsRng)
+}
+
+pub fn run_dkg_with_rng<R: CryptoRng + RngCore>(t: u32, n: u32, rng: &mut R) -> Result<Vec<DkgOutput>, ReportingError> {
+    let mut participants: Vec<DkgParticipant> = (1..=n).map(|i| DkgParticipant::new_with_rng(i, t, n, rng)).collect::<Result<_, _>>()?;
+
+    let (commitments, shares): (Vec<DealerCommitments>, Vec<Vec<DealtShare>>) = participants.iter().map(|p| p.deal()).unzip();
+    let shares: Vec<DealtShare> = shares.into_iter().flatten().collect();
+
+    let complaints: Vec<Complaint> = participants.iter_mut().flat_map(|p| p.receive(&commitments, &shares)).collect();
+    let responses: Vec<ComplaintResponse> = participants.iter().flat_map(|p| p.respond(&complaints)).collect();
+
+    participants.into_iter().map(|p| p.finish(&complaints, &responses)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::lib_domain::{Domain, Role, Usage};
+    use crate::lib_threshold::threshold_decrypt;
+    use crate::lib_wire::SCHEME_BASIC;
+
+    const DOMAIN: Domain = Domain::new(SCHEME_BASIC, Role::Moderator, Usage::Proof);
+    const T: u32 = 2;
+    const N: u32 = 4;
+
+    // Round 1 for N participants, with the share from dealer to recipient off by one
+    fn deal(rng: &mut StdRng, bad: Option<(u32, u32)>) -> (Vec<DkgParticipant>, Vec<DealerCommitments>, Vec<DealtShare>) {
+        let participants: Vec<DkgParticipant> = (1..=N).map(|i| DkgParticipant::new_with_rng(i, T, N, rng).unwrap()).collect();
+        let (commitments, shares): (Vec<DealerCommitments>, Vec<Vec<DealtShare>>) = participants.iter().map(|p| p.deal()).unzip();
+        let mut shares: Vec<DealtShare> = shares.into_iter().flatten().collect();
+        for s in shares.iter_mut().filter(|s| Some((s.dealer, s.recipient)) == bad) {
+            *s.share += Scalar::ONE;
+        }
+
+        (participants, commitments, shares)
+    }
+
+    // The joint key decrypts with the shares of members
+    fn decrypts(outputs: &[DkgOutput], members: &[usize], rng: &mut StdRng) -> bool {
+        let key = &outputs[0].key;
+        let m = RistrettoPoint::random(rng);
+        let ct = gamal::elgamal_enc_with_rng(&gamal::decompress(&key.pk).unwrap(), &m, rng);
+        let shares: Vec<_> = members.iter().map(|j| outputs[*j].share.partial_decrypt_with_rng(&DOMAIN, &ct.0, rng)).collect();
+
+        threshold_decrypt(&DOMAIN, key, &ct, &shares) == Ok(m)
+    }
+
+    #[test]
+    fn bad_share_disqualifies_dealer() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (mut participants, commitments, shares) = deal(&mut rng, Some((2, 3)));
+
+        let complaints: Vec<Complaint> = participants.iter_mut().flat_map(|p| p.receive(&commitments, &shares)).collect();
+        assert_eq!(complaints, vec![Complaint { dealer: 2, complainer: 3 }]);
+
+        // Dealer 2 stands by the bad share
+        let mut responses: Vec<ComplaintResponse> = participants.iter().flat_map(|p| p.respond(&complaints)).collect();
+        assert_eq!(responses.len(), 1);
+        responses[0].share += Scalar::ONE;
+
+        let outputs: Vec<DkgOutput> = participants.into_iter().map(|p| p.finish(&complaints, &responses).unwrap()).collect();
+        for output in outputs.iter() {
+            assert_eq!(output.qualified, vec![1, 3, 4]);
+            assert_eq!(output.key.pk, outputs[0].key.pk);
+        }
+        assert!(decrypts(&outputs, &[0, 2], &mut rng));
+        assert!(decrypts(&outputs, &[1, 3], &mut rng));
+    }
+
+    #[test]
+    fn answered_complaint_keeps_dealer() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (mut participants, commitments, shares) = deal(&mut rng, Some((2, 3)));
+
+        let complaints: Vec<Complaint> = participants.iter_mut().flat_map(|p| p.receive(&commitments, &shares)).collect();
+        let responses: Vec<ComplaintResponse> = participants.iter().flat_map(|p| p.respond(&complaints)).collect();
+
+        let outputs: Vec<DkgOutput> = participants.into_iter().map(|p| p.finish(&complaints, &responses).unwrap()).collect();
+        for output in outputs.iter() {
+            assert_eq!(output.qualified, vec![1, 2, 3, 4]);
+        }
+        // Participant 3 took the revealed share
+        assert!(decrypts(&outputs, &[2, 3], &mut rng));
+        assert!(decrypts(&outputs, &[0, 1], &mut rng));
+    }
+
+    #[test]
+    fn bogus_complaints_are_ignored() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (mut participants, commitments, shares) = deal(&mut rng, None);
+
+        let mut complaints: Vec<Complaint> = participants.iter_mut().flat_map(|p| p.receive(&commitments, &shares)).collect();
+        assert!(complaints.is_empty());
+        // Complainer 0 would get f_1(0), the dealer and complainers past n
+        // would get points of f_1 nobody holds
+        complaints.extend([0, 1, N + 1, u32::MAX].map(|complainer| Complaint { dealer: 1, complainer }));
+
+        let responses: Vec<ComplaintResponse> = participants.iter().flat_map(|p| p.respond(&complaints)).collect();
+        assert!(responses.is_empty());
+
+        let outputs: Vec<DkgOutput> = participants.into_iter().map(|p| p.finish(&complaints, &responses).unwrap()).collect();
+        for output in outputs.iter() {
+            assert_eq!(output.qualified, vec![1, 2, 3, 4]);
+        }
+        assert!(decrypts(&outputs, &[0, 3], &mut rng));
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let mut rng = StdRng::seed_from_u64(4);
+        assert_eq!(DkgParticipant::new_with_rng(1, 0, N, &mut rng).err(), Some(ReportingError::InvalidThreshold(0)));
+        assert_eq!(DkgParticipant::new_with_rng(1, N + 1, N, &mut rng).err(), Some(ReportingError::InvalidThreshold(N + 1)));
+        assert_eq!(DkgParticipant::new_with_rng(0, T, N, &mut rng).err(), Some(ReportingError::InvalidParticipant(0)));
+        assert_eq!(DkgParticipant::new_with_rng(N + 1, T, N, &mut rng).err(), Some(ReportingError::InvalidParticipant(N + 1)));
+        assert_eq!(run_dkg_with_rng(5, 3, &mut rng).err(), Some(ReportingError::InvalidThreshold(5)));
+    }
+}
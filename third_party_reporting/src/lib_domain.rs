@@ -0,0 +1,77 @@
+use crate::lib_wire::*;
+
+// Domain separation for every commitment, MAC, hash-to-curve, KDF, proof, verdict, registration, rotation and revocation call
+//
+// label = "TPR-v" DOMAIN_VERSION "/" scheme "/" role "/" usage
+//
+// e.g. "TPR-v1/constant_mod_priv/platform/hash-to-curve". MACs and
+// commitments absorb len(label) (4 bytes, big endian) || label before the
+// message and hash_to_curve uses the label as its DST, so a value computed
+// for one scheme, role, usage or version never verifies under another one,
+// even when the schemes share keys.
+
+pub const DOMAIN_VERSION: u8 = 1;
+
+// Protocol role computing the value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Sender,
+    Platform,
+    Moderator
+}
+
+// What the value is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    Commitment, // com_commit / com_open
+    PlatformTag, // Platform MAC over the report material
+    HashToCurve, // DST of hash_to_curve
+    Kdf, // Key derivation (leaf keys of lib_merkle)
+    Proof, // Fiat-Shamir challenge of a zero knowledge proof
+    Verdict, // Proof of decryption signing a moderator verdict
+    Registration, // Proof of possession and certificate of a moderator registration
+    Rotation, // Signature authorizing a moderator or platform key rotation
+    Revocation // Platform signature on the revocation list
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Domain {
+    pub scheme: u8, // Scheme identifier from lib_wire
+    pub role: Role,
+    pub usage: Usage
+}
+
+impl Domain {
+    pub const fn new(scheme: u8, role: Role, usage: Usage) -> Domain {
+        Domain { scheme, role, usage }
+    }
+
+    pub fn label(&self) -> Vec<u8> {
+        let scheme = match self.scheme {
+            SCHEME_PLAIN => "plain",
+            SCHEME_BASIC => "basic",
+            SCHEME_MOD_PRIV => "mod_priv",
+            SCHEME_CONSTANT_MOD_PRIV => "constant_mod_priv",
+            SCHEME_RSA_BASIC => "rsa_basic",
+            _ => "unknown"
+        };
+        let role = match self.role {
+            Role::Sender => "sender",
+            Role::Platform => "platform",
+            Role::Moderator => "moderator"
+        };
+        let usage = match self.usage {
+            Usage::Commitment => "commitment",
+            Usage::PlatformTag => "tag",
+            Usage::HashToCurve => "hash-to-curve",
+            Usage::Kdf => "kdf",
+            Usage::Proof => "proof",
+            Usage::Verdict => "verdict",
+            Usage::Registration => "registration",
+            Usage::Rotation => "rotation",
+            Usage::Revocation => "revocation"
+        };
+
+        format!("TPR-v{}/{}/{}/{}", DOMAIN_VERSION, scheme, role, usage).into_bytes()
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+// Errors returned by the operations of every scheme
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportingError {
+    BadCommitment, // com_open failed on (c2, m, k_f)
+    BadPlatformTag, // Platform MAC / signature did not verify
+    BadModeratorKey, // Masked moderator key does not match the chosen moderator
+    DecryptionFailure, // Authenticated decryption failed
+    MalformedEncoding, // Bytes could not be decoded, or a field is too long to encode
+    UnknownModerator(u32), // No moderator registered under this id
+    InvalidCurvePoint, // Encoding is not a valid group element
+    UnsupportedVersion(u8), // Wire format version this crate cannot decode
+    WrongScheme(u8), // Wire message belongs to another scheme
+    InsufficientShares(u32), // Fewer than t valid partial decryptions, carries the number of valid ones
+    BadProof, // Zero knowledge proof did not verify
+    TooFewDealers(u32), // DKG ended with fewer than t qualified dealers, carries the number of qualified ones
+    BadVerdict, // Signed verdict contradicts the report it is about
+    BadCertificate, // Registration certificate or revocation list does not verify under k_reg, or the certificate does not match the keys
+    RevokedModerator(u32), // Moderator id was revoked by the platform
+    StaleRevocationList(u32), // Revocation list older than the one already accepted, carries its version
+    ExpiredEpoch(u32), // Key epoch is past its grace period or older than the current key
+    UnknownEpoch(u32), // Key epoch is ahead of the current key
+    DuplicateMessage(u64), // Commitment already processed in this conversation, carries the conversation id
+    ExpiredReport(u64), // Report verified but is older than the expiry policy allows, carries its platform timestamp
+    EmptyWindow, // Window report without any report
+    NotAdjacent(u64), // Report of a window does not follow the previous one in its conversation, carries its sequence number
+    BadSegment(u32), // No segment revealed, segment index out of range, not increasing, or of a report that does not open the whole message
+    InvalidThreshold(u32), // Threshold t outside 1 <= t <= n for a committee of n members, carries t
+    InvalidParticipant(u32), // DKG participant index outside 1 <= index <= n
+    InvalidCapacity, // Replay cache capacity of 0
+}
+
+impl fmt::Display for ReportingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportingError::BadCommitment => write!(f, "commitment does not open to the message"),
+            ReportingError::BadPlatformTag => write!(f, "platform tag does not verify"),
+            ReportingError::BadModeratorKey => write!(f, "masked moderator key does not match the chosen moderator"),
+            ReportingError::DecryptionFailure => write!(f, "decryption failed"),
+            ReportingError::MalformedEncoding => write!(f, "malformed encoding"),
+            ReportingError::UnknownModerator(id) => write!(f, "unknown moderator id {}", id),
+            ReportingError::InvalidCurvePoint => write!(f, "invalid curve point"),
+            ReportingError::UnsupportedVersion(v) => write!(f, "unsupported wire format version {}", v),
+            ReportingError::WrongScheme(id) => write!(f, "message belongs to scheme {}", id),
+            ReportingError::InsufficientShares(k) => write!(f, "only {} valid partial decryptions, below the threshold", k),
+            ReportingError::BadProof => write!(f, "zero knowledge proof does not verify"),
+            ReportingError::TooFewDealers(k) => write!(f, "only {} qualified dealers, below the threshold", k),
+            ReportingError::BadVerdict => write!(f, "verdict contradicts the report"),
+            ReportingError::BadCertificate => write!(f, "registration certificate does not verify"),
+            ReportingError::RevokedModerator(id) => write!(f, "moderator id {} is revoked", id),
+            ReportingError::StaleRevocationList(v) => write!(f, "revocation list version {} is older than the current one", v),
+            ReportingError::ExpiredEpoch(e) => write!(f, "key epoch {} has expired", e),
+            ReportingError::UnknownEpoch(e) => write!(f, "unknown key epoch {}", e),
+            ReportingError::DuplicateMessage(c) => write!(f, "commitment already processed in conversation {}", c),
+            ReportingError::ExpiredReport(t) => write!(f, "report with timestamp {} has expired", t),
+            ReportingError::EmptyWindow => write!(f, "window report is empty"),
+            ReportingError::NotAdjacent(s) => write!(f, "report with sequence number {} does not follow the previous report of the window", s),
+            ReportingError::BadSegment(i) => write!(f, "segment {} cannot be disclosed", i),
+            ReportingError::InvalidThreshold(t) => write!(f, "threshold {} is not between 1 and the number of committee members", t),
+            ReportingError::InvalidParticipant(i) => write!(f, "participant index {} is not between 1 and the number of participants", i),
+            ReportingError::InvalidCapacity => write!(f, "replay cache capacities must be at least 1"),
+        }
+    }
+}
+
+impl ReportingError {
+    // Stable code of the error, signed in verdicts (lib_verdict.rs) instead of
+    // its text. Codes are never renumbered or reused
+    pub fn code(&self) -> u32 {
+        match self {
+            ReportingError::BadCommitment => 1,
+            ReportingError::BadPlatformTag => 2,
+            ReportingError::BadModeratorKey => 3,
+            ReportingError::DecryptionFailure => 4,
+            ReportingError::MalformedEncoding => 5,
+            ReportingError::UnknownModerator(_) => 6,
+            ReportingError::InvalidCurvePoint => 7,
+            ReportingError::UnsupportedVersion(_) => 8,
+            ReportingError::WrongScheme(_) => 9,
+            ReportingError::InsufficientShares(_) => 10,
+            ReportingError::BadProof => 11,
+            ReportingError::TooFewDealers(_) => 12,
+            ReportingError::BadVerdict => 13,
+            ReportingError::InvalidThreshold(_) => 14,
+            ReportingError::InvalidParticipant(_) => 15,
+            ReportingError::BadCertificate => 16,
+            ReportingError::RevokedModerator(_) => 17,
+            ReportingError::StaleRevocationList(_) => 18,
+            ReportingError::ExpiredEpoch(_) => 19,
+            ReportingError::UnknownEpoch(_) => 20,
+            ReportingError::DuplicateMessage(_) => 21,
+            ReportingError::InvalidCapacity => 22,
+            ReportingError::ExpiredReport(_) => 23,
+            ReportingError::EmptyWindow => 24,
+            ReportingError::NotAdjacent(_) => 25,
+            ReportingError::BadSegment(_) => 26,
+        }
+    }
+
+    // Value carried by the error, 0 if it carries none
+    pub fn value(&self) -> u64 {
+        match self {
+            ReportingError::UnsupportedVersion(x) | ReportingError::WrongScheme(x) => u64::from(*x),
+            ReportingError::UnknownModerator(x) | ReportingError::InsufficientShares(x) | ReportingError::TooFewDealers(x)
+                | ReportingError::RevokedModerator(x) | ReportingError::StaleRevocationList(x) | ReportingError::ExpiredEpoch(x)
+                | ReportingError::UnknownEpoch(x) | ReportingError::BadSegment(x) | ReportingError::InvalidThreshold(x)
+                | ReportingError::InvalidParticipant(x) => u64::from(*x),
+            ReportingError::DuplicateMessage(x) | ReportingError::ExpiredReport(x) | ReportingError::NotAdjacent(x) => *x,
+            _ => 0
+        }
+    }
+}
+
+impl std::error::Error for ReportingError {}
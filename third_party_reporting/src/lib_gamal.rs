@@ -1,13 +1,14 @@
 use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 use sha2::{Sha256, Digest};
 use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
 use curve25519_dalek::scalar::Scalar;
-use rand::rngs;
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit},
     Aes256Gcm, Nonce
 };
 use std::mem;
+use crate::lib_error::ReportingError;
 
 type Ciphertext = (Point, Point);
 use generic_array::typenum::U12;
@@ -22,81 +23,63 @@ pub fn size_of_el_gamal_ct(ct: ((CompressedRistretto, CompressedRistretto), Vec<
 }
 
 // Proxy Re-Encryption El Gamal Scheme
-pub(crate) fn pre_elgamal_enc(pk: &Point, m: &Point) -> Ciphertext {
-    let r = Scalar::random(&mut OsRng);
+#[cfg(feature = "mod_priv")]
+pub(crate) fn pre_elgamal_enc<R: CryptoRng + RngCore>(pk: &Point, m: &Point, rng: &mut R) -> Ciphertext {
+    let r = Scalar::random(rng);
 
-    let c1 = RistrettoPoint::mul_base(&r) + m;
-    let c2 = &r * pk;
-
-    (c1, c2) // (g^(r) * m, g^(x*r)
+    pre_elgamal_enc_with_r(pk, m, &r)
 }
 
-pub(crate) fn pre_enc(pk: &Point, m: &Vec<u8>) -> (Ciphertext, Vec<u8>, Nonce<U12>) {
-    // Choose random point p to encrypt with ElGamal. H(p) is the symmetric key
-    // (we model H as a random oracle)
-    let p = Point::random(&mut OsRng);
-    let ct = pre_elgamal_enc(pk, &p);
-
-    let pt = m;
-
-    let mut hasher = Sha256::new();
-    Digest::update(&mut hasher, pzip(p));
-    let k = hasher.finalize();
-
-    let cipher = Aes256Gcm::new(&k);
-    let nonce = Aes256Gcm::generate_nonce(&mut rngs::OsRng);
-    let sym_ct = cipher.encrypt(&nonce, pt.as_slice());
+// Encryption with caller chosen randomness r, for proofs about the ciphertext
+#[cfg(any(feature = "mod_priv", feature = "constant_mod_priv"))]
+pub(crate) fn pre_elgamal_enc_with_r(pk: &Point, m: &Point, r: &Scalar) -> Ciphertext {
+    let c1 = RistrettoPoint::mul_base(r) + m;
+    let c2 = r * pk;
 
-    let sym_ct = match sym_ct {
-        Ok(ct) => ct,
-        Err(_) => panic!("Symmetric encryption failed")
-    };
-
-
-    (ct, sym_ct, nonce)
+    (c1, c2) // (g^(r) * m, g^(x*r)
 }
 
+#[cfg(any(feature = "mod_priv", feature = "constant_mod_priv"))]
 pub(crate) fn pre_re_enc(ct: &Ciphertext, rk: &Scalar) -> Ciphertext {
-    (ct.0, *&rk * ct.1) // (m * g^r, g^((x*r*s) * y * 1/(x*s)))
+    (ct.0, rk * ct.1) // (m * g^r, g^((x*r*s) * y * 1/(x*s)))
 }
 
+#[cfg(any(feature = "mod_priv", feature = "constant_mod_priv"))]
 pub(crate) fn pre_elgamal_dec(sk: &Scalar, ct: &Ciphertext) -> Point {
     ct.0 - (ct.1 * sk.invert()) // g^r * m / g^((y*r)*(1/y))
 }
 
 
-pub(crate) fn pre_dec(sk: &Scalar, ct: &(Ciphertext, Vec<u8>), nonce: &Nonce<U12>) -> Vec<u8> {
-    let p = pre_elgamal_dec(sk, &ct.0);
-
-    let mut hasher = Sha256::new();
-    Digest::update(&mut hasher, pzip(p));
-    let k = hasher.finalize();
-
-    let cipher = Aes256Gcm::new(&k);
-    let pt = cipher.decrypt(&nonce, ct.1.as_ref()).ok();
-
-    let m: Vec<u8> = pt.unwrap();
+// El Gamal Scheme
 
-    m
+// Decompress a point received from another party
+pub(crate) fn decompress(p: &CompressedRistretto) -> Result<Point, ReportingError> {
+    p.decompress().ok_or(ReportingError::InvalidCurvePoint)
 }
 
-// El Gamal Scheme
-
 
 pub(crate) fn pzip(p: Point) -> [u8; 32] {
     p.compress().to_bytes()
 }
 
 pub fn elgamal_keygen() -> (Scalar, Point) {
-    let x: Scalar = Scalar::random(&mut OsRng);
+    elgamal_keygen_with_rng(&mut OsRng)
+}
+
+pub fn elgamal_keygen_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> (Scalar, Point) {
+    let x: Scalar = Scalar::random(rng);
     let h: Point = RistrettoPoint::mul_base(&x);
     (x, h)
 }
 
 pub fn elgamal_enc(pk: &Point, m: &Point) -> Ciphertext {
-    let r = Scalar::random(&mut OsRng);
+    elgamal_enc_with_rng(pk, m, &mut OsRng)
+}
+
+pub fn elgamal_enc_with_rng<R: CryptoRng + RngCore>(pk: &Point, m: &Point, rng: &mut R) -> Ciphertext {
+    let r = Scalar::random(rng);
     let c1 = RistrettoPoint::mul_base(&r);
-    let c2 = &r*pk + m;
+    let c2 = r*pk + m;
 
     (c1, c2)
 }
@@ -105,15 +88,20 @@ pub fn elgamal_enc(pk: &Point, m: &Point) -> Ciphertext {
 //      sk: a compressed Scalar
 //      ct:  a compressed (Point, Point) ciphertext
 // Returns the decrypted chosen mask
+#[cfg(feature = "basic")]
 pub(crate) fn elgamal_dec(sk: &Scalar, ct: &Ciphertext) -> Point {
     ct.1 + (Scalar::ZERO - sk) * ct.0 // m * g^(x*r) / g^(r/x)
 }
 
-pub fn encrypt(pk: &Point, m: &Vec<u8>) -> (Ciphertext, Vec<u8>, Nonce<U12>) {
+pub fn encrypt(pk: &Point, m: &[u8]) -> (Ciphertext, Vec<u8>, Nonce<U12>) {
+    encrypt_with_rng(pk, m, &mut OsRng)
+}
+
+pub fn encrypt_with_rng<R: CryptoRng + RngCore>(pk: &Point, m: &[u8], rng: &mut R) -> (Ciphertext, Vec<u8>, Nonce<U12>) {
     // Choose random point p to encrypt with ElGamal. H(p) is the symmetric key
     // (we model H as a random oracle)
-    let p = Point::random(&mut OsRng);
-    let ct = elgamal_enc(pk, &p);
+    let p = Point::random(rng);
+    let ct = elgamal_enc_with_rng(pk, &p, rng);
 
     let pt = m;
 
@@ -122,8 +110,8 @@ pub fn encrypt(pk: &Point, m: &Vec<u8>) -> (Ciphertext, Vec<u8>, Nonce<U12>) {
     let k = hasher.finalize();
 
     let cipher = Aes256Gcm::new(&k);
-    let nonce = Aes256Gcm::generate_nonce(&mut rngs::OsRng);
-    let sym_ct = cipher.encrypt(&nonce, pt.as_slice());
+    let nonce = Aes256Gcm::generate_nonce(&mut *rng);
+    let sym_ct = cipher.encrypt(&nonce, pt);
 
     let sym_ct = match sym_ct {
         Ok(ct) => ct,
@@ -132,18 +120,3 @@ pub fn encrypt(pk: &Point, m: &Vec<u8>) -> (Ciphertext, Vec<u8>, Nonce<U12>) {
 
     (ct, sym_ct, nonce)
 }
-
-pub(crate) fn decrypt(sk: &Scalar, ct: &(Ciphertext, Vec<u8>), nonce: &Nonce<U12>) -> Vec<u8> {
-    let p = elgamal_dec(sk, &ct.0);
-
-    let mut hasher = Sha256::new();
-    Digest::update(&mut hasher, pzip(p));
-    let k = hasher.finalize();
-
-    let cipher = Aes256Gcm::new(&k);
-    let pt = cipher.decrypt(&nonce, ct.1.as_ref()).ok();
-
-    let m: Vec<u8> = pt.unwrap();
-
-    m
-}
@@ -0,0 +1,378 @@
+use std::mem;
+use hmac::Mac;
+use sha2::{Sha256, Digest};
+use serde::{Serialize, Deserialize};
+use crate::lib_common::{HmacSha256, domain_mac, ct_eq_bytes};
+use crate::lib_domain::{Domain, Usage};
+use crate::lib_error::ReportingError;
+use crate::lib_secret::Secret;
+use crate::lib_wire::{FieldWriter, FieldReader};
+
+// Redactable message commitments
+//
+// The message is split into n segments (segments) and committed to with a
+// Merkle tree whose leaves have their own randomness:
+//
+// r_i    = HMAC(k_f, label_kdf || i)
+// leaf_i = HMAC(r_i, label || i || segment_i)
+// node   = H(len(label) || label || 1 || left || right), the leaves split as in RFC 6962
+// c2     = H(len(label) || label || 2 || n || root)
+//
+// com_commit and com_open (lib_common.rs) compute c2 from k_f and the whole
+// message, so ccae_enc and ccae_dec of every scheme commit to the tree. A
+// report opens c2 (Opening) either with k_f, revealing the whole message, or
+// with a Disclosure of chosen segments: their index, text and r_i, and the
+// roots of the subtrees without a revealed segment. The r_i of a hidden
+// segment is never revealed, so its leaf hides it. A disclosure does reveal
+// n and the positions of the revealed segments.
+
+// Segments of a message: words with the whitespace following them, so that
+// they concatenate to the message. The empty message is one empty segment
+pub fn segments(message: &str) -> Vec<&str> {
+    if message.is_empty() {
+        return vec![message];
+    }
+
+    message.split_inclusive(char::is_whitespace).collect()
+}
+
+// Revealed segments of a message with the rest of its tree
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disclosure {
+    pub num_segments: u32, // n
+    pub segments: Vec<(u32, String, [u8; 32])>, // (i, segment_i, r_i) by increasing i
+    pub hidden: Vec<[u8; 32]> // Roots of the subtrees without a revealed segment, left to right
+}
+
+// How a report opens its commitment c2
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Opening {
+    Message(Secret<[u8; 32]>), // k_f, opens c2 to the whole message
+    Segments(Disclosure) // Opens c2 to the revealed segments only
+}
+
+impl Disclosure {
+    // The revealed segments, concatenated
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|(_i, segment, _r)| segment.as_str()).collect()
+    }
+
+    // Checks that the revealed segments, at least one, and hidden subtrees
+    // rebuild c2
+    pub(crate) fn open(&self, domain: &Domain, c2: &[u8]) -> bool {
+        let n = self.num_segments;
+        let sorted = self.segments.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        if n == 0 || self.segments.is_empty() || !sorted || self.segments.last().is_some_and(|(i, _, _)| *i >= n) {
+            return false;
+        }
+
+        let leaves: Vec<(u32, [u8; 32])> = self.segments.iter().map(|(i, segment, r)| (*i, leaf(domain, r, *i, segment))).collect();
+        let mut hidden = self.hidden.iter();
+        let root = match rebuild(domain, 0, n, &leaves, &mut hidden) {
+            Some(root) => root,
+            None => return false
+        };
+
+        hidden.next().is_none() && ct_eq_bytes(&seal(domain, n, &root), c2)
+    }
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.num_segments);
+        w.u32(self.segments.len() as u32);
+        for (i, segment, r) in self.segments.iter() {
+            w.u32(*i);
+            w.bytes(segment.as_bytes());
+            w.bytes(r);
+        }
+        w.u32(self.hidden.len() as u32);
+        for h in self.hidden.iter() {
+            w.bytes(h);
+        }
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Disclosure, ReportingError> {
+        let num_segments = r.u32()?;
+        let count = r.u32()?;
+        let segments = (0..count).map(|_i| Ok((r.u32()?, r.string()?, r.array()?))).collect::<Result<_, ReportingError>>()?;
+        let count = r.u32()?;
+        let hidden = (0..count).map(|_i| r.array()).collect::<Result<_, ReportingError>>()?;
+
+        Ok(Disclosure { num_segments, segments, hidden })
+    }
+}
+
+impl Opening {
+    // Checks that the opening opens c2 to message, for a disclosure the
+    // concatenation of its revealed segments
+    pub(crate) fn open(&self, domain: &Domain, c2: &[u8], message: &str) -> bool {
+        match self {
+            Opening::Message(k_f) => ct_eq_bytes(&commit(domain, &k_f[..], message), c2),
+            Opening::Segments(disclosure) => disclosure.text() == message && disclosure.open(domain, c2)
+        }
+    }
+
+    // Disclosure of the segments of message with indices reveal, given by
+    // increasing index and not empty. Only an opening to the whole message can
+    // be disclosed
+    pub(crate) fn disclose(&self, domain: &Domain, message: &str, reveal: &[u32]) -> Result<Opening, ReportingError> {
+        let k_f = match self {
+            Opening::Message(k_f) => k_f,
+            Opening::Segments(_) => return Err(ReportingError::BadSegment(reveal.first().copied().unwrap_or(0)))
+        };
+
+        if reveal.is_empty() {
+            return Err(ReportingError::BadSegment(0));
+        }
+
+        let segments = segments(message);
+        let n = segments.len();
+        for (j, i) in reveal.iter().enumerate() {
+            if *i as usize >= n || (j > 0 && reveal[j - 1] >= *i) {
+                return Err(ReportingError::BadSegment(*i));
+            }
+        }
+
+        let leaves = leaves(domain, &k_f[..], &segments);
+        let mut hidden = Vec::new();
+        prove(domain, &leaves, 0, reveal, &mut hidden);
+
+        let segments = reveal.iter().map(|i| (*i, segments[*i as usize].to_string(), *leaf_key(domain, &k_f[..], *i))).collect();
+
+        Ok(Opening::Segments(Disclosure { num_segments: n as u32, segments, hidden }))
+    }
+
+    pub(crate) fn write_fields(&self, w: &mut FieldWriter) {
+        match self {
+            Opening::Message(k_f) => {
+                w.u32(0);
+                w.bytes(&k_f[..]);
+            },
+            Opening::Segments(disclosure) => {
+                w.u32(1);
+                disclosure.write_fields(w);
+            }
+        }
+    }
+
+    pub(crate) fn read_fields(r: &mut FieldReader) -> Result<Opening, ReportingError> {
+        match r.u32()? {
+            0 => Ok(Opening::Message(Secret::new(r.array()?))),
+            1 => Ok(Opening::Segments(Disclosure::read_fields(r)?)),
+            _ => Err(ReportingError::MalformedEncoding)
+        }
+    }
+
+    // Communication cost (bytes): k_f, or for a disclosure
+    // (1) n (2) index, segment and r_i of every revealed segment (3) hidden subtree roots
+    pub fn cost(&self) -> usize {
+        match self {
+            Opening::Message(k_f) => mem::size_of_val(&**k_f),
+            Opening::Segments(Disclosure { num_segments, segments, hidden }) => {
+                mem::size_of_val(num_segments)
+                    + segments.iter().map(|(i, segment, r)| mem::size_of_val(i) + segment.len() + mem::size_of_val(r)).sum::<usize>()
+                    + mem::size_of_val(&**hidden)
+            }
+        }
+    }
+}
+
+// c2 for the whole message
+pub(crate) fn commit(domain: &Domain, k_f: &[u8], message: &str) -> [u8; 32] {
+    let leaves = leaves(domain, k_f, &segments(message));
+
+    seal(domain, leaves.len() as u32, &root(domain, &leaves))
+}
+
+fn leaf_key(domain: &Domain, k_f: &[u8], i: u32) -> Secret<[u8; 32]> {
+    let mut mac = domain_mac::<HmacSha256>(&Domain { usage: Usage::Kdf, ..*domain }, k_f);
+    mac.update(&i.to_be_bytes());
+
+    Secret::new(mac.finalize().into_bytes().into())
+}
+
+fn leaf(domain: &Domain, r_i: &[u8], i: u32, segment: &str) -> [u8; 32] {
+    let mut mac = domain_mac::<HmacSha256>(domain, r_i);
+    mac.update(&i.to_be_bytes());
+    mac.update(segment.as_bytes());
+
+    mac.finalize().into_bytes().into()
+}
+
+fn leaves(domain: &Domain, k_f: &[u8], segments: &[&str]) -> Vec<[u8; 32]> {
+    segments.iter().enumerate().map(|(i, segment)| leaf(domain, &leaf_key(domain, k_f, i as u32)[..], i as u32, segment)).collect()
+}
+
+// H(len(label) || label || prefix || parts)
+fn hash(domain: &Domain, prefix: u8, parts: &[&[u8]]) -> [u8; 32] {
+    let label = domain.label();
+    let mut hasher = Sha256::new();
+    hasher.update((label.len() as u32).to_be_bytes());
+    hasher.update(&label);
+    hasher.update([prefix]);
+    for part in parts {
+        hasher.update(part);
+    }
+
+    hasher.finalize().into()
+}
+
+fn node(domain: &Domain, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hash(domain, 1, &[left, right])
+}
+
+fn seal(domain: &Domain, n: u32, root: &[u8; 32]) -> [u8; 32] {
+    hash(domain, 2, &[&n.to_be_bytes(), root])
+}
+
+// Size of the left subtree over n >= 2 leaves, the largest power of two below n
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+fn root(domain: &Domain, leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.len() == 1 {
+        return leaves[0];
+    }
+
+    let k = split(leaves.len());
+    node(domain, &root(domain, &leaves[..k]), &root(domain, &leaves[k..]))
+}
+
+// Roots of the subtrees of leaves (the first at index offset) without an index
+// in reveal, left to right
+fn prove(domain: &Domain, leaves: &[[u8; 32]], offset: u32, reveal: &[u32], hidden: &mut Vec<[u8; 32]>) {
+    let end = offset + leaves.len() as u32;
+    if !reveal.iter().any(|i| (offset..end).contains(i)) {
+        hidden.push(root(domain, leaves));
+        return;
+    }
+    if leaves.len() == 1 {
+        return;
+    }
+
+    let k = split(leaves.len());
+    prove(domain, &leaves[..k], offset, reveal, hidden);
+    prove(domain, &leaves[k..], offset + k as u32, reveal, hidden);
+}
+
+// Root of the subtree over len leaves from index offset, given its revealed
+// leaves, taking the roots of hidden subtrees from hidden
+fn rebuild<'a>(domain: &Domain, offset: u32, len: u32, leaves: &[(u32, [u8; 32])], hidden: &mut impl Iterator<Item = &'a [u8; 32]>) -> Option<[u8; 32]> {
+    if leaves.is_empty() {
+        return hidden.next().copied();
+    }
+    if len == 1 {
+        return Some(leaves[0].1);
+    }
+
+    let k = split(len as usize) as u32;
+    let mid = leaves.partition_point(|(i, _)| *i < offset + k);
+    let left = rebuild(domain, offset, k, &leaves[..mid], hidden)?;
+    let right = rebuild(domain, offset + k, len - k, &leaves[mid..], hidden)?;
+
+    Some(node(domain, &left, &right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib_domain::Role;
+    use crate::lib_wire::SCHEME_BASIC;
+
+    const DOMAIN: Domain = Domain::new(SCHEME_BASIC, Role::Sender, Usage::Commitment);
+    const MESSAGE: &str = "one two three four five";
+    const K_F: [u8; 32] = [7; 32];
+
+    fn disclose(reveal: &[u32]) -> Disclosure {
+        match Opening::Message(Secret::new(K_F)).disclose(&DOMAIN, MESSAGE, reveal).unwrap() {
+            Opening::Segments(disclosure) => disclosure,
+            Opening::Message(_) => unreachable!("disclose returns a disclosure")
+        }
+    }
+
+    #[test]
+    fn every_disclosure_opens() {
+        let c2 = commit(&DOMAIN, &K_F, MESSAGE);
+        for mask in 1..(1u32 << 5) {
+            let reveal: Vec<u32> = (0..5).filter(|i| mask & (1 << i) != 0).collect();
+            let disclosure = disclose(&reveal);
+            let text: String = reveal.iter().map(|i| segments(MESSAGE)[*i as usize]).collect();
+
+            assert!(disclosure.open(&DOMAIN, &c2));
+            assert!(Opening::Segments(disclosure.clone()).open(&DOMAIN, &c2, &text));
+            assert_eq!(Opening::Segments(disclosure).open(&DOMAIN, &c2, MESSAGE), reveal.len() == 5);
+        }
+    }
+
+    #[test]
+    fn unsorted_disclosure_fails() {
+        let c2 = commit(&DOMAIN, &K_F, MESSAGE);
+
+        let mut swapped = disclose(&[1, 3]);
+        swapped.segments.swap(0, 1);
+        assert!(!swapped.open(&DOMAIN, &c2));
+
+        let mut repeated = disclose(&[1, 3]);
+        repeated.segments[1] = repeated.segments[0].clone();
+        assert!(!repeated.open(&DOMAIN, &c2));
+    }
+
+    #[test]
+    fn out_of_range_disclosure_fails() {
+        let c2 = commit(&DOMAIN, &K_F, MESSAGE);
+
+        let mut beyond = disclose(&[4]);
+        beyond.segments[0].0 = 5;
+        assert!(!beyond.open(&DOMAIN, &c2));
+
+        for num_segments in [0, 4, 6] {
+            let disclosure = Disclosure { num_segments, ..disclose(&[1]) };
+            assert!(!disclosure.open(&DOMAIN, &c2));
+        }
+    }
+
+    #[test]
+    fn wrong_hidden_roots_fail() {
+        let c2 = commit(&DOMAIN, &K_F, MESSAGE);
+
+        let mut extra = disclose(&[2]);
+        extra.hidden.push([0; 32]);
+        assert!(!extra.open(&DOMAIN, &c2));
+
+        let mut missing = disclose(&[2]);
+        missing.hidden.pop();
+        assert!(!missing.open(&DOMAIN, &c2));
+
+        let mut changed = disclose(&[2]);
+        changed.hidden[0][0] ^= 1;
+        assert!(!changed.open(&DOMAIN, &c2));
+
+        let mut reordered = disclose(&[2]);
+        reordered.hidden.swap(0, 1);
+        assert!(!reordered.open(&DOMAIN, &c2));
+    }
+
+    #[test]
+    fn empty_disclosure_fails() {
+        let c2 = commit(&DOMAIN, &K_F, MESSAGE);
+
+        // The root alone opens to "" without revealing anything
+        let leaves = leaves(&DOMAIN, &K_F, &segments(MESSAGE));
+        let empty = Disclosure { num_segments: 5, segments: Vec::new(), hidden: vec![root(&DOMAIN, &leaves)] };
+        assert!(!empty.open(&DOMAIN, &c2));
+        assert!(!Opening::Segments(empty).open(&DOMAIN, &c2, ""));
+    }
+
+    #[test]
+    fn disclose_checks_reveal() {
+        let opening = Opening::Message(Secret::new(K_F));
+        assert_eq!(opening.disclose(&DOMAIN, MESSAGE, &[5]).err(), Some(ReportingError::BadSegment(5)));
+        assert_eq!(opening.disclose(&DOMAIN, MESSAGE, &[2, 1]).err(), Some(ReportingError::BadSegment(1)));
+        assert_eq!(opening.disclose(&DOMAIN, MESSAGE, &[2, 2]).err(), Some(ReportingError::BadSegment(2)));
+        assert_eq!(opening.disclose(&DOMAIN, MESSAGE, &[]).err(), Some(ReportingError::BadSegment(0)));
+
+        // Only the whole message can be disclosed
+        let disclosed = Opening::Segments(disclose(&[1, 2]));
+        assert_eq!(disclosed.disclose(&DOMAIN, MESSAGE, &[1]).err(), Some(ReportingError::BadSegment(1)));
+    }
+}
@@ -1,437 +1,1107 @@
 use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit},
-    Aes256Gcm, Nonce, Key
+    aead::KeyInit,
+    Aes256Gcm, Key
 };
 use std::mem;
-use bincode;
 use crate::lib_common::*;
 use crate::lib_gamal as gamal;
-use rand::distributions::Alphanumeric;
-use rand::thread_rng;
-use rand::distributions::DistString;
-use rand::Rng;
+use crate::lib_error::ReportingError;
+use crate::lib_nizk::{self as nizk, SchnorrProof};
+use crate::lib_registration::{self as registration, Revocations, RetiredKeys};
+use crate::lib_replay::ReplayCache;
+use crate::lib_context::{ReportContext, ExpiryPolicy};
+use crate::lib_merkle::Opening;
+use crate::lib_verdict::{self as verdict, SignedVerdict};
+use crate::lib_scheme::ReportingScheme;
+use crate::lib_secret::Secret;
+use crate::lib_wire::*;
+use crate::lib_domain::{Domain, Role, Usage};
 use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
 use curve25519_dalek::scalar::Scalar;
 use group::GroupEncoding;
 use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use serde::{Serialize, Deserialize};
+use subtle::ConstantTimeEq;
 
 type Point = CompressedRistretto;
-type PublicKey = (Point, Point, Scalar);
+// (pk_enc_1, pk_enc_2, k1_2, epoch of the keys)
+pub type PublicKey = (Point, Point, Scalar, u32);
 type Ciphertext = (Point, Point);
-type ProcessState = (Ciphertext, Point, Vec<u8>);
-use generic_array::typenum::U12;
 
-type ReportDoc = ([u8; 32], Vec<u8>, Vec<u8>, Vec<u8>, Scalar, Ciphertext);
-type Report = ([u8; 32], Vec<u8>, Ciphertext, Vec<u8>, Vec<u8>);
+// Domain separation labels (see lib_domain.rs)
+const COMMITMENT: Domain = Domain::new(SCHEME_MOD_PRIV, Role::Sender, Usage::Commitment);
+const PLATFORM_TAG: Domain = Domain::new(SCHEME_MOD_PRIV, Role::Platform, Usage::PlatformTag);
+const VERDICT: Domain = Domain::new(SCHEME_MOD_PRIV, Role::Moderator, Usage::Verdict);
+const POSSESSION: Domain = Domain::new(SCHEME_MOD_PRIV, Role::Moderator, Usage::Registration);
+const CERTIFICATE: Domain = Domain::new(SCHEME_MOD_PRIV, Role::Platform, Usage::Registration);
+const REVOCATION: Domain = Domain::new(SCHEME_MOD_PRIV, Role::Platform, Usage::Revocation);
+const ROTATION: Domain = Domain::new(SCHEME_MOD_PRIV, Role::Moderator, Usage::Rotation);
+
+// Entry of sigma output by process: moderator id (4 bytes) and tag (32 bytes)
+const TAG_ENTRY: usize = 36;
+
+// Output of send: (c1, c2, ad)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub c1: Vec<u8>, // Encryption of (m, moderator id, k_f, k_r) under the shared message key
+    pub c2: Vec<u8>, // Commitment to the message
+    pub epk: Point // ad, masked public key of the chosen moderator
+}
+
+// State output by process alongside the per-moderator tags
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessState {
+    pub c3: Ciphertext, // PRE encryption of r' under epk
+    pub epk: Point,
+    pub ctx: ReportContext // Platform context, bound into the tags
+}
+
+// Report doc stored by the receiver
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReportDoc {
+    pub k_f: Secret<[u8; 32]>, // Commitment randomness
+    pub c2: Vec<u8>, // Commitment to the message
+    pub ctx: ReportContext, // Platform context
+    pub sigma: Vec<u8>, // Tag for the chosen moderator
+    pub k_r: Secret<Scalar>, // Re-encryption key from epk to the chosen moderator
+    pub c3: Ciphertext,
+    pub epoch: u32 // Epoch of the moderator key the message was sent under
+}
+
+// Report sent to the moderator
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub opening: Opening, // Opens c2 to the message (k_f) or to some of its segments (lib_merkle.rs)
+    pub c2: Vec<u8>,
+    pub c3_prime: Ciphertext, // c3 re-encrypted to the chosen moderator
+    pub ctx: ReportContext,
+    pub sigma: Vec<u8>,
+    pub epoch: u32 // Epoch of the key c3_prime is encrypted under
+}
+
+// Registration request (lib_registration.rs), proves possession of sk_enc
+// (log_G(pk_enc_2)), which with k1_2 * pk_enc_1 = pk_enc_2 also gives sk_1
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationRequest {
+    pub public_key: PublicKey,
+    pub pop: SchnorrProof
+}
+
+// Platform signature on a moderator directory entry
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    pub moderator_id: u32,
+    pub public_key: PublicKey,
+    pub signature: SchnorrProof // Signature under k_reg
+}
+
+// Registration response, sent over a private channel
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationResponse {
+    pub sk_p: Secret<[u8; 32]>, // MAC key issued by the Platform
+    pub certificate: Certificate
+}
+
+// Key rotation request (lib_registration.rs), the keys of the next epoch
+// with a proof of possession, authorized with the current sk_enc
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RotationRequest {
+    pub moderator_id: u32,
+    pub public_key: PublicKey,
+    pub pop: SchnorrProof,
+    pub authorization: SchnorrProof // Signature under the current pk_enc_2
+}
+
+// Revoked moderator ids published by the Platform
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub version: u32, // Number of revocations so far
+    pub revoked: Vec<u32>,
+    pub signature: SchnorrProof // Signature under k_reg
+}
+
+fn write_public_key(w: &mut FieldWriter, pk: &PublicKey) {
+    w.point(&pk.0);
+    w.point(&pk.1);
+    w.scalar(&pk.2);
+    w.u32(pk.3);
+}
+
+fn read_public_key(r: &mut FieldReader) -> Result<PublicKey, ReportingError> {
+    Ok((r.point()?, r.point()?, r.scalar()?, r.u32()?))
+}
+
+fn public_key_bytes(pk: &PublicKey) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    write_public_key(&mut w, pk);
+
+    w.finish().expect("fixed-size fields")
+}
+
+impl Certificate {
+    // Checks the Platform signature, clients call it before using public_key
+    pub fn verify(&self, k_reg: &Point) -> Result<(), ReportingError> {
+        let message = registration::certificate_message(self.moderator_id, &public_key_bytes(&self.public_key));
+        if !nizk::schnorr_verify(&CERTIFICATE, &gamal::decompress(k_reg)?, &message, &self.signature) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(())
+    }
+}
+
+impl RevocationList {
+    pub fn verify(&self, k_reg: &Point) -> Result<(), ReportingError> {
+        let message = registration::revocation_message(self.version, &self.revoked);
+        if !nizk::schnorr_verify(&REVOCATION, &gamal::decompress(k_reg)?, &message, &self.signature) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(())
+    }
+}
+
+impl WireMessage for SentMessage {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Send;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.c1);
+        w.bytes(&self.c2);
+        w.point(&self.epk);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<SentMessage, ReportingError> {
+        Ok(SentMessage { c1: r.bytes()?.to_vec(), c2: r.bytes()?.to_vec(), epk: r.point()? })
+    }
+}
+
+// (sigma, st)
+impl WireMessage for (Vec<u8>, ProcessState) {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Process;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        let (sigma, st) = self;
+        w.bytes(sigma);
+        w.point(&st.c3.0);
+        w.point(&st.c3.1);
+        w.point(&st.epk);
+        w.bytes(&st.ctx.to_bytes());
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        let sigma = r.bytes()?.to_vec();
+        let st = ProcessState { c3: (r.point()?, r.point()?), epk: r.point()?, ctx: ReportContext::from_bytes(r.bytes()?)? };
+
+        Ok((sigma, st))
+    }
+}
+
+impl WireMessage for ReportDoc {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::ReportDoc;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.k_f[..]);
+        w.bytes(&self.c2);
+        w.bytes(&self.ctx.to_bytes());
+        w.bytes(&self.sigma);
+        w.scalar(&self.k_r);
+        w.point(&self.c3.0);
+        w.point(&self.c3.1);
+        w.u32(self.epoch);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<ReportDoc, ReportingError> {
+        Ok(ReportDoc {
+            k_f: Secret::new(r.array()?),
+            c2: r.bytes()?.to_vec(),
+            ctx: ReportContext::from_bytes(r.bytes()?)?,
+            sigma: r.bytes()?.to_vec(),
+            k_r: Secret::new(r.scalar()?),
+            c3: (r.point()?, r.point()?),
+            epoch: r.u32()?
+        })
+    }
+}
+
+impl WireMessage for Report {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Report;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        self.opening.write_fields(w);
+        w.bytes(&self.c2);
+        w.point(&self.c3_prime.0);
+        w.point(&self.c3_prime.1);
+        w.bytes(&self.ctx.to_bytes());
+        w.bytes(&self.sigma);
+        w.u32(self.epoch);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Report, ReportingError> {
+        Ok(Report {
+            opening: Opening::read_fields(r)?,
+            c2: r.bytes()?.to_vec(),
+            c3_prime: (r.point()?, r.point()?),
+            ctx: ReportContext::from_bytes(r.bytes()?)?,
+            sigma: r.bytes()?.to_vec(),
+            epoch: r.u32()?
+        })
+    }
+}
+
+impl WireMessage for RegistrationRequest {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RegistrationRequest;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        write_public_key(w, &self.public_key);
+        w.scalar(&self.pop.c);
+        w.scalar(&self.pop.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationRequest, ReportingError> {
+        Ok(RegistrationRequest {
+            public_key: read_public_key(r)?,
+            pop: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+impl WireMessage for RotationRequest {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RotationRequest;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.moderator_id);
+        write_public_key(w, &self.public_key);
+        w.scalar(&self.pop.c);
+        w.scalar(&self.pop.z);
+        w.scalar(&self.authorization.c);
+        w.scalar(&self.authorization.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RotationRequest, ReportingError> {
+        Ok(RotationRequest {
+            moderator_id: r.u32()?,
+            public_key: read_public_key(r)?,
+            pop: SchnorrProof { c: r.scalar()?, z: r.scalar()? },
+            authorization: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+impl WireMessage for Certificate {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::Certificate;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.moderator_id);
+        write_public_key(w, &self.public_key);
+        w.scalar(&self.signature.c);
+        w.scalar(&self.signature.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Certificate, ReportingError> {
+        Ok(Certificate {
+            moderator_id: r.u32()?,
+            public_key: read_public_key(r)?,
+            signature: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+// The certificate fields follow the MAC key
+impl WireMessage for RegistrationResponse {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RegistrationResponse;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.sk_p[..]);
+        self.certificate.write_fields(w);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationResponse, ReportingError> {
+        Ok(RegistrationResponse { sk_p: Secret::new(r.array()?), certificate: Certificate::read_fields(r)? })
+    }
+}
+
+impl WireMessage for RevocationList {
+    const SCHEME_ID: u8 = SCHEME_MOD_PRIV;
+    const KIND: MessageKind = MessageKind::RevocationList;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.version);
+        registration::write_ids(w, &self.revoked);
+        w.scalar(&self.signature.c);
+        w.scalar(&self.signature.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RevocationList, ReportingError> {
+        Ok(RevocationList {
+            version: r.u32()?,
+            revoked: registration::read_ids(r)?,
+            signature: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+// Moderator keys before registration
+pub struct PendingModerator {
+    sk_enc: Secret<Scalar>, // Moderator private key
+    pub pk_enc_1: Point, // Moderator public key
+    pub pk_enc_2: Point, // Moderator public key 2
+    k1_2: Secret<Scalar>, // Moderator re-encryption key
+    pub k_reg: Point, // Registration key of the Platform
+    pub epoch: u32 // Epoch the keys are for, 0 at registration
+}
+
+impl PendingModerator {
+    // SetupMod(pk_reg, 1^lambda)
+    pub fn new(k_reg: &Point) -> PendingModerator {
+        Self::new_with_rng(k_reg, &mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(k_reg: &Point, rng: &mut R) -> PendingModerator {
+        Self::for_epoch(k_reg, 0, rng)
+    }
+
+    fn for_epoch<R: CryptoRng + RngCore>(k_reg: &Point, epoch: u32, rng: &mut R) -> PendingModerator {
+        let keys = gamal::elgamal_keygen_with_rng(rng);
+        let keys2 = gamal::elgamal_keygen_with_rng(rng);
+        PendingModerator {
+            sk_enc: Secret::new(keys2.0),
+            pk_enc_1: keys.1.compress(),
+            pk_enc_2: keys2.1.compress(),
+            k1_2: Secret::new(keys2.0 * keys.0.invert()), // sk2 / sk1
+            k_reg: *k_reg,
+            epoch
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        (self.pk_enc_1, self.pk_enc_2, *self.k1_2, self.epoch)
+    }
+
+    pub fn request(&self) -> RegistrationRequest {
+        self.request_with_rng(&mut OsRng)
+    }
+
+    pub fn request_with_rng<R: CryptoRng + RngCore>(&self, rng: &mut R) -> RegistrationRequest {
+        let public_key = self.public_key();
+        let message = registration::pop_message(self.k_reg.as_bytes(), &public_key_bytes(&public_key));
+        let pop = nizk::schnorr_prove(&POSSESSION, &self.sk_enc, &RistrettoPoint::mul_base(&self.sk_enc), &message, rng);
+
+        RegistrationRequest { public_key, pop }
+    }
+
+    // Checks the certificate and that it certifies this moderator's keys
+    pub fn finish(self, response: RegistrationResponse) -> Result<Moderator, ReportingError> {
+        let RegistrationResponse { sk_p, certificate } = response;
+        certificate.verify(&self.k_reg)?;
+
+        if public_key_bytes(&certificate.public_key) != public_key_bytes(&self.public_key()) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(Moderator {
+            sk_p,
+            sk_enc: self.sk_enc,
+            pk_enc_1: self.pk_enc_1,
+            pk_enc_2: self.pk_enc_2,
+            k1_2: self.k1_2,
+            k_reg: self.k_reg,
+            retired: RetiredKeys::new(),
+            grace_epochs: registration::GRACE_EPOCHS,
+            certificate
+        })
+    }
+}
 
 // Moderator Properties
 pub struct Moderator {
-    pub sk_p: [u8; 32], // Mac Key shared with the Platform
-    pub sk_enc: Scalar, // Moderator private key
+    sk_p: Secret<[u8; 32]>, // Mac Key shared with the Platform
+    sk_enc: Secret<Scalar>, // Moderator private key
     pub pk_enc_1: Point, // Moderator public key
     pub pk_enc_2: Point, // Moderator public key 2
-    pub k1_2: Scalar // Moderator re-encryption key
+    k1_2: Secret<Scalar>, // Moderator re-encryption key
+    pub k_reg: Point, // Registration key of the Platform
+    retired: RetiredKeys<Secret<Scalar>>, // sk_enc of the earlier epochs still in their grace period
+    pub grace_epochs: u32, // Number of earlier epochs whose reports are still moderated
+    pub certificate: Certificate // Issued at registration or at the last rotation
 }
 
 // Moderator Implementation 
 impl Moderator {
-    // SetupMod(pk_reg, 1^lambda)
-    pub fn new(_pk_reg: &Option<Vec<u8>>) -> Moderator {
-        let keys = gamal::elgamal_keygen();
-        let keys2 = gamal::elgamal_keygen();
-        Moderator {
-            sk_p: mac_keygen(),
-            sk_enc: keys2.0,
-            pk_enc_1: keys.1.compress(),
-            pk_enc_2: keys2.1.compress(),
-            k1_2: keys2.0 * keys.0.invert() // sk2 / sk1
+    pub fn id(&self) -> u32 {
+        self.certificate.moderator_id
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.certificate.public_key.3
+    }
+
+    // (pk_enc_1, pk_enc_2, k1_2, epoch) handed to clients
+    pub fn public_key(&self) -> PublicKey {
+        (self.pk_enc_1, self.pk_enc_2, *self.k1_2, self.epoch())
+    }
+
+    // Keys for the next epoch and the request to send to the platform
+    pub fn rotate(&self) -> (PendingModerator, RotationRequest) {
+        self.rotate_with_rng(&mut OsRng)
+    }
+
+    pub fn rotate_with_rng<R: CryptoRng + RngCore>(&self, rng: &mut R) -> (PendingModerator, RotationRequest) {
+        let pending = PendingModerator::for_epoch(&self.k_reg, self.epoch() + 1, rng);
+        let RegistrationRequest { public_key, pop } = pending.request_with_rng(rng);
+
+        let message = registration::rotation_message(self.k_reg.as_bytes(), self.id(), &public_key_bytes(&public_key));
+        let authorization = nizk::schnorr_prove(&ROTATION, &self.sk_enc, &RistrettoPoint::mul_base(&self.sk_enc), &message, rng);
+
+        (pending, RotationRequest { moderator_id: self.id(), public_key, pop, authorization })
+    }
+
+    // Switches to the keys of pending once the platform certified them, the
+    // current sk_enc is kept for grace_epochs epochs
+    pub fn finish_rotation(&mut self, pending: PendingModerator, certificate: Certificate) -> Result<(), ReportingError> {
+        certificate.verify(&self.k_reg)?;
+        if certificate.moderator_id != self.id() || public_key_bytes(&certificate.public_key) != public_key_bytes(&pending.public_key()) {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        let epoch = self.epoch();
+        let sk_enc = mem::replace(&mut self.sk_enc, pending.sk_enc);
+        self.retired.retire(epoch, sk_enc, pending.epoch, self.grace_epochs);
+        self.pk_enc_1 = pending.pk_enc_1;
+        self.pk_enc_2 = pending.pk_enc_2;
+        self.k1_2 = pending.k1_2;
+        self.certificate = certificate;
+
+        Ok(())
+    }
+
+    // sk_enc of the epoch a report was made under
+    fn epoch_key(&self, epoch: u32) -> Result<&Secret<Scalar>, ReportingError> {
+        self.retired.get(epoch, self.epoch(), &self.sk_enc)
+    }
+
+    pub fn moderate(&self, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        let (u, v) = &report.c3_prime;
+        let decrypted = self.epoch_key(report.epoch).and_then(|sk_enc| Ok(Secret::new(gamal::pre_elgamal_dec(sk_enc, &(gamal::decompress(u)?, gamal::decompress(v)?)))));
+
+        match decrypted {
+            Ok(r_prime) => self.verify_report(message, report, &r_prime, policy),
+            // The report names an epoch without a key or carries an invalid point
+            Err(e) => Ok(Verdict::Reject(e))
         }
     }
 
-    pub fn moderate(sk_enc: &Scalar, sk_p: &[u8; 32], moderator_id: usize, message: &str, report: &Report) -> String {
-        let (k_f, c2, c3_prime, ctx, sigma) = report;
+    // moderate with a verdict anyone can check against pk_enc_2 (lib_verdict.rs)
+    pub fn moderate_signed(&self, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<SignedVerdict, ReportingError> {
+        self.moderate_signed_with_rng(message, report, policy, &mut OsRng)
+    }
+
+    pub fn moderate_signed_with_rng<R: CryptoRng + RngCore>(&self, message: &str, report: &Report, policy: &ExpiryPolicy, rng: &mut R) -> Result<SignedVerdict, ReportingError> {
+        let sk_enc = self.epoch_key(report.epoch)?;
+        let (u, v) = (gamal::decompress(&report.c3_prime.0)?, gamal::decompress(&report.c3_prime.1)?);
+        let d = v * sk_enc.invert();
+        let r_prime = Secret::new(u - d);
 
-        let (u, v) = c3_prime;
+        let verdict = self.verify_report(message, report, &r_prime, policy)?;
 
-        let r_prime = gamal::pre_elgamal_dec(&sk_enc, &(u.decompress().unwrap(), v.decompress().unwrap()));
+        // log_G(pk_enc_2) = log_(u - r')(v), signing the verdict
+        let transcript = verdict::verdict_transcript(&verdict, &report.ctx, &report.c2)?;
+        let proof = nizk::dleq_prove_with_message(&VERDICT, sk_enc, &d, &RistrettoPoint::mul_base(sk_enc), &v, &transcript, rng);
+
+        Ok(SignedVerdict { verdict, ctx: report.ctx, decrypted: r_prime.compress(), proof })
+    }
+
+    // Checks the commitment and the platform tag of a report given r', then
+    // its age against policy
+    fn verify_report(&self, message: &str, report: &Report, r_prime: &RistrettoPoint, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        let Report { opening, c2, ctx, sigma, .. } = report;
 
         // Verify committment
-        assert!(com_open(&c2, message, k_f));
+        if !opening.open(&COMMITMENT, c2, message) {
+            return Ok(Verdict::Reject(ReportingError::BadCommitment));
+        }
 
         // Verify signature
-        assert!(mac_verify(&sk_p, &[&c2[..], &(r_prime.to_bytes().to_vec()[..]), &ctx[..]].concat(), &sigma));
+        if !mac_verify(&PLATFORM_TAG, &self.sk_p, &[&c2[..], &(r_prime.to_bytes().to_vec()[..]), &ctx.to_bytes()].concat(), sigma) {
+            return Ok(Verdict::Reject(ReportingError::BadPlatformTag));
+        }
 
-        let ctx_s = std::str::from_utf8(&ctx).unwrap();
-        return ctx_s.to_string();
+        Ok(policy.verdict(ctx))
     }
 }
 
+// Checks a signed verdict of the moderator with public key pk, of the epoch
+// of the report, on report
+pub fn verify_verdict(pk: &PublicKey, message: &str, report: &Report, sv: &SignedVerdict) -> Result<(), ReportingError> {
+    let Report { opening, c2, c3_prime, ctx, epoch, .. } = report;
+    let (_pk_enc_1, pk_enc_2, _k1_2, pk_epoch) = pk;
+    registration::check_epoch(*epoch, *pk_epoch)?;
+
+    verdict::check_outcome(sv, ctx, opening.open(&COMMITMENT, c2, message))?;
+
+    let (u, v) = (gamal::decompress(&c3_prime.0)?, gamal::decompress(&c3_prime.1)?);
+    let d = u - gamal::decompress(&sv.decrypted)?;
+    let transcript = verdict::verdict_transcript(&sv.verdict, ctx, c2)?;
+    if !nizk::dleq_verify_with_message(&VERDICT, &d, &gamal::decompress(pk_enc_2)?, &v, &transcript, &sv.proof) {
+        return Err(ReportingError::BadProof);
+    }
+
+    Ok(())
+}
+
 
 
 // Platform Properties
 pub struct Platform {
-    pub k_p: Option<Vec<u8>>, // Platform key
-    pub k_reg: Option<Vec<u8>>, // Registration key
-    pub sk_p: Vec<([u8; 32], Point)> // Vector of Moderator keys accessible to the Platform
+    k_p: Secret<Scalar>, // Certificate signing key
+    pub k_reg: Point, // Registration key k_p * G
+    sk_p: Vec<Option<(Secret<[u8; 32]>, PublicKey)>>, // Vector of Moderator keys accessible to the Platform, indexed by moderator id, None once revoked
+    revocations: RevocationList, // Signed list of the revoked ids
+    pub replay: ReplayCache // Commitments processed per conversation (lib_replay.rs)
 }
 
 // Platform Implementation
 impl Platform {
     pub fn new() -> Platform {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
+        let (k_p, k_reg) = gamal::elgamal_keygen_with_rng(rng);
+        let k_p = Secret::new(k_p);
+        let revocations = sign_revocations(&k_p, 0, Vec::new(), rng);
+
         Platform {
-            k_p: None,
-            k_reg: None,
-            sk_p: Vec::<([u8; 32], Point)>::new()
+            k_p,
+            k_reg: k_reg.compress(),
+            sk_p: Vec::<Option<(Secret<[u8; 32]>, PublicKey)>>::new(),
+            revocations,
+            replay: ReplayCache::new()
         }
     }
 
+    // Removes moderator id from the directory and returns the new revocation
+    // list for clients. The slot stays empty so no other id changes
+    pub fn revoke(&mut self, moderator_id: u32) -> Result<RevocationList, ReportingError> {
+        self.revoke_with_rng(moderator_id, &mut OsRng)
+    }
+
+    pub fn revoke_with_rng<R: CryptoRng + RngCore>(&mut self, moderator_id: u32, rng: &mut R) -> Result<RevocationList, ReportingError> {
+        let slot = self.sk_p.get_mut(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
+        if slot.take().is_some() {
+            let mut revoked = self.revocations.revoked.clone();
+            revoked.push(moderator_id);
+            self.revocations = sign_revocations(&self.k_p, self.revocations.version + 1, revoked, rng);
+        }
+
+        Ok(self.revocations.clone())
+    }
+
+    pub fn revocation_list(&self) -> &RevocationList {
+        &self.revocations
+    }
+
+    // Checks the proof of possession, issues the MAC key and certifies the
+    // new directory entry
+    pub fn register(&mut self, request: &RegistrationRequest) -> Result<RegistrationResponse, ReportingError> {
+        self.register_with_rng(request, &mut OsRng)
+    }
+
+    pub fn register_with_rng<R: CryptoRng + RngCore>(&mut self, request: &RegistrationRequest, rng: &mut R) -> Result<RegistrationResponse, ReportingError> {
+        let RegistrationRequest { public_key, pop } = request;
+        registration::check_epoch(public_key.3, 0)?;
+        self.check_keys(public_key, pop)?;
+
+        let sk_p = Secret::new(mac_keygen_with_rng(rng));
+        let moderator_id = self.sk_p.len() as u32;
+        let certificate = self.certify(moderator_id, public_key, rng);
+
+        self.sk_p.push(Some((sk_p.clone(), *public_key)));
+
+        Ok(RegistrationResponse { sk_p, certificate })
+    }
+
+    // Checks the new keys, the authorization by the current ones and
+    // certifies the new keys, the MAC key stays the same
+    pub fn rotate(&mut self, request: &RotationRequest) -> Result<Certificate, ReportingError> {
+        self.rotate_with_rng(request, &mut OsRng)
+    }
+
+    pub fn rotate_with_rng<R: CryptoRng + RngCore>(&mut self, request: &RotationRequest, rng: &mut R) -> Result<Certificate, ReportingError> {
+        let RotationRequest { moderator_id, public_key, pop, authorization } = request;
+        let slot = self.sk_p.get(*moderator_id as usize).ok_or(ReportingError::UnknownModerator(*moderator_id))?;
+        let (_, current) = slot.as_ref().ok_or(ReportingError::RevokedModerator(*moderator_id))?;
+
+        registration::check_epoch(public_key.3, current.3 + 1)?;
+        self.check_keys(public_key, pop)?;
+
+        let message = registration::rotation_message(self.k_reg.as_bytes(), *moderator_id, &public_key_bytes(public_key));
+        if !nizk::schnorr_verify(&ROTATION, &gamal::decompress(&current.1)?, &message, authorization) {
+            return Err(ReportingError::BadProof);
+        }
+
+        let certificate = self.certify(*moderator_id, public_key, rng);
+        if let Some((_, current)) = &mut self.sk_p[*moderator_id as usize] {
+            *current = *public_key;
+        }
+
+        Ok(certificate)
+    }
+
+    // k1_2 and the proof of possession of sk_enc
+    fn check_keys(&self, public_key: &PublicKey, pop: &SchnorrProof) -> Result<(), ReportingError> {
+        let (pk_enc_1, pk_enc_2, k1_2, _epoch) = public_key;
+        let pk_enc_2_pt = gamal::decompress(pk_enc_2)?;
+
+        // Clients mask pk_enc_1 and re-encrypt with k1_2, the result must be pk_enc_2
+        if !bool::from((k1_2 * gamal::decompress(pk_enc_1)?).ct_eq(&pk_enc_2_pt)) {
+            return Err(ReportingError::BadModeratorKey);
+        }
+
+        let message = registration::pop_message(self.k_reg.as_bytes(), &public_key_bytes(public_key));
+        if !nizk::schnorr_verify(&POSSESSION, &pk_enc_2_pt, &message, pop) {
+            return Err(ReportingError::BadProof);
+        }
+
+        Ok(())
+    }
+
+    fn certify<R: CryptoRng + RngCore>(&self, moderator_id: u32, public_key: &PublicKey, rng: &mut R) -> Certificate {
+        let message = registration::certificate_message(moderator_id, &public_key_bytes(public_key));
+        let signature = nizk::schnorr_prove(&CERTIFICATE, &self.k_p, &RistrettoPoint::mul_base(&self.k_p), &message, rng);
+
+        Certificate { moderator_id, public_key: *public_key, signature }
+    }
+
+    pub fn process<R: CryptoRng + RngCore>(&mut self, _c1: &[u8], c2: &[u8], ad: &Point, ctx: &ReportContext, rng: &mut R) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        let ctx = self.replay.admit(ctx, c2)?;
 
-    pub fn process(_k_p: &Option<Vec<u8>>, ks: &Vec<([u8; 32], Point)>, _c1: &Vec<u8>, c2: &Vec<u8>, ad: &Point, ctx: &Vec<u8>) -> (Vec<u8>, ProcessState) {
         // Get random group element of ristretto group
-        let mut r_prime = RistrettoPoint::random(&mut OsRng);
+        let r_prime = Secret::new(RistrettoPoint::random(rng));
         
-        let to_sign = [&c2[..], &(r_prime.to_bytes().to_vec()[..]), &ctx[..]].concat();
+        let to_sign = [c2, &(r_prime.to_bytes().to_vec()[..]), &ctx.to_bytes()].concat();
         
+        // The platform does not learn the chosen moderator and tags for every
+        // moderator that is not revoked, each tag after its moderator id
         let mut sigma_pt: Vec<u8> = Vec::<u8>::new();
-        for i in 0..ks.len() {
-            sigma_pt.extend(&mac_sign(&ks[i].0, &to_sign));
+        for (id, slot) in self.sk_p.iter().enumerate() {
+            if let Some((mac_key_i, _)) = slot {
+                sigma_pt.extend(&(id as u32).to_be_bytes());
+                sigma_pt.extend(&mac_sign(&PLATFORM_TAG, mac_key_i, &to_sign));
+            }
         }
 
-        let epk = ad.decompress().unwrap();
-        let c3 = gamal::pre_elgamal_enc(&epk, &r_prime);
+        let epk = gamal::decompress(ad)?;
+        let c3 = gamal::pre_elgamal_enc(&epk, &r_prime, rng);
         let (u, v) = c3;
 
-        let st: ProcessState = ((u.compress(), v.compress()), *ad, ctx.clone());
+        let st = ProcessState { c3: (u.compress(), v.compress()), epk: *ad, ctx };
 
-        (sigma_pt, st)
+        Ok((sigma_pt, st))
     }
 
 }
 
+impl Default for Platform {
+    fn default() -> Platform {
+        Platform::new()
+    }
+}
+
+fn sign_revocations<R: CryptoRng + RngCore>(k_p: &Scalar, version: u32, revoked: Vec<u32>, rng: &mut R) -> RevocationList {
+    let message = registration::revocation_message(version, &revoked);
+    let signature = nizk::schnorr_prove(&REVOCATION, k_p, &RistrettoPoint::mul_base(k_p), &message, rng);
+
+    RevocationList { version, revoked, signature }
+}
+
 
 // Client Properties
 pub struct Client {
-    pub msg_key: Key<Aes256Gcm> // Symmetric key used to encrypt messages between sender and
+    msg_key: Secret<Key<Aes256Gcm>>, // Symmetric key used to encrypt messages between sender and
+    revocations: Revocations // Newest revocation list verified by this client
 }
 
 // Client Implementation
 impl Client {
     pub fn new() -> Client {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
         Client {
-            msg_key: Aes256Gcm::generate_key(aes_gcm::aead::OsRng)
+            msg_key: Secret::new(Aes256Gcm::generate_key(rng)),
+            revocations: Revocations::default()
         }
     }
 
-    pub fn ccae_enc(msg_key: &Key<Aes256Gcm>, message: &str, moderator_id: u32, k_r: Scalar) -> (Vec<u8>, Vec<u8>) {
-        let k_f: [u8; 32] = mac_keygen(); // franking key or r in H(m, r) for committment
-        
-        let c2 = com_commit(&k_f, message);
-
-        let cipher = Aes256Gcm::new(&msg_key);
-        let nonce = Aes256Gcm::generate_nonce(&mut rand::rngs::OsRng);
-
-        let payload = bincode::serialize(&(message, moderator_id, k_f, k_r.to_bytes())).expect("");
-        let c1_obj = cipher.encrypt(&nonce, payload.as_slice()).unwrap();
-        let c1 = bincode::serialize::<(Vec<u8>, Vec<u8>)>(&(c1_obj, nonce.to_vec())).expect("");
+    // Accepts the revocation list of the platform with registration key k_reg
+    pub fn update_revocations(&mut self, list: &RevocationList, k_reg: &Point) -> Result<(), ReportingError> {
+        list.verify(k_reg)?;
 
-        (c1, c2)
+        self.revocations.update(list.version, &list.revoked)
     }
 
-    pub fn ccae_dec(msg_key: &Key<Aes256Gcm>, c1: &Vec<u8>, c2: &Vec<u8>) -> (String, u32, [u8; 32], Scalar) {
-        let c1_obj = bincode::deserialize::<(Vec<u8>, Vec<u8>)>(&c1).unwrap();
-        let ct = c1_obj.0;
-        let nonce = Nonce::from_slice(&c1_obj.1);
+    pub fn revocations(&self) -> &Revocations {
+        &self.revocations
+    }
 
-        let cipher = Aes256Gcm::new(&msg_key);
-        let payload_bytes = cipher.decrypt(&nonce, ct.as_ref()).unwrap();
-        let payload = bincode::deserialize::<(&str, u32, [u8; 32], [u8; 32])>(&payload_bytes).unwrap();
+    pub fn ccae_enc<R: CryptoRng + RngCore>(&self, message: &str, moderator_id: u32, epoch: u32, k_r: Scalar, rng: &mut R) -> Result<(Vec<u8>, Vec<u8>), ReportingError> {
+        let k_f = Secret::new(mac_keygen_with_rng(rng)); // franking key or r in H(m, r) for committment
+        
+        let c2 = com_commit(&COMMITMENT, &k_f[..], message);
+
+        let mut payload = FieldWriter::new();
+        payload.bytes(message.as_bytes());
+        payload.u32(moderator_id);
+        payload.u32(epoch);
+        payload.bytes(&k_f[..]);
+        payload.scalar(&k_r);
+        let payload = Secret::new(payload.finish()?);
+        let c1 = sym_enc(&self.msg_key, &payload, rng);
+
+        Ok((c1, c2))
+    }
 
-        let (message, moderator_id, k_f, k_r) = payload;
-        let k_r = Scalar::from_bytes_mod_order(k_r);
+    #[allow(clippy::type_complexity)]
+    pub fn ccae_dec(&self, c1: &[u8], c2: &[u8]) -> Result<(String, u32, u32, Secret<[u8; 32]>, Secret<Scalar>), ReportingError> {
+        let payload_bytes = Secret::new(sym_dec(&self.msg_key, c1)?);
+        let mut payload = FieldReader::new(&payload_bytes);
+        let message = payload.string()?;
+        let moderator_id = payload.u32()?;
+        let epoch = payload.u32()?;
+        let k_f = Secret::new(payload.array::<32>()?);
+        let k_r = Secret::new(payload.scalar()?);
+        payload.finish()?;
 
         // Verify committment
-        assert!(com_open(&c2, message, &k_f));
+        if !com_open(&COMMITMENT, c2, &message, &k_f[..]) {
+            return Err(ReportingError::BadCommitment);
+        }
 
-        (message.to_string(), moderator_id, k_f, k_r)
+        Ok((message, moderator_id, epoch, k_f, k_r))
     }
 
-    pub fn send(msg_key: &Key<Aes256Gcm>, message: &str, moderator_id: u32, pk_i: &PublicKey) -> (Vec<u8>, Vec<u8>, Point) {
-        let (pk1, pk2, k1_2) = pk_i;
-        let s: Scalar = Scalar::random(&mut OsRng);
-        let epk = &s * pk1.decompress().unwrap();
+    pub fn send<R: CryptoRng + RngCore>(&self, message: &str, moderator_id: u32, pk_i: &PublicKey, rng: &mut R) -> Result<SentMessage, ReportingError> {
+        self.revocations.check(moderator_id)?;
+
+        let (pk1, _pk2, k1_2, epoch) = pk_i;
+        let s: Scalar = Scalar::random(rng);
+        let epk = s * gamal::decompress(pk1)?;
         let k_r = k1_2 * s.invert();
 
-        let (c1, c2) = Self::ccae_enc(msg_key, message, moderator_id, k_r);       
+        let (c1, c2) = self.ccae_enc(message, moderator_id, *epoch, k_r, rng)?;       
 
-        (c1, c2, epk.compress())
+        Ok(SentMessage { c1, c2, epk: epk.compress() })
     }
     
-    pub fn read(msg_key: &Key<Aes256Gcm>, pks: &Vec<PublicKey>, c1: &Vec<u8>, c2: &Vec<u8>, sigma: &Vec<u8>, st: &ProcessState) -> (String, u32, ReportDoc) {
-        let (c3, epk, ctx) = st;
-        let (message, moderator_id, k_f, k_r) = Self::ccae_dec(msg_key, c1, c2);
+    pub fn read(&self, pks: &[PublicKey], c1: &[u8], c2: &[u8], sigma: &[u8], st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        let ProcessState { c3, epk, ctx } = st;
+        let (message, moderator_id, epoch, k_f, k_r) = self.ccae_dec(c1, c2)?;
+        self.revocations.check(moderator_id)?;
 
-        let pk2 = pks[usize::try_from(moderator_id).unwrap()].1;
+        // The message must be sent under the keys in the directory
+        let (_pk1, pk2, _k1_2, pk_epoch) = pks.get(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
+        registration::check_epoch(epoch, *pk_epoch)?;
 
         // Ensure this message is reportable
-        assert!((&k_r * epk.decompress().unwrap()) == pk2.decompress().unwrap());
+        if !bool::from((*k_r * gamal::decompress(epk)?).ct_eq(&gamal::decompress(pk2)?)) {
+            return Err(ReportingError::BadModeratorKey);
+        }
 
-        // Sigma For Chosen Moderator
-        let l: usize = (moderator_id as usize) * 32;
-        let r: usize = l + 32;
-        let tag: Vec<u8> = sigma[l..r].to_vec();
+        // Sigma For Chosen Moderator, missing once the platform revoked it
+        if !sigma.len().is_multiple_of(TAG_ENTRY) {
+            return Err(ReportingError::MalformedEncoding);
+        }
+        let tag: Vec<u8> = sigma.chunks_exact(TAG_ENTRY).find(|entry| entry[..4] == moderator_id.to_be_bytes())
+            .ok_or(ReportingError::RevokedModerator(moderator_id))?[4..].to_vec();
         
-        let rd: ReportDoc = (k_f, c2.to_vec(), ctx.to_vec(), tag.clone(), k_r, c3.clone());
+        let rd = ReportDoc { k_f, c2: c2.to_vec(), ctx: *ctx, sigma: tag, k_r, c3: *c3, epoch };
 
-        (message, moderator_id, rd)
+        Ok((message, moderator_id, rd))
     }
 
-    pub fn report_gen(_msg: &String, rd: &ReportDoc) -> Report {
-        let (k_f, c2, ctx, sigma, k_r, c3) = rd;
+    pub fn report_gen(_msg: &str, rd: &ReportDoc) -> Result<Report, ReportingError> {
+        let ReportDoc { k_f, c2, ctx, sigma, k_r, c3, epoch } = rd;
 
         let(u, v) = c3;
 
-        let c3_prime = gamal::pre_re_enc(&(u.decompress().unwrap(), v.decompress().unwrap()), &k_r);
+        let c3_prime = gamal::pre_re_enc(&(gamal::decompress(u)?, gamal::decompress(v)?), k_r);
 
         let (u_prime, v_prime) = c3_prime;
 
-        let report: Report = (*k_f, c2.clone(), (u_prime.compress(), v_prime.compress()), ctx.clone(), sigma.clone());
+        let report = Report { opening: Opening::Message(k_f.clone()), c2: c2.clone(), c3_prime: (u_prime.compress(), v_prime.compress()), ctx: *ctx, sigma: sigma.clone(), epoch: *epoch };
 
-        report
+        Ok(report)
     }
 
+    // Report revealing only the segments reveal of msg (lib_merkle.rs)
+    pub fn report_gen_partial(msg: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        let opening = Opening::Message(rd.k_f.clone()).disclose(&COMMITMENT, msg, reveal)?;
 
-
-}
+        Ok(Report { opening, ..Self::report_gen(msg, rd)? })
+    }
 
 
 
-pub fn test_setup_platform() -> Platform {
-    let platform = Platform::new();
+}
 
-    platform
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
 }
 
 
-// SetupMod(pk_reg, 1^lambda)
-pub fn test_setup_mod(platform: &mut Platform, num_moderators: usize) -> (Vec<Moderator>, Vec<PublicKey>) {
-    let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
-    let mut pks: Vec<PublicKey> = Vec::with_capacity(num_moderators);
+// Moderator privacy scheme: the platform tags for every moderator and
+// proxy re-encrypts r' to the moderator chosen by the sender
+pub struct ModPriv;
 
-    for _i in 0..num_moderators {
-        let moderator = Moderator::new(&platform.k_reg);
-        platform.sk_p.push((moderator.sk_p.clone(), moderator.pk_enc_2.clone()));
-        pks.push((moderator.pk_enc_1.clone(), moderator.pk_enc_2.clone(), moderator.k1_2.clone()));
-        moderators.push(moderator);
+impl ReportingScheme for ModPriv {
+    const NAME: &'static str = "Moderator Privacy Scheme";
+
+    type Platform = Platform;
+    type Moderator = Moderator;
+    type Client = Client;
+    type PublicKey = PublicKey;
+    type Ciphertext = SentMessage;
+    type Sigma = Vec<u8>;
+    type ProcessState = ProcessState;
+    type ReportDoc = ReportDoc;
+    type Report = Report;
+
+    fn setup_platform_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
+        Platform::new_with_rng(rng)
     }
 
+    fn setup_mod_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, num_moderators: usize, rng: &mut R) -> (Vec<Moderator>, Vec<PublicKey>) {
+        let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
+        let mut pks: Vec<PublicKey> = Vec::with_capacity(num_moderators);
 
-    (moderators, pks)
-}
+        for _i in 0..num_moderators {
+            let pending = PendingModerator::new_with_rng(&platform.k_reg, rng);
+            let response = platform.register_with_rng(&pending.request_with_rng(rng), rng).expect("registration of an honest moderator");
+            let moderator = pending.finish(response).expect("certificate of an honest platform");
+            pks.push(moderator.public_key());
+            moderators.push(moderator);
+        }
 
-pub fn test_setup() -> (Vec<Platform>, Vec<Vec<Moderator>>, Vec<Vec<PublicKey>>) {
-    let n: usize = usize::try_from(MOD_SCALE.len()).unwrap();
-    let mut platforms: Vec<Platform> = Vec::with_capacity(n);
-    
-    for _i in 0..n {
-        platforms.push(Platform::new());
+        (moderators, pks)
     }
-    
-    let mut moderators: Vec<Vec<Moderator>> = Vec::with_capacity(n);
-    let mut pubs: Vec<Vec<PublicKey>> = Vec::new();
 
-    for (i, num_moderators) in MOD_SCALE.iter().enumerate() {
-        let k = usize::try_from(*num_moderators).unwrap();
-        let (mods, pks) = test_setup_mod(&mut platforms[i], k);
-        moderators.push(mods);
-        pubs.push(pks);
+    fn init_client_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
+        Client::new_with_rng(rng)
     }
 
-    (platforms, moderators, pubs)
-}
+    fn send_with_rng<R: CryptoRng + RngCore>(client: &Client, message: &str, moderator_id: u32, pks: &[PublicKey], rng: &mut R) -> Result<SentMessage, ReportingError> {
+        let pk_i = pks.get(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
 
+        client.send(message, moderator_id, pk_i, rng)
+    }
 
-// Setup Clients
-pub fn test_init_clients(num_clients: usize) -> Vec<Client> {
-    let mut clients: Vec<Client> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let client = Client::new();
-        clients.push(client);
+    fn process_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, ct: &SentMessage, ctx: &ReportContext, rng: &mut R) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        platform.process(&ct.c1, &ct.c2, &ct.epk, ctx, rng)
     }
 
-    clients
-}
-// Setup Messages
-pub fn test_init_messages(num_clients: usize, msg_size: usize) -> Vec<String> {
-    // Prepare messages
-    let mut ms: Vec<String> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let m = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        ms.push(m);
+    fn read(client: &Client, pks: &[PublicKey], ct: &SentMessage, sigma: &Vec<u8>, st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        client.read(pks, &ct.c1, &ct.c2, sigma, st)
     }
-    ms
-}
 
+    fn report_gen(message: &str, rd: &ReportDoc) -> Result<Report, ReportingError> {
+        Client::report_gen(message, rd)
+    }
 
-// send(k, m, pk_i)
-pub fn test_send(num_clients: usize, moderators: &Vec<Moderator>, clients: &Vec<Client>, ms: &Vec<String>, print: bool) -> Vec<(Vec<u8>, Vec<u8>, Point)> {
-    let mut c1c2ad: Vec<(Vec<u8>, Vec<u8>, Point)> = Vec::with_capacity(num_clients);
-    let num_moderators = moderators.len();
+    fn report_gen_partial(message: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        Client::report_gen_partial(message, rd, reveal)
+    }
 
-    // send message i to client i to be moderated by random mod
-    let mut rng = thread_rng();
-    for i in 0..num_clients {
-        let mod_i = rng.gen_range(0..num_moderators);
-        let mod_ref = &moderators[usize::try_from(mod_i).unwrap()];
-        let pki: PublicKey = (mod_ref.pk_enc_1.clone(), mod_ref.pk_enc_2.clone(), mod_ref.k1_2.clone());
-        let (c1, c2, ad) = Client::send(&clients[i].msg_key, &ms[i], mod_i.try_into().unwrap(), &pki);
-        
-        if print {
-            // Additional Costs
-            // (1) Commitment to the Message
-            // (2) Moderator masked public key (element of G)
-            // (3) commitment randomness (32 bytes)
-            // (4) Scalar of G (32 bytes)
-            // Moderator id (4 bytes)
-            let mut send_cost: usize = mem::size_of_val(&*c2) + mem::size_of_val(&ad) + 32 + 32 + 4;
-
-            println!("Sending communication cost: {} (bytes)", &send_cost);
-        }
-        c1c2ad.push((c1, c2, ad));
+    fn moderate(moderator: &Moderator, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        moderator.moderate(message, report, policy)
     }
 
-    c1c2ad
-}
+    // (1) Commitment to the Message
+    // (2) Moderator masked public key (element of G)
+    // (3) commitment randomness (32 bytes)
+    // (4) Scalar of G (32 bytes)
+    // (5) Moderator id (4 bytes)
+    // (6) Key epoch (4 bytes)
+    fn send_cost(ct: &SentMessage) -> usize {
+        mem::size_of_val(&*ct.c2) + mem::size_of_val(&ct.epk) + 32 + 32 + 4 + 4
+    }
 
-// Send messages of sizes in MSG_SIZE_SCALE
-// to platforms with num moderators in MOD_SCALE
-pub fn test_send_variable(moderators: &Vec<Vec<Moderator>>, clients: &Vec<Client>, ms: &Vec<Vec<String>>) -> 
-Vec<Vec<Vec<(Vec<u8>, Vec<u8>, Point)>>> {
-    // Send messages
-    let mut c1c2ad: Vec<Vec<Vec<(Vec<u8>, Vec<u8>, Point)>>> = Vec::new();
-    // c1c2ad[i][j] = Encryption of message j to moderator i
-    for i in 0..moderators.len() {
-        let mut tmp: Vec<Vec<(Vec<u8>, Vec<u8>, Point)>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-        for (j, _msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
-            tmp.push(test_send(1, &moderators[i], clients, &ms[j], false));
-        }
-        c1c2ad.push(tmp);
+    // (1) commitment randomness (32 bytes)
+    // (2) commitment
+    // (3) sigmas (36 bytes: moderator id and tag * # moderators not revoked)
+    // (4) epk
+    // (5) el gamal ct
+    // (6) Scalar of G
+    // (7) moderator id (4 bytes)
+    // (8) key epoch
+    // (9) message id
+    // (10) sequence number
+    fn receive_cost(sigmas: &Vec<u8>, st: &ProcessState, rd: &ReportDoc) -> usize {
+        let ReportDoc { k_f, c2, k_r, c3: (u, v), epoch, .. } = rd;
+
+        mem::size_of_val(&st.epk) + mem::size_of_val(k_f) + mem::size_of_val(&**c2) + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(k_r) + mem::size_of_val(&**sigmas) + 4 + mem::size_of_val(epoch) + mem::size_of_val(&st.ctx.message_id) + mem::size_of_val(&st.ctx.sequence)
     }
 
-    c1c2ad
-}
+    // (1) commitment randomness (32 bytes)
+    // (2) commitment
+    // (3) sigma (32 bytes)
+    // (4) moderator id (4 bytes)
+    // (5) el gamal ct
+    // (6) Scalar of G
+    // (7) key epoch
+    // (8) message id
+    // (9) sequence number
+    fn storage_cost(rd: &ReportDoc) -> usize {
+        let ReportDoc { k_f, c2, sigma, k_r, c3: (u, v), epoch, ctx } = rd;
+
+        4 + mem::size_of_val(k_f) + mem::size_of_val(&**c2) + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(k_r) + mem::size_of_val(&**sigma) + mem::size_of_val(epoch) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
 
+    // (1) commitment
+    // (2) opening of the commitment (k_f, 32 bytes, or the disclosed segments)
+    // (3) sigma
+    // (4) el gamal ct
+    // (5) key epoch
+    // (6) message id
+    // (7) sequence number
+    fn moderation_cost(report: &Report) -> usize {
+        let Report { opening, c2, c3_prime: (u, v), sigma, epoch, ctx } = report;
+
+        opening.cost() + mem::size_of_val(&**c2) + mem::size_of_val(u) + mem::size_of_val(v) + mem::size_of_val(&**sigma) + mem::size_of_val(epoch) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
+}
 
-// process(k_p, ks, c1, c2, ad, ctx)
-pub fn test_process(num_clients: usize, msg_size: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, Point)>, platform: &Platform, print: bool) -> Vec<(Vec<u8>, ProcessState)> {
-    let mut sigma_st: Vec<(Vec<u8>, ProcessState)> = Vec::with_capacity(num_clients);
-    // Platform processes message
-    for i in 0..num_clients {
-        let (c1, c2, ad) = &c1c2ad[i];
-        let ctx = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        let (sigma, st) = Platform::process(&platform.k_p, &platform.sk_p, &c1, &c2, ad, &ctx.as_bytes().to_vec());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::lib_scheme::Fixture;
+    use crate::lib_wire::check_encoding;
+    use crate::lib_verdict::check_signed_verdict;
+
+    #[test]
+    fn registration_checks_proof_of_possession() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut platform = Platform::new_with_rng(&mut rng);
+        let pending = PendingModerator::new_with_rng(&platform.k_reg, &mut rng);
+        let other = PendingModerator::new_with_rng(&platform.k_reg, &mut rng);
+
+        // A proof for other keys
+        let request = RegistrationRequest { pop: other.request_with_rng(&mut rng).pop, ..pending.request_with_rng(&mut rng) };
+        assert_eq!(platform.register_with_rng(&request, &mut rng).err(), Some(ReportingError::BadProof));
+    }
 
-        sigma_st.push((sigma, st));
+    #[test]
+    fn revoked_moderator_gets_no_tag() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut platform = Platform::new_with_rng(&mut rng);
+        let (moderators, pks) = ModPriv::setup_mod_with_rng(&mut platform, 2, &mut rng);
+        let mut client = Client::new_with_rng(&mut rng);
+
+        let to_revoked = ModPriv::send_with_rng(&client, "m", 0, &pks, &mut rng).unwrap();
+        let to_other = ModPriv::send_with_rng(&client, "m", 1, &pks, &mut rng).unwrap();
+        let list = platform.revoke_with_rng(0, &mut rng).unwrap();
+
+        // Only the moderator that is not revoked gets a tag
+        let (sigma, st) = ModPriv::process_with_rng(&mut platform, &to_revoked, &ReportContext::new(1, 2, 3), &mut rng).unwrap();
+        assert_eq!(sigma.len(), TAG_ENTRY);
+        assert_eq!(ModPriv::read(&client, &pks, &to_revoked, &sigma, &st).err(), Some(ReportingError::RevokedModerator(0)));
+
+        let (sigma, st) = ModPriv::process_with_rng(&mut platform, &to_other, &ReportContext::new(1, 2, 3), &mut rng).unwrap();
+        let (message, _, rd) = ModPriv::read(&client, &pks, &to_other, &sigma, &st).unwrap();
+        let report = ModPriv::report_gen(&message, &rd).unwrap();
+        assert!(moderators[1].moderate(&message, &report, &ExpiryPolicy::unlimited()).unwrap().is_accept());
+
+        // Once it knows of the revocation, the client refuses the moderator itself
+        client.update_revocations(&list, &platform.k_reg).unwrap();
+        assert_eq!(ModPriv::send_with_rng(&client, "m", 0, &pks, &mut rng).err(), Some(ReportingError::RevokedModerator(0)));
+        assert_eq!(ModPriv::read(&client, &pks, &to_revoked, &sigma, &st).err(), Some(ReportingError::RevokedModerator(0)));
     }
 
-    sigma_st
-}
+    #[test]
+    fn tampered_tag_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<ModPriv>::new(&mut rng);
 
-// Process messages of sizes in MSG_SIZE_SCALE
-// and encrypt them to moderators in MOD_SCALE
-pub fn test_process_variable(moderators: &Vec<Vec<Moderator>>, c1c2ad: &Vec<Vec<Vec<(Vec<u8>, Vec<u8>, Point)>>>, platforms: &Vec<Platform>) -> Vec<Vec<Vec<(Vec<u8>, ProcessState)>>> {
-    // Process messages
-    let mut sigma_st: Vec<Vec<Vec<(Vec<u8>, ProcessState)>>> = Vec::new();
-    // sigma_st[i][j] = encrypted signature on message commitmment j to moderator i
-    for i in 0..moderators.len() {
-        let mut tmp: Vec<Vec<(Vec<u8>, ProcessState)>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
-        for (j, msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
-            tmp.push(test_process(1, *msg_size, &c1c2ad[i][j], &platforms[i], false));
-        }
-        sigma_st.push(tmp);
-    }
-
-    sigma_st
-}
-
-// read(k, pks, c1, c2, sigma, st)
-pub fn test_read(num_clients: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, Point)>, sigma_st: &Vec<(Vec<u8>, ProcessState)>, clients: &Vec<Client>, pks: &Vec<PublicKey>, print: bool) -> Vec<(String, u32, ReportDoc)> {
-    // Receive messages
-    let mut rds: Vec<(String, u32, ReportDoc)> = Vec::with_capacity(num_clients);
-    // Receive message i from client i to be moderated by randomly selected moderator mod_i
-    for i in 0..num_clients {
-        let (c1, c2, _ad) = &c1c2ad[i];
-        let (sigmas, st) = &sigma_st[i];
-        let (message, ad, rd) = Client::read(&clients[i].msg_key, &pks, &c1, &c2, &sigmas, &st);
-
-        if print {
-            let (_, epk, __) = st.clone();
-            let (k_f, c2, _ctx, sigma, k_r, c3): ReportDoc = rd.clone();
-            let (u, v) = c3;
-            // Receiving communication cost
-            // (1) commitment randomness (32 bytes)
-            // (2) commitment
-            // (3) sigmas (32 bytes * # moderators)
-            // (4) epk
-            // (5) el gamal ct
-            // (6) Scalar of G
-            // (7) moderator id (4 bytes)
-
-            let mut recv_cost: usize = mem::size_of_val(&epk) + mem::size_of_val(&k_f) + mem::size_of_val(&*c2) + mem::size_of_val(&u) + mem::size_of_val(&v) + mem::size_of_val(&k_r) + mem::size_of_val(&**sigmas) + mem::size_of_val(&ad);
-
-            println!("Receiving communication cost: {} (bytes)", &recv_cost);
-
-
-            // Storage communication cost
-            // (1) commitment randomness (32 bytes)
-            // (2) commitment
-            // (3) sigma (32 bytes)
-            // (4) moderator id
-            // (5) el gamal ct
-            // (6) Scalar of G
-
-            let mut storage_cost: usize = mem::size_of_val(&ad) + mem::size_of_val(&k_f) + mem::size_of_val(&*c2) + mem::size_of_val(&u) + mem::size_of_val(&v) + mem::size_of_val(&k_r) + mem::size_of_val(&*sigma);
-            println!("Storage communication cost: {} (bytes)", &storage_cost);
-        }
-        rds.push((message, ad, rd));
+        // The tag is bound to the context
+        let mut report = f.report.clone();
+        report.ctx.timestamp += 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+
+        // MAC chosen without sk_p
+        let mut report = f.report.clone();
+        rng.fill_bytes(&mut report.sigma);
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
     }
 
-    rds
-}
+    #[test]
+    fn undecryptable_report_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let f = Fixture::<ModPriv>::new(&mut rng);
 
-pub fn test_report(num_clients: usize, rds: &Vec<(String, u32, ReportDoc)>, print: bool) -> Vec<(String, u32, Report)> {
-    let mut reports: Vec<(String, u32, Report)> = Vec::with_capacity(num_clients);
+        let mut report = f.report.clone();
+        report.epoch = 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::UnknownEpoch(1)));
 
-    for i in 0..num_clients {
-        let (message, mod_id, rd) = &rds[i];
-        let report = Client::report_gen(&message, &rd);
+        let mut report = f.report.clone();
+        report.c3_prime.0 = CompressedRistretto([0xff; 32]);
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::InvalidCurvePoint));
+    }
 
+    #[test]
+    fn wire_encoding_round_trips() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<ModPriv>::new(&mut rng);
 
-        reports.push((message.clone(), *mod_id, report));
+        check_encoding(&f.ct);
+        check_encoding(&(f.sigma.clone(), f.st.clone()));
+        check_encoding(&f.rd);
+        check_encoding(&f.report);
     }
 
-    reports
-}
+    #[test]
+    fn signed_verdicts_verify() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let mut f = Fixture::<ModPriv>::new(&mut rng);
+        let policy = ExpiryPolicy::default();
 
+        let accepted = f.moderators[0].moderate_signed_with_rng(&f.message, &f.report, &policy, &mut rng).unwrap();
+        assert!(accepted.verdict.is_accept());
+        check_signed_verdict(&accepted, |sv| verify_verdict(&f.pks[0], &f.message, &f.report, sv));
 
-// moderate(sk_mod, sk_p, m, report)
-pub fn test_moderate(num_clients: usize, reports: &Vec<(String, u32, Report)>, moderators: &Vec<Moderator>, print: bool) {
-    // Moderate messages
-    for i in 0..num_clients {
-        let (message, moderator_id, report) = &reports[i];
-        let j = usize::try_from(*moderator_id).unwrap();
-        let ctx = Moderator::moderate(&moderators[j].sk_enc, &moderators[j].sk_p, j, &message, &report);
-        if print {
-            // Judging cost is inputs to moderate
-            // (1) commitment
-            // (2) commitment randomness (32 bytes)
-            // (3) sigma
-            // (4) el gamal ct
+        let rejected = f.moderators[0].moderate_signed_with_rng("other", &f.report, &policy, &mut rng).unwrap();
+        assert_eq!(rejected.verdict, Verdict::Reject(ReportingError::BadCommitment));
+        check_signed_verdict(&rejected, |sv| verify_verdict(&f.pks[0], "other", &f.report, sv));
 
-            let (k_f, c2, c3_prime, ctx, sigma) = report.clone();
-            let (u, v) = c3_prime.clone();
-            let mut cost: usize = mem::size_of_val(&k_f) + mem::size_of_val(&*c2) + mem::size_of_val(&u) + mem::size_of_val(&v) + mem::size_of_val(&*sigma);
-            println!("Moderation communication cost: {} (bytes)", &cost);
-        }
+        // Signed by the key of another moderator
+        let (_moderators, pks) = ModPriv::setup_mod_with_rng(&mut f.platform, 1, &mut rng);
+        assert_eq!(verify_verdict(&pks[0], &f.message, &f.report, &accepted), Err(ReportingError::BadProof));
     }
 
+    #[test]
+    fn replayed_message_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut f = Fixture::<ModPriv>::new(&mut rng);
+
+        let replay = ModPriv::process_with_rng(&mut f.platform, &f.ct, &ReportContext::new(1, 2, 3), &mut rng);
+        assert_eq!(replay.err(), Some(ReportingError::DuplicateMessage(3)));
+        assert_eq!(f.read(&f.sigma, &f.st).unwrap().0, f.message);
+
+        // The same text sent again is a new message
+        let ct = ModPriv::send_with_rng(&f.client, &f.message, 0, &f.pks, &mut rng).unwrap();
+        let (_sigma, st) = ModPriv::process_with_rng(&mut f.platform, &ct, &ReportContext::new(1, 2, 3), &mut rng).unwrap();
+        assert_eq!(st.ctx.message_id, f.st.ctx.message_id + 1);
+    }
 }
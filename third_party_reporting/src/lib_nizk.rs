@@ -0,0 +1,133 @@
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use rand::{CryptoRng, RngCore};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha512};
+use crate::lib_domain::Domain;
+use crate::lib_secret::Secret;
+use crate::lib_wire::FieldWriter;
+
+type Point = RistrettoPoint;
+
+// Sigma protocols over Ristretto made non-interactive with Fiat-Shamir
+//
+// The challenge is c = H(label, message, statement, commitments) with every
+// part written as a length-prefixed field (lib_wire.rs) and H = Sha512 reduced
+// mod the group order, the label binds every proof to its scheme, role and
+// usage. The message is empty except for proofs that sign something (verdicts,
+// registration requests and certificates), it enters as its Sha512 digest so
+// every field has a fixed size.
+
+// Chaum-Pedersen proof of equality of discrete logarithms
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct DleqProof {
+    pub c: Scalar, // Challenge
+    pub z: Scalar // Response
+}
+
+// Schnorr proof of knowledge of x = log_G(a), a signature on the message
+// absorbed into the challenge
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SchnorrProof {
+    pub c: Scalar, // Challenge
+    pub z: Scalar // Response
+}
+
+// Proof that (u, v) is an El Gamal encryption under pk of k * h, where k is
+// the discrete logarithm of a public key_pt = k * G
+//
+// Statement: u = r * G, key_pt = k * G, v = r * pk + k * h for some (r, k)
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct EncryptionProof {
+    pub c: Scalar, // Challenge
+    pub z_r: Scalar, // Response for the encryption randomness
+    pub z_k: Scalar // Response for the tag key
+}
+
+// Proves log_G(a) = log_h(b) = x
+#[cfg(any(feature = "basic", feature = "constant_mod_priv"))]
+pub(crate) fn dleq_prove<R: CryptoRng + RngCore>(domain: &Domain, x: &Scalar, h: &Point, a: &Point, b: &Point, rng: &mut R) -> DleqProof {
+    dleq_prove_with_message(domain, x, h, a, b, &[], rng)
+}
+
+#[cfg(any(feature = "basic", feature = "constant_mod_priv"))]
+pub(crate) fn dleq_verify(domain: &Domain, h: &Point, a: &Point, b: &Point, proof: &DleqProof) -> bool {
+    dleq_verify_with_message(domain, h, a, b, &[], proof)
+}
+
+// Same proof with message absorbed into the challenge, which makes it a
+// signature on message by the holder of x (a = x * G)
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv"))]
+pub(crate) fn dleq_prove_with_message<R: CryptoRng + RngCore>(domain: &Domain, x: &Scalar, h: &Point, a: &Point, b: &Point, message: &[u8], rng: &mut R) -> DleqProof {
+    let k = Secret::new(Scalar::random(rng));
+    let k_g = RistrettoPoint::mul_base(&k);
+    let k_h = *k * h;
+
+    let c = challenge(domain, message, &[h, a, b], &[&k_g, &k_h]);
+    let z = *k + c * x;
+
+    DleqProof { c, z }
+}
+
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv"))]
+pub(crate) fn dleq_verify_with_message(domain: &Domain, h: &Point, a: &Point, b: &Point, message: &[u8], proof: &DleqProof) -> bool {
+    let DleqProof { c, z } = proof;
+    let k_g = RistrettoPoint::mul_base(z) - c * a;
+    let k_h = z * h - c * b;
+
+    challenge(domain, message, &[h, a, b], &[&k_g, &k_h]) == *c
+}
+
+// Proves knowledge of x = log_G(a), signing message
+pub(crate) fn schnorr_prove<R: CryptoRng + RngCore>(domain: &Domain, x: &Scalar, a: &Point, message: &[u8], rng: &mut R) -> SchnorrProof {
+    let k = Secret::new(Scalar::random(rng));
+    let k_g = RistrettoPoint::mul_base(&k);
+
+    let c = challenge(domain, message, &[a], &[&k_g]);
+
+    SchnorrProof { c, z: *k + c * x }
+}
+
+pub(crate) fn schnorr_verify(domain: &Domain, a: &Point, message: &[u8], proof: &SchnorrProof) -> bool {
+    let SchnorrProof { c, z } = proof;
+    let k_g = RistrettoPoint::mul_base(z) - c * a;
+
+    challenge(domain, message, &[a], &[&k_g]) == *c
+}
+
+// Proves that (u, v) = (r * G, r * pk + k * h) with key_pt = k * G
+#[cfg(feature = "basic")]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn encryption_prove<R: CryptoRng + RngCore>(domain: &Domain, r: &Scalar, k: &Scalar, pk: &Point, key_pt: &Point, h: &Point, u: &Point, v: &Point, rng: &mut R) -> EncryptionProof {
+    let a = Secret::new(Scalar::random(rng));
+    let b = Secret::new(Scalar::random(rng));
+    let a_u = RistrettoPoint::mul_base(&a);
+    let a_k = RistrettoPoint::mul_base(&b);
+    let a_v = *a * pk + *b * h;
+
+    let c = challenge(domain, &[], &[pk, key_pt, h, u, v], &[&a_u, &a_k, &a_v]);
+
+    EncryptionProof { c, z_r: *a + c * r, z_k: *b + c * k }
+}
+
+#[cfg(feature = "basic")]
+pub(crate) fn encryption_verify(domain: &Domain, pk: &Point, key_pt: &Point, h: &Point, u: &Point, v: &Point, proof: &EncryptionProof) -> bool {
+    let EncryptionProof { c, z_r, z_k } = proof;
+    let a_u = RistrettoPoint::mul_base(z_r) - c * u;
+    let a_k = RistrettoPoint::mul_base(z_k) - c * key_pt;
+    let a_v = z_r * pk + z_k * h - c * v;
+
+    challenge(domain, &[], &[pk, key_pt, h, u, v], &[&a_u, &a_k, &a_v]) == *c
+}
+
+// c = H(label, message, statement, commitments)
+fn challenge(domain: &Domain, message: &[u8], statement: &[&Point], commitments: &[&Point]) -> Scalar {
+    let mut transcript = FieldWriter::new();
+    transcript.bytes(&domain.label());
+    transcript.bytes(&Sha512::digest(message));
+    for p in statement.iter().chain(commitments) {
+        transcript.point(&p.compress());
+    }
+
+    Scalar::hash_from_bytes::<Sha512>(&transcript.finish().expect("fixed-size fields"))
+}
@@ -1,212 +1,343 @@
-use rand::RngCore;
+use rand::{CryptoRng, Rng, RngCore};
+use rand::rngs::OsRng;
 use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit},
-    Aes256Gcm, Nonce, Key
+    aead::KeyInit,
+    Aes256Gcm, Key
 };
-use rand::distributions::Alphanumeric; 
-use rand::distributions::DistString;
-use bincode;
 use std::mem;
+use serde::{Serialize, Deserialize};
 
 use crate::lib_common::*;
+use crate::lib_error::ReportingError;
+use crate::lib_replay::ReplayCache;
+use crate::lib_context::{ReportContext, ExpiryPolicy};
+use crate::lib_merkle::Opening;
+use crate::lib_scheme::ReportingScheme;
+use crate::lib_secret::Secret;
+use crate::lib_wire::*;
+use crate::lib_domain::{Domain, Role, Usage};
+
+// Domain separation labels (see lib_domain.rs)
+const COMMITMENT: Domain = Domain::new(SCHEME_PLAIN, Role::Sender, Usage::Commitment);
+const PLATFORM_TAG: Domain = Domain::new(SCHEME_PLAIN, Role::Platform, Usage::PlatformTag);
 
 pub struct Client {
     pub uid: u32,
-    pub k_r: Key<Aes256Gcm>, // Symmetric key shared with the receiver
+    k_r: Secret<Key<Aes256Gcm>>, // Symmetric key shared with the receiver
 }
 
 pub struct Moderator {
-    pub k_m: [u8; 32]
+    k_m: Secret<[u8; 32]>,
+    pub replay: ReplayCache // Commitments processed per conversation (lib_replay.rs)
+}
+
+// Output of send: (c1, c2)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub c1: Vec<u8>, // Encryption of (m, k_f) under the shared key
+    pub c2: Vec<u8> // Commitment to the message
+}
+
+// State output by process alongside the franking tag
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessState {
+    pub ctx: ReportContext // Platform context, bound into the tag
+}
+
+// Report sent to the moderator (the report doc stored by the receiver is identical)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub ctx: ReportContext, // Platform context
+    pub opening: Opening, // Opens c2 to the message (k_f) or to some of its segments (lib_merkle.rs)
+    pub c2: Vec<u8>, // Commitment to the message
+    pub sigma: Vec<u8> // Franking tag
+}
+
+pub type ReportDoc = Report;
+
+impl WireMessage for SentMessage {
+    const SCHEME_ID: u8 = SCHEME_PLAIN;
+    const KIND: MessageKind = MessageKind::Send;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.c1);
+        w.bytes(&self.c2);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<SentMessage, ReportingError> {
+        Ok(SentMessage { c1: r.bytes()?.to_vec(), c2: r.bytes()?.to_vec() })
+    }
+}
+
+// (sigma, st)
+impl WireMessage for (Vec<u8>, ProcessState) {
+    const SCHEME_ID: u8 = SCHEME_PLAIN;
+    const KIND: MessageKind = MessageKind::Process;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.0);
+        w.bytes(&self.1.ctx.to_bytes());
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        Ok((r.bytes()?.to_vec(), ProcessState { ctx: ReportContext::from_bytes(r.bytes()?)? }))
+    }
+}
+
+// Report docs are stored with the same encoding as reports
+impl WireMessage for Report {
+    const SCHEME_ID: u8 = SCHEME_PLAIN;
+    const KIND: MessageKind = MessageKind::Report;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.ctx.to_bytes());
+        self.opening.write_fields(w);
+        w.bytes(&self.c2);
+        w.bytes(&self.sigma);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Report, ReportingError> {
+        Ok(Report { ctx: ReportContext::from_bytes(r.bytes()?)?, opening: Opening::read_fields(r)?, c2: r.bytes()?.to_vec(), sigma: r.bytes()?.to_vec() })
+    }
 }
 
 // Client operations
 
 impl Client {
     pub fn new(k_r: Key<Aes256Gcm>) -> Client {
+        Self::new_with_rng(k_r, &mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(k_r: Key<Aes256Gcm>, rng: &mut R) -> Client {
         Client {
-            uid: rand::random(),
-            k_r: k_r,
+            uid: rng.gen(),
+            k_r: Secret::new(k_r),
         }
     }
 
-    pub fn send(message: &str, k_r: Key<Aes256Gcm>) -> (Vec<u8>, Vec<u8>) {
-        let mut k_f: Vec<u8> = vec![0; 32];
-        rand::thread_rng().fill_bytes(&mut k_f);
+    pub fn send<R: CryptoRng + RngCore>(&self, message: &str, rng: &mut R) -> Result<SentMessage, ReportingError> {
+        let k_f = Secret::new(mac_keygen_with_rng(rng));
 
-        let c2 = com_commit(&k_f, message);
+        let c2 = com_commit(&COMMITMENT, &k_f[..], message);
 
-        let cipher = Aes256Gcm::new(&k_r);
-        let nonce = Aes256Gcm::generate_nonce(&mut rand::rngs::OsRng);
+        let mut payload = FieldWriter::new();
+        payload.bytes(message.as_bytes());
+        payload.bytes(&k_f[..]);
+        let payload = Secret::new(payload.finish()?);
+        let c1 = sym_enc(&self.k_r, &payload, rng);
 
-        let payload = bincode::serialize(&(message, k_f)).expect("");
-        let c1_obj = cipher.encrypt(&nonce, payload.as_slice()).unwrap();
-        let c1 = bincode::serialize::<(Vec<u8>, Vec<u8>)>(&(c1_obj, nonce.to_vec())).expect("");
-
-        (c1, c2)
+        Ok(SentMessage { c1, c2 })
     }
 
-    pub fn read(k_r: Key<Aes256Gcm>, c1: Vec<u8>, rt: (Vec<u8>, String, Vec<u8>)) -> (String, String, (Vec<u8>, Vec<u8>), Vec<u8>) {
-
-        let c1_obj = bincode::deserialize::<(Vec<u8>, Vec<u8>)>(&c1).unwrap();
-        let ct = c1_obj.0;
-        let nonce = Nonce::from_slice(&c1_obj.1);
-
-        let cipher = Aes256Gcm::new(&k_r);
-        let payload_bytes = cipher.decrypt(&nonce, ct.as_ref()).unwrap();
-        let payload = bincode::deserialize::<(&str, Vec<u8>)>(&payload_bytes).unwrap();
+    pub fn read(&self, ct: &SentMessage, sigma: &[u8], st: &ProcessState) -> Result<(String, ReportDoc), ReportingError> {
+        let SentMessage { c1, c2 } = ct;
 
-        let (m, k_f) = payload;
-
-        let (c2, ctx, sigma) = rt;
+        let payload_bytes = Secret::new(sym_dec(&self.k_r, c1)?);
+        let mut payload = FieldReader::new(&payload_bytes);
+        let m = payload.string()?;
+        let k_f = Secret::new(payload.array::<32>()?);
+        payload.finish()?;
 
         // Verify franking tag
-        assert!(com_open(&c2, m, &k_f));
+        if !com_open(&COMMITMENT, c2, &m, &k_f[..]) {
+            return Err(ReportingError::BadCommitment);
+        }
 
-        let rd = (k_f.to_vec(), c2);
+        let rd = ReportDoc { ctx: st.ctx, opening: Opening::Message(k_f), c2: c2.clone(), sigma: sigma.to_vec() };
 
-        (m.to_string(), ctx.to_string(), rd, sigma)
+        Ok((m, rd))
     }
 }
 
 // Moderator operations
 
 impl Moderator {
-    pub fn mod_process(k_m: &[u8; 32], c2: &Vec<u8>, ctx: &str) -> Vec<u8> {
-        let sigma = mac_sign(k_m, &[&c2, ctx.as_bytes()].concat());
+    // Tags c2 for a message of ctx.conversation under a fresh message id and
+    // sequence number, fails with DuplicateMessage if c2 was already processed in it
+    pub fn mod_process(&mut self, c2: &[u8], ctx: &ReportContext) -> Result<(Vec<u8>, ReportContext), ReportingError> {
+        let ctx = self.replay.admit(ctx, c2)?;
+        let sigma = mac_sign(&PLATFORM_TAG, &self.k_m, &[c2, &ctx.to_bytes()].concat());
 
-        sigma
+        Ok((sigma, ctx))
     }
 
-    pub fn moderate(k_m: &[u8; 32], m: &str, ctx: &str, rd: (Vec<u8>, Vec<u8>), sigma: Vec<u8>) -> bool {
-        let (k_f, c2) = rd;
+    pub fn moderate(&self, m: &str, report: &Report, policy: &ExpiryPolicy) -> Verdict {
+        let Report { ctx, opening, c2, sigma } = report;
+
+        // Verify committment
+        if !opening.open(&COMMITMENT, c2, m) {
+            return Verdict::Reject(ReportingError::BadCommitment);
+        }
 
-        let valid_f = com_open(&c2, m, &k_f);
-        let valid_r = mac_verify(k_m, &[&c2, ctx.as_bytes()].concat(), &sigma);
+        // Verify franking tag
+        if !mac_verify(&PLATFORM_TAG, &self.k_m, &[&c2[..], &ctx.to_bytes()].concat(), sigma) {
+            return Verdict::Reject(ReportingError::BadPlatformTag);
+        }
 
-        valid_f && valid_r
+        policy.verdict(ctx)
     }
 
     pub fn new() -> Moderator {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Moderator {
         Moderator {
-            k_m: mac_keygen()
+            k_m: Secret::new(mac_keygen_with_rng(rng)),
+            replay: ReplayCache::new()
         }
     }
 }
 
-
-
-// Setup Messages
-pub fn test_init_messages(num_clients: usize, msg_size: usize) -> Vec<String> {
-    // Prepare messages
-    let mut ms: Vec<String> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let m = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        ms.push(m);
+impl Default for Moderator {
+    fn default() -> Moderator {
+        Moderator::new()
     }
-    ms
 }
 
-// Clients
-pub fn test_init_clients(num_clients: usize) -> Vec<Client> {
-    let mut clients: Vec<Client> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let k_r = Aes256Gcm::generate_key(aes_gcm::aead::OsRng);
-        let client = Client::new(k_r);
-        clients.push(client);
+
+// Plain message franking: the moderator also acts as the platform
+pub struct Plain;
+
+impl ReportingScheme for Plain {
+    const NAME: &'static str = "Plain Franking Scheme";
+
+    type Platform = Moderator;
+    type Moderator = Moderator;
+    type Client = Client;
+    type PublicKey = ();
+    type Ciphertext = SentMessage;
+    type Sigma = Vec<u8>;
+    type ProcessState = ProcessState;
+    type ReportDoc = ReportDoc;
+    type Report = Report;
+
+    fn setup_platform_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Moderator {
+        Moderator::new_with_rng(rng)
     }
 
-    clients
-}
+    // Every moderator shares the platform's mac key
+    fn setup_mod_with_rng<R: CryptoRng + RngCore>(platform: &mut Moderator, num_moderators: usize, _rng: &mut R) -> (Vec<Moderator>, Vec<()>) {
+        let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
+        for _i in 0..num_moderators {
+            moderators.push(Moderator { k_m: platform.k_m.clone(), replay: ReplayCache::new() });
+        }
+
+        (moderators, vec![(); num_moderators])
+    }
 
-// send()
-pub fn test_send(senders: &Vec<Client>, ms: &Vec<Vec<String>>, print: bool) -> Vec<(Vec<u8>, Vec<u8>)> {
-    let n: usize = senders.len();
-    let mut c1c2s: Vec<(Vec<u8>, Vec<u8>)> = Vec::with_capacity(n);
-    for i in 0..n {
-        let (c1, c2) = Client::send(&ms[i][0], senders[i].k_r);
+    fn init_client_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
+        Client::new_with_rng(Aes256Gcm::generate_key(&mut *rng), rng)
+    }
 
-        // let (c1, c2, c3) = p::Client::send(&ms[i], senders[i].k_r, &pks, n);
-        c1c2s.push((c1, c2));
+    fn send_with_rng<R: CryptoRng + RngCore>(client: &Client, message: &str, _moderator_id: u32, _pks: &[()], rng: &mut R) -> Result<SentMessage, ReportingError> {
+        client.send(message, rng)
     }
 
-    if print {
-        // Message sending Communication cost
-        // (1) commitment
-        // (2) commitment randomness (32 bytes)
-        
-        let (c1, c2) = c1c2s[0].clone();
+    fn process_with_rng<R: CryptoRng + RngCore>(platform: &mut Moderator, ct: &SentMessage, ctx: &ReportContext, _rng: &mut R) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        let (sigma, ctx) = platform.mod_process(&ct.c2, ctx)?;
 
-        let cost: usize = mem::size_of_val(&*c2) + 32;
-        
-        println!("Sending Communication cost: {}", &cost);
+        Ok((sigma, ProcessState { ctx }))
     }
 
-    c1c2s
-}
+    fn read(client: &Client, _pks: &[()], ct: &SentMessage, sigma: &Vec<u8>, st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        let (m, rd) = client.read(ct, sigma, st)?;
 
-// mod_process
-pub fn test_process(moderator: &Moderator, c1c2s: &Vec<(Vec<u8>, Vec<u8>)>) -> Vec<Vec<u8>> {
-    let n: usize = c1c2s.len();
-    let mut sigmas: Vec<Vec<u8>> = Vec::with_capacity(n);
+        Ok((m, 0, rd))
+    }
 
-    for i in 0..n {
-        let (_, c2) = &c1c2s[i];
-        sigmas.push(Moderator::mod_process(&moderator.k_m, &c2, CTX_STR));
+    fn report_gen(_message: &str, rd: &ReportDoc) -> Result<Report, ReportingError> {
+        Ok(rd.clone())
     }
 
-    sigmas
-}
+    fn report_gen_partial(message: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        Ok(Report { opening: rd.opening.disclose(&COMMITMENT, message, reveal)?, ..rd.clone() })
+    }
+
+    fn moderate(moderator: &Moderator, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        Ok(moderator.moderate(message, report, policy))
+    }
 
-// receive messages
-pub fn test_read(clients: &Vec<Client>, c1c2s: &Vec<(Vec<u8>, Vec<u8>)>, sigmas: &Vec<Vec<u8>>, print: bool) -> Vec<(String, String, (Vec<u8>, Vec<u8>), Vec<u8>)> {
-    let n: usize = c1c2s.len();
-    let mut reports: Vec<(String, String, (Vec<u8>, Vec<u8>), Vec<u8>)> = Vec::with_capacity(n);
+    // (1) commitment
+    // (2) commitment randomness (32 bytes)
+    fn send_cost(ct: &SentMessage) -> usize {
+        mem::size_of_val(&*ct.c2) + 32
+    }
 
-    for i in 0..n {
-        let (c1, c2) = c1c2s[i].clone();
-        let st = (c2, CTX_STR.to_string(), sigmas[i].clone());
-        reports.push(Client::read(clients[0].k_r, c1.clone(), st.clone()));
+    // (1) commitment
+    // (2) commitment randomness (32 bytes)
+    // (3) sigma
+    // (4) message id
+    // (5) sequence number
+    fn receive_cost(sigma: &Vec<u8>, st: &ProcessState, rd: &ReportDoc) -> usize {
+        mem::size_of_val(&*rd.c2) + mem::size_of_val(&**sigma) + 32 + mem::size_of_val(&st.ctx.message_id) + mem::size_of_val(&st.ctx.sequence)
     }
 
-    if print {
-        // Receiving Communication cost
-        // (1) Commitment
-        // (2) Commitment randomness (32 bytes)
-        // (3) Sigma
-        let (c1, c2) = c1c2s[0].clone();
-        let sigma = sigmas[0].clone();
+    fn storage_cost(rd: &ReportDoc) -> usize {
+        let ReportDoc { opening, c2, sigma, ctx } = rd;
 
-        let cost: usize = mem::size_of_val(&*c2) + mem::size_of_val(&*sigma) + 32;
-    
-        println!("Receiving Communication Cost: {}", &cost);
+        opening.cost() + mem::size_of_val(&**c2) + mem::size_of_val(&**sigma) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
     }
 
-    reports
+    // (1) commitment
+    // (2) opening of the commitment (k_f, 32 bytes, or the disclosed segments)
+    // (3) sigma (32 bytes)
+    // (4) message id
+    // (5) sequence number
+    fn moderation_cost(report: &Report) -> usize {
+        let Report { opening, c2, ctx, .. } = report;
+
+        opening.cost() + mem::size_of_val(&**c2) + 32 + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+    use crate::lib_scheme::Fixture;
+    use crate::lib_wire::check_encoding;
+
+    #[test]
+    fn tampered_tag_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<Plain>::new(&mut rng);
+
+        // The tag is bound to the context
+        let mut report = f.report.clone();
+        report.ctx.sender += 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+
+        // MAC chosen without k_m
+        let mut report = f.report.clone();
+        rng.fill_bytes(&mut report.sigma);
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+    }
 
-pub fn test_moderate(moderator: &Moderator, reports: &Vec<(String, String, (Vec<u8>, Vec<u8>), Vec<u8>)>, ms: &Vec<Vec<String>>, print: bool) -> Vec<bool> {
-    let n: usize = reports.len();
-    let mut judges: Vec<bool> = Vec::new();
+    #[test]
+    fn wire_encoding_round_trips() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<Plain>::new(&mut rng);
 
-    for i in 0..n {
-        let (m, ctx, rd, sigma) = &reports[i].clone();
-        judges.push(Moderator::moderate(&moderator.k_m, &m, &ctx, rd.clone(), sigma.to_vec()));
+        check_encoding(&f.ct);
+        check_encoding(&(f.sigma.clone(), f.st.clone()));
+        check_encoding(&f.rd); // Same type as the report
+        check_encoding(&f.report);
     }
-    
-    if print {
-        // Moderating Communication Cost
-        // (1) Commitment 
-        // (2) Commitment randomness (32 bytes)
-        // (3) sigma
 
-        let (m, ctx, rd, sigma) = &reports[0].clone();
-        let (k_f, c2) = rd.clone();
+    #[test]
+    fn replayed_message_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut f = Fixture::<Plain>::new(&mut rng);
 
-        let cost: usize = mem::size_of_val(&*k_f) + mem::size_of_val(&*c2) + 32;
+        let replay = Plain::process_with_rng(&mut f.platform, &f.ct, &ReportContext::new(1, 2, 3), &mut rng);
+        assert_eq!(replay.err(), Some(ReportingError::DuplicateMessage(3)));
+        assert_eq!(f.read(&f.sigma, &f.st).unwrap().0, f.message);
 
-        println!("Moderating Communication Cost: {}", &cost);
+        // The same text sent again is a new message
+        let ct = Plain::send_with_rng(&f.client, &f.message, 0, &f.pks, &mut rng).unwrap();
+        let (_sigma, st) = Plain::process_with_rng(&mut f.platform, &ct, &ReportContext::new(1, 2, 3), &mut rng).unwrap();
+        assert_eq!(st.ctx.message_id, f.st.ctx.message_id + 1);
     }
-    
-    judges
 }
@@ -0,0 +1,183 @@
+use crate::lib_error::ReportingError;
+use crate::lib_wire::{FieldWriter, FieldReader};
+
+// Moderator registration, the same handshake in every scheme with moderator keys
+//
+//   1. moderator -> platform: RegistrationRequest, the moderator public keys
+//      with a proof of possession of the secret keys over pop_message, which
+//      binds the request to the platform's k_reg
+//   2. the platform verifies the proof, draws the MAC (tag) key it shares with
+//      the moderator, assigns the moderator id and signs a Certificate on
+//      certificate_message(moderator id, public keys) with k_p
+//   3. platform -> moderator: RegistrationResponse, over a private channel
+//      since it carries the MAC key. The moderator checks the certificate
+//      against k_reg before using the key
+//
+// Clients check the certificate of a moderator against k_reg before trusting
+// its public keys. k_reg is a Ristretto Schnorr key in the basic, moderator
+// privacy and RSA schemes and the BLS key g2^(1/k_p) in the constant scheme.
+// The plain scheme has no moderator keys, its moderators hold the platform key.
+//
+// Moderator ids are stable: they are assigned in registration order, never
+// reused, and revoking a moderator empties its directory slot instead of
+// removing it. The platform signs the list of revoked ids with k_reg under a
+// version that grows with every revocation, clients keep the newest list they
+// verified (Revocations) and refuse to send to or read for a revoked id.
+//
+// Moderator keys belong to an epoch, counted from 0 at registration and part
+// of the certified public key. A moderator rotates by sending a
+// RotationRequest: keys for the next epoch with a proof of possession, signed
+// with the current key over rotation_message. The platform answers with a new
+// certificate, the MAC (tag) key shared with the platform does not change.
+// Reports name the epoch of the key they were made under, the moderator keeps
+// the keys of the last grace_epochs epochs (RetiredKeys) and rejects reports
+// of older epochs with ExpiredEpoch. Rotation covers single moderators of the
+// basic, moderator privacy and constant schemes only: a committee (lib_basic)
+// stays at epoch 0 and the RSA variant has no epochs.
+//
+// In the constant scheme the platform can also rotate its own BLS key. It signs
+// key_rotation_message(version, new k_reg) with the previous key and
+// re-certifies every directory entry under the new one.
+
+// Message covered by the proof of possession
+pub(crate) fn pop_message(k_reg: &[u8], public_key: &[u8]) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    w.bytes(k_reg);
+    w.bytes(public_key);
+
+    w.finish().expect("fixed-size fields")
+}
+
+// Message signed by the platform in a certificate
+pub(crate) fn certificate_message(moderator_id: u32, public_key: &[u8]) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    w.u32(moderator_id);
+    w.bytes(public_key);
+
+    w.finish().expect("fixed-size fields")
+}
+
+// Message signed by the platform in a revocation list
+pub(crate) fn revocation_message(version: u32, revoked: &[u32]) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    w.u32(version);
+    write_ids(&mut w, revoked);
+
+    w.finish().expect("fixed-size fields")
+}
+
+// Message signed with the previous platform key to endorse the new k_reg
+#[cfg(feature = "constant_mod_priv")]
+pub(crate) fn key_rotation_message(version: u32, k_reg: &[u8]) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    w.u32(version);
+    w.bytes(k_reg);
+
+    w.finish().expect("fixed-size fields")
+}
+
+// Moderator ids as a count followed by the ids
+pub(crate) fn write_ids(w: &mut FieldWriter, ids: &[u32]) {
+    w.u32(ids.len() as u32);
+    for id in ids {
+        w.u32(*id);
+    }
+}
+
+pub(crate) fn read_ids(r: &mut FieldReader) -> Result<Vec<u32>, ReportingError> {
+    let count = r.u32()?;
+    (0..count).map(|_i| r.u32()).collect()
+}
+
+// Epochs a moderator keeps a rotated out key for, unless it sets its own
+pub const GRACE_EPOCHS: u32 = 1;
+
+// Message signed with the current key of moderator_id to authorize the
+// rotation to the new public key
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv"))]
+pub(crate) fn rotation_message(k_reg: &[u8], moderator_id: u32, public_key: &[u8]) -> Vec<u8> {
+    let mut w = FieldWriter::new();
+    w.bytes(k_reg);
+    w.u32(moderator_id);
+    w.bytes(public_key);
+
+    w.finish().expect("fixed-size fields")
+}
+
+// Fails unless epoch is the expected one
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv"))]
+pub(crate) fn check_epoch(epoch: u32, expected: u32) -> Result<(), ReportingError> {
+    if epoch < expected {
+        return Err(ReportingError::ExpiredEpoch(epoch));
+    }
+    if epoch > expected {
+        return Err(ReportingError::UnknownEpoch(epoch));
+    }
+
+    Ok(())
+}
+
+// Secret keys of earlier epochs a moderator still moderates reports for
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv"))]
+pub(crate) struct RetiredKeys<K> {
+    keys: Vec<(u32, K)>
+}
+
+#[cfg(any(feature = "basic", feature = "mod_priv", feature = "constant_mod_priv"))]
+impl<K> RetiredKeys<K> {
+    pub(crate) fn new() -> RetiredKeys<K> {
+        RetiredKeys { keys: Vec::new() }
+    }
+
+    // Key of epoch, current is the key of current_epoch
+    pub(crate) fn get<'a>(&'a self, epoch: u32, current_epoch: u32, current: &'a K) -> Result<&'a K, ReportingError> {
+        if epoch >= current_epoch {
+            check_epoch(epoch, current_epoch)?;
+            return Ok(current);
+        }
+
+        self.keys.iter().find(|(e, _)| *e == epoch).map(|(_, key)| key).ok_or(ReportingError::ExpiredEpoch(epoch))
+    }
+
+    // Keeps key of epoch after the rotation to new_epoch and drops (wipes)
+    // the keys that fall out of the grace period
+    pub(crate) fn retire(&mut self, epoch: u32, key: K, new_epoch: u32, grace_epochs: u32) {
+        self.keys.push((epoch, key));
+        self.keys.retain(|(e, _)| e.saturating_add(grace_epochs) >= new_epoch);
+    }
+}
+
+// Newest revocation list a client has verified
+#[derive(Clone, Debug, Default)]
+pub struct Revocations {
+    pub version: u32,
+    pub revoked: Vec<u32>
+}
+
+impl Revocations {
+    pub fn is_revoked(&self, moderator_id: u32) -> bool {
+        self.revoked.contains(&moderator_id)
+    }
+
+    // Fails with RevokedModerator for a revoked id
+    pub fn check(&self, moderator_id: u32) -> Result<(), ReportingError> {
+        if self.is_revoked(moderator_id) {
+            return Err(ReportingError::RevokedModerator(moderator_id));
+        }
+
+        Ok(())
+    }
+
+    // Takes a list whose signature was verified, a list older than the
+    // current one is a rollback and is refused
+    pub(crate) fn update(&mut self, version: u32, revoked: &[u32]) -> Result<(), ReportingError> {
+        if version < self.version {
+            return Err(ReportingError::StaleRevocationList(version));
+        }
+
+        self.version = version;
+        self.revoked = revoked.to_vec();
+
+        Ok(())
+    }
+}
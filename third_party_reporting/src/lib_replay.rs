@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use sha2::{Sha256, Digest};
+use crate::lib_context::{ReportContext, unix_time};
+use crate::lib_error::ReportingError;
+
+// Replay protection for process
+//
+// The platform stamps every message it processes with the time of its clock,
+// whatever timestamp the caller put in the context, and gives it a message id,
+// unique among the
+// messages it processed, in the report context (lib_context.rs) bound into the
+// platform tag of every scheme, so a report carries a verifiable identity and
+// a replayed process output keeps the id of the original. Submissions are
+// keyed by ctx.conversation: the cache
+// holds H(c2) for the last per_conversation messages of the
+// max_conversations most recently used conversations, and process rejects a
+// c2 already seen in the same conversation with DuplicateMessage. The cache is
+// bounded, so a commitment is accepted again (under a new message id) once it
+// has been forgotten: the oldest commitment of a full conversation, or every
+// commitment of the least recently used conversation when a new one comes in.
+//
+// Every admitted message also gets the next sequence number of its
+// conversation. The counter lives in the cache entry of the conversation and
+// starts at the message id of the message that creates the entry, 0 for the
+// first conversation of the platform. Message ids only grow, so a conversation
+// evicted and seen again starts above every number it was given before: a
+// sequence number is never handed out twice in a conversation, consecutive
+// numbers are consecutive messages, and a window across an eviction has a gap.
+
+// Commitments remembered per conversation by ReplayCache::new
+pub const PER_CONVERSATION: usize = 1024;
+
+// Conversations remembered by ReplayCache::new
+pub const MAX_CONVERSATIONS: usize = 4096;
+
+pub struct ReplayCache {
+    per_conversation: usize,
+    max_conversations: usize,
+    conversations: HashMap<u64, Conversation>,
+    recent: BTreeMap<u64, u64>, // Conversations in the cache by last use, least recently used first
+    uses: u64, // Number of submissions, orders the uses of conversations
+    next_message_id: u64,
+    clock: fn() -> u64 // Current time in seconds since the Unix epoch
+}
+
+// H(c2) of the last messages of a conversation and its next sequence number
+#[derive(Default)]
+struct Conversation {
+    seen: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>, // Oldest first
+    last_use: u64, // Key of the conversation in recent
+    next_sequence: u64
+}
+
+impl ReplayCache {
+    pub fn new() -> ReplayCache {
+        Self::build(PER_CONVERSATION, MAX_CONVERSATIONS, unix_time)
+    }
+
+    // Fails with InvalidCapacity unless both capacities are at least 1
+    pub fn with_capacity(per_conversation: usize, max_conversations: usize) -> Result<ReplayCache, ReportingError> {
+        Self::with_clock(per_conversation, max_conversations, unix_time)
+    }
+
+    pub fn with_clock(per_conversation: usize, max_conversations: usize, clock: fn() -> u64) -> Result<ReplayCache, ReportingError> {
+        if per_conversation == 0 || max_conversations == 0 {
+            return Err(ReportingError::InvalidCapacity);
+        }
+
+        Ok(Self::build(per_conversation, max_conversations, clock))
+    }
+
+    fn build(per_conversation: usize, max_conversations: usize, clock: fn() -> u64) -> ReplayCache {
+        ReplayCache {
+            per_conversation,
+            max_conversations,
+            conversations: HashMap::new(),
+            recent: BTreeMap::new(),
+            uses: 0,
+            next_message_id: 0,
+            clock
+        }
+    }
+
+    // Records c2 in ctx.conversation and returns ctx with the current time, the
+    // next message id and sequence number
+    pub fn admit(&mut self, ctx: &ReportContext, c2: &[u8]) -> Result<ReportContext, ReportingError> {
+        let conversation = ctx.conversation;
+        let digest: [u8; 32] = Sha256::digest(c2).into();
+
+        let last_use = self.uses;
+        self.uses += 1;
+        match self.conversations.get_mut(&conversation) {
+            Some(entry) => {
+                self.recent.remove(&entry.last_use);
+                entry.last_use = last_use;
+            },
+            None => {
+                if self.conversations.len() == self.max_conversations {
+                    let (_, least_recent) = self.recent.pop_first().expect("cache holds max_conversations conversations");
+                    self.conversations.remove(&least_recent);
+                }
+                self.conversations.insert(conversation, Conversation { last_use, next_sequence: self.next_message_id, ..Conversation::default() });
+            }
+        }
+        self.recent.insert(last_use, conversation);
+
+        let entry = self.conversations.get_mut(&conversation).expect("conversation is in the cache");
+
+        if !entry.seen.insert(digest) {
+            return Err(ReportingError::DuplicateMessage(conversation));
+        }
+        entry.order.push_back(digest);
+        if entry.order.len() > self.per_conversation {
+            let oldest = entry.order.pop_front().expect("conversation holds more than per_conversation commitments");
+            entry.seen.remove(&oldest);
+        }
+
+        let sequence = entry.next_sequence;
+        entry.next_sequence += 1;
+
+        let message_id = self.next_message_id;
+        self.next_message_id += 1;
+
+        Ok(ReportContext { timestamp: (self.clock)(), message_id, sequence, ..*ctx })
+    }
+}
+
+impl Default for ReplayCache {
+    fn default() -> ReplayCache {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(conversation: u64) -> ReportContext {
+        ReportContext::new(1, 2, conversation)
+    }
+
+    #[test]
+    fn duplicate_commitment_is_rejected() {
+        let mut cache = ReplayCache::new();
+        let first = cache.admit(&ctx(1), b"c2").unwrap();
+
+        assert_eq!(cache.admit(&ctx(1), b"c2"), Err(ReportingError::DuplicateMessage(1)));
+
+        // but not in another conversation, where it is a new message
+        let other = cache.admit(&ctx(2), b"c2").unwrap();
+        assert_ne!(other.message_id, first.message_id);
+        assert_eq!(other.sequence, other.message_id);
+    }
+
+    #[test]
+    fn message_ids_are_assigned_by_the_platform() {
+        let mut cache = ReplayCache::new();
+        let chosen = ReportContext { message_id: 7, sequence: 7, ..ctx(1) };
+
+        let ids: Vec<(u64, u64)> = [b"a", b"b", b"c"].iter().map(|c2| {
+            let ctx = cache.admit(&chosen, *c2).unwrap();
+            (ctx.message_id, ctx.sequence)
+        }).collect();
+        assert_eq!(ids, vec![(0, 0), (1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn timestamp_is_stamped_by_the_platform() {
+        let mut cache = ReplayCache::with_clock(PER_CONVERSATION, MAX_CONVERSATIONS, || 42).unwrap();
+        let chosen = ReportContext { timestamp: 7, ..ctx(1) };
+
+        assert_eq!(cache.admit(&chosen, b"c2").unwrap().timestamp, 42);
+    }
+
+    #[test]
+    fn least_recently_used_conversation_is_evicted() {
+        let mut cache = ReplayCache::with_capacity(2, 2).unwrap();
+        cache.admit(&ctx(1), b"a").unwrap();
+        let first = cache.admit(&ctx(2), b"b").unwrap();
+        cache.admit(&ctx(1), b"c").unwrap();
+
+        // Conversation 2 was used last before 1 and is forgotten for 3
+        cache.admit(&ctx(3), b"d").unwrap();
+        assert_eq!(cache.admit(&ctx(1), b"a"), Err(ReportingError::DuplicateMessage(1)));
+        let again = cache.admit(&ctx(2), b"b").unwrap();
+
+        // The sequence restarts above the evicted ones, leaving a gap
+        assert_eq!(again.sequence, again.message_id);
+        assert!(again.sequence > first.sequence + 1);
+    }
+
+    #[test]
+    fn conversations_are_bounded() {
+        let mut cache = ReplayCache::with_capacity(1, 2).unwrap();
+        for conversation in 0..10 {
+            cache.admit(&ctx(conversation), b"c2").unwrap();
+        }
+
+        assert_eq!(cache.conversations.len(), 2);
+        assert_eq!(cache.recent.len(), 2);
+    }
+
+    #[test]
+    fn zero_capacity_is_rejected() {
+        assert_eq!(ReplayCache::with_capacity(0, 1).err(), Some(ReportingError::InvalidCapacity));
+        assert_eq!(ReplayCache::with_capacity(1, 0).err(), Some(ReportingError::InvalidCapacity));
+    }
+
+    #[test]
+    fn oldest_commitment_of_a_conversation_is_evicted() {
+        let mut cache = ReplayCache::with_capacity(2, 1).unwrap();
+        for c2 in [b"a", b"b", b"c"] {
+            cache.admit(&ctx(1), c2).unwrap();
+        }
+
+        assert_eq!(cache.admit(&ctx(1), b"c"), Err(ReportingError::DuplicateMessage(1)));
+        assert!(cache.admit(&ctx(1), b"a").is_ok());
+    }
+}
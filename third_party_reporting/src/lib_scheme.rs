@@ -0,0 +1,539 @@
+#[cfg(feature = "basic")]
+use std::mem;
+use rand::distributions::Alphanumeric;
+use rand::distributions::DistString;
+use rand::Rng;
+use rand::{CryptoRng, RngCore};
+use rand::rngs::OsRng;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::lib_common::*;
+use crate::lib_error::ReportingError;
+use crate::lib_context::{ReportContext, ExpiryPolicy};
+use crate::lib_wire::WireMessage;
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+use crate::lib_window::{window_gen, moderate_window};
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+use crate::lib_merkle::segments;
+
+// Common interface implemented by every reporting scheme.
+// Each scheme module exposes a unit struct (lib_plain::Plain, lib_basic::Basic,
+// lib_mod_priv::ModPriv and lib_constant_mod_priv::ConstantModPriv) implementing
+// this trait, so the test flow, main.rs and the benches are written once.
+pub trait ReportingScheme {
+    // Name printed by the test flow
+    const NAME: &'static str;
+
+    type Platform;
+    type Moderator;
+    type Client;
+    type PublicKey: Clone; // Moderator public key material available to clients
+    // Messages exchanged between the parties, serializable so they can be
+    // persisted and sent over the wire (see lib_wire.rs; (Sigma, ProcessState)
+    // is encoded as a single MessageKind::Process message)
+    type Ciphertext: Clone + Serialize + DeserializeOwned + WireMessage; // Output of send: (c1, c2, ad)
+    type Sigma: Clone + Serialize + DeserializeOwned; // Platform tag output by process
+    type ProcessState: Clone + Serialize + DeserializeOwned; // State output by process alongside the tag
+    type ReportDoc: Clone + Serialize + DeserializeOwned + WireMessage; // Output of read, stored by the receiver
+    type Report: Clone + Serialize + DeserializeOwned + WireMessage; // Output of report_gen, sent to the moderator
+
+    // Randomized operations take a caller supplied rng; the versions without
+    // _with_rng draw from OsRng
+
+    // SetupPlatform(1^lambda)
+    fn setup_platform_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self::Platform;
+
+    fn setup_platform() -> Self::Platform {
+        Self::setup_platform_with_rng(&mut OsRng)
+    }
+
+    // SetupMod(pk_reg, 1^lambda) for num_moderators moderators registered with the platform
+    fn setup_mod_with_rng<R: CryptoRng + RngCore>(platform: &mut Self::Platform, num_moderators: usize, rng: &mut R) -> (Vec<Self::Moderator>, Vec<Self::PublicKey>);
+
+    fn setup_mod(platform: &mut Self::Platform, num_moderators: usize) -> (Vec<Self::Moderator>, Vec<Self::PublicKey>) {
+        Self::setup_mod_with_rng(platform, num_moderators, &mut OsRng)
+    }
+
+    fn init_client_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self::Client;
+
+    fn init_client() -> Self::Client {
+        Self::init_client_with_rng(&mut OsRng)
+    }
+
+    // send(k, m, pk_i)
+    fn send_with_rng<R: CryptoRng + RngCore>(client: &Self::Client, message: &str, moderator_id: u32, pks: &[Self::PublicKey], rng: &mut R) -> Result<Self::Ciphertext, ReportingError>;
+
+    fn send(client: &Self::Client, message: &str, moderator_id: u32, pks: &[Self::PublicKey]) -> Result<Self::Ciphertext, ReportingError> {
+        Self::send_with_rng(client, message, moderator_id, pks, &mut OsRng)
+    }
+
+    // process(k_p, ks, c1, c2, ad, ctx) for a message of ctx.conversation, fails
+    // with DuplicateMessage if c2 was already processed in it (lib_replay.rs)
+    fn process_with_rng<R: CryptoRng + RngCore>(platform: &mut Self::Platform, ct: &Self::Ciphertext, ctx: &ReportContext, rng: &mut R) -> Result<(Self::Sigma, Self::ProcessState), ReportingError>;
+
+    fn process(platform: &mut Self::Platform, ct: &Self::Ciphertext, ctx: &ReportContext) -> Result<(Self::Sigma, Self::ProcessState), ReportingError> {
+        Self::process_with_rng(platform, ct, ctx, &mut OsRng)
+    }
+
+    // read(k, pks, c1, c2, sigma, st)
+    fn read(client: &Self::Client, pks: &[Self::PublicKey], ct: &Self::Ciphertext, sigma: &Self::Sigma, st: &Self::ProcessState) -> Result<(String, u32, Self::ReportDoc), ReportingError>;
+
+    // report(m, rd)
+    fn report_gen(message: &str, rd: &Self::ReportDoc) -> Result<Self::Report, ReportingError>;
+
+    // report(m, rd) revealing only the segments of m with indices reveal,
+    // increasing (lib_merkle.rs). The moderator is given their concatenation
+    // as the message
+    fn report_gen_partial(message: &str, rd: &Self::ReportDoc, reveal: &[u32]) -> Result<Self::Report, ReportingError>;
+
+    // moderate(sk_mod, sk_p, m, report)
+    // Reports that fail verification, including reports under an epoch the
+    // moderator has no key for or with an invalid point, and reports that
+    // verify but are older than policy allows, yield Verdict::Reject. Errors
+    // are left to failures on the moderator's side
+    fn moderate(moderator: &Self::Moderator, message: &str, report: &Self::Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError>;
+
+    // Communication costs (bytes) printed by the test flow
+    fn send_cost(ct: &Self::Ciphertext) -> usize;
+    fn receive_cost(sigma: &Self::Sigma, st: &Self::ProcessState, rd: &Self::ReportDoc) -> usize;
+    fn storage_cost(rd: &Self::ReportDoc) -> usize;
+    fn moderation_cost(report: &Self::Report) -> usize;
+}
+
+
+
+// Setup platforms with moderators as in MOD_SCALE
+#[allow(clippy::type_complexity)]
+pub fn test_setup<S: ReportingScheme, R: CryptoRng + RngCore>(rng: &mut R) -> (Vec<S::Platform>, Vec<Vec<S::Moderator>>, Vec<Vec<S::PublicKey>>) {
+    test_setup_with_scale::<S, R>(&MOD_SCALE, rng)
+}
+
+// Setup one platform for each entry of mod_scale with that many moderators
+#[allow(clippy::type_complexity)]
+pub fn test_setup_with_scale<S: ReportingScheme, R: CryptoRng + RngCore>(mod_scale: &[usize], rng: &mut R) -> (Vec<S::Platform>, Vec<Vec<S::Moderator>>, Vec<Vec<S::PublicKey>>) {
+    let n: usize = mod_scale.len();
+    let mut platforms: Vec<S::Platform> = Vec::with_capacity(n);
+
+    for _i in 0..n {
+        platforms.push(S::setup_platform_with_rng(rng));
+    }
+
+    let mut moderators: Vec<Vec<S::Moderator>> = Vec::with_capacity(n);
+    let mut pubs: Vec<Vec<S::PublicKey>> = Vec::with_capacity(n);
+
+    for (i, num_moderators) in mod_scale.iter().enumerate() {
+        let (mods, pks) = S::setup_mod_with_rng(&mut platforms[i], *num_moderators, rng);
+        moderators.push(mods);
+        pubs.push(pks);
+    }
+
+    (platforms, moderators, pubs)
+}
+
+// Setup Clients
+pub fn test_init_clients<S: ReportingScheme, R: CryptoRng + RngCore>(num_clients: usize, rng: &mut R) -> Vec<S::Client> {
+    let mut clients: Vec<S::Client> = Vec::with_capacity(num_clients);
+    for _i in 0..num_clients {
+        clients.push(S::init_client_with_rng(rng));
+    }
+
+    clients
+}
+
+// Setup Messages
+pub fn test_init_messages<R: CryptoRng + RngCore>(num_clients: usize, msg_size: usize, rng: &mut R) -> Vec<String> {
+    // Prepare messages
+    let mut ms: Vec<String> = Vec::with_capacity(num_clients);
+    for _i in 0..num_clients {
+        let m = Alphanumeric.sample_string(rng, msg_size);
+        ms.push(m);
+    }
+    ms
+}
+
+// Setup one message of each size in MSG_SIZE_SCALE
+pub fn test_init_messages_variable<R: CryptoRng + RngCore>(rng: &mut R) -> Vec<Vec<String>> {
+    let mut ms: Vec<Vec<String>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+    for msg_size in MSG_SIZE_SCALE.iter() {
+        ms.push(test_init_messages(1, *msg_size, rng));
+    }
+
+    ms
+}
+
+// send(k, m, pk_i)
+pub fn test_send<S: ReportingScheme, R: CryptoRng + RngCore>(num_clients: usize, pks: &[S::PublicKey], clients: &[S::Client], ms: &[String], print: bool, rng: &mut R) -> Vec<S::Ciphertext> {
+    let mut c1c2ad: Vec<S::Ciphertext> = Vec::with_capacity(num_clients);
+
+    // send message i to client i to be moderated by random mod
+    for i in 0..num_clients {
+        let mod_i: u32 = rng.gen_range(0..pks.len()).try_into().unwrap();
+        let ct = S::send_with_rng(&clients[i], &ms[i], mod_i, pks, rng).unwrap();
+
+        if print {
+            println!("Sending communication cost: {} (bytes)", S::send_cost(&ct));
+        }
+        c1c2ad.push(ct);
+    }
+
+    c1c2ad
+}
+
+// Send messages of sizes in MSG_SIZE_SCALE
+// to platforms with num moderators in MOD_SCALE
+pub fn test_send_variable<S: ReportingScheme, R: CryptoRng + RngCore>(pubs: &[Vec<S::PublicKey>], clients: &[S::Client], ms: &[Vec<String>], rng: &mut R) -> Vec<Vec<Vec<S::Ciphertext>>> {
+    // Send messages
+    let mut c1c2ad: Vec<Vec<Vec<S::Ciphertext>>> = Vec::with_capacity(pubs.len());
+    // c1c2ad[i][j] = Encryption of message j to moderator i
+    for pks in pubs.iter() {
+        let mut tmp: Vec<Vec<S::Ciphertext>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+        for (j, _msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
+            tmp.push(test_send::<S, R>(1, pks, clients, &ms[j], false, rng));
+        }
+        c1c2ad.push(tmp);
+    }
+
+    c1c2ad
+}
+
+// process(k_p, ks, c1, c2, ad, ctx)
+pub fn test_process<S: ReportingScheme, R: CryptoRng + RngCore>(num_clients: usize, c1c2ad: &[S::Ciphertext], platform: &mut S::Platform, rng: &mut R) -> Vec<(S::Sigma, S::ProcessState)> {
+    let mut sigma_st: Vec<(S::Sigma, S::ProcessState)> = Vec::with_capacity(num_clients);
+    // Platform processes message, client i sends to itself in conversation i
+    for (i, ct) in c1c2ad.iter().enumerate().take(num_clients) {
+        let ctx = ReportContext::new(i as u64, i as u64, i as u64);
+        sigma_st.push(S::process_with_rng(platform, ct, &ctx, rng).unwrap());
+    }
+
+    sigma_st
+}
+
+// Process messages of sizes in MSG_SIZE_SCALE
+// and encrypt them to moderators in MOD_SCALE
+#[allow(clippy::type_complexity)]
+pub fn test_process_variable<S: ReportingScheme, R: CryptoRng + RngCore>(c1c2ad: &[Vec<Vec<S::Ciphertext>>], platforms: &mut [S::Platform], rng: &mut R) -> Vec<Vec<Vec<(S::Sigma, S::ProcessState)>>> {
+    // Process messages
+    let mut sigma_st: Vec<Vec<Vec<(S::Sigma, S::ProcessState)>>> = Vec::with_capacity(platforms.len());
+    // sigma_st[i][j] = signature on message commitmment j for platform i
+    for i in 0..platforms.len() {
+        let mut tmp: Vec<Vec<(S::Sigma, S::ProcessState)>> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+        for (j, _msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
+            tmp.push(test_process::<S, R>(1, &c1c2ad[i][j], &mut platforms[i], rng));
+        }
+        sigma_st.push(tmp);
+    }
+
+    sigma_st
+}
+
+// read(k, pks, c1, c2, sigma, st)
+pub fn test_read<S: ReportingScheme>(num_clients: usize, c1c2ad: &[S::Ciphertext], sigma_st: &[(S::Sigma, S::ProcessState)], clients: &[S::Client], pks: &[S::PublicKey], print: bool) -> Vec<(String, u32, S::ReportDoc)> {
+    // Receive messages
+    let mut rds: Vec<(String, u32, S::ReportDoc)> = Vec::with_capacity(num_clients);
+    // Receive message i from client i to be moderated by randomly selected moderator mod_i
+    for i in 0..num_clients {
+        let (sigma, st) = &sigma_st[i];
+        let (message, moderator_id, rd) = S::read(&clients[i], pks, &c1c2ad[i], sigma, st).unwrap();
+
+        if print {
+            println!("Receiving communication cost: {} (bytes)", S::receive_cost(sigma, st, &rd));
+            println!("Storage communication cost: {} (bytes)", S::storage_cost(&rd));
+        }
+        rds.push((message, moderator_id, rd));
+    }
+
+    rds
+}
+
+// Read messages of sizes in MSG_SIZE_SCALE
+// sent to moderators in MOD_SCALE
+#[allow(clippy::type_complexity)]
+pub fn test_read_variable<S: ReportingScheme>(c1c2ad: &[Vec<Vec<S::Ciphertext>>], sigma_st: &[Vec<Vec<(S::Sigma, S::ProcessState)>>], clients: &[S::Client], pubs: &[Vec<S::PublicKey>]) -> Vec<Vec<(String, u32, S::ReportDoc)>> {
+    let mut rds: Vec<Vec<(String, u32, S::ReportDoc)>> = Vec::with_capacity(pubs.len());
+    // rds[i][j] = report doc for message j to moderator for platform i
+    for i in 0..pubs.len() {
+        let mut tmp: Vec<(String, u32, S::ReportDoc)> = Vec::with_capacity(MSG_SIZE_SCALE.len());
+        for (j, _msg_size) in MSG_SIZE_SCALE.iter().enumerate() {
+            let rd = test_read::<S>(1, &c1c2ad[i][j], &sigma_st[i][j], clients, &pubs[i], false);
+            tmp.push(rd[0].clone());
+        }
+        rds.push(tmp);
+    }
+
+    rds
+}
+
+// report(m, rd)
+pub fn test_report<S: ReportingScheme>(num_clients: usize, rds: &[(String, u32, S::ReportDoc)]) -> Vec<(String, u32, S::Report)> {
+    let mut reports: Vec<(String, u32, S::Report)> = Vec::with_capacity(num_clients);
+
+    for (message, moderator_id, rd) in rds.iter().take(num_clients) {
+        let report = S::report_gen(message, rd).unwrap();
+
+        reports.push((message.clone(), *moderator_id, report));
+    }
+
+    reports
+}
+
+// moderate(sk_mod, sk_p, m, report)
+pub fn test_moderate<S: ReportingScheme>(num_clients: usize, reports: &[(String, u32, S::Report)], moderators: &[S::Moderator], print: bool) -> Vec<Verdict> {
+    let mut verdicts: Vec<Verdict> = Vec::with_capacity(num_clients);
+
+    // Moderate messages
+    for (message, moderator_id, report) in reports.iter().take(num_clients) {
+        let j = usize::try_from(*moderator_id).unwrap();
+        verdicts.push(S::moderate(&moderators[j], message, report, &ExpiryPolicy::default()).unwrap());
+
+        if print {
+            println!("Moderation communication cost: {} (bytes)", S::moderation_cost(report));
+        }
+    }
+
+    verdicts
+}
+
+
+
+// Run the whole flow of a scheme with variable number of clients / msgs sent, msg_size, and
+// number of moderators, printing the communication cost of each step.
+// All randomness is drawn from rng, so a seeded rng gives a reproducible run
+pub fn test_flow<S: ReportingScheme, R: CryptoRng + RngCore>(num_clients: usize, msg_size: usize, num_moderators: usize, rng: &mut R) {
+    println!("======================== Started Testing {} with {} moderators ====================", S::NAME, num_moderators);
+    println!();
+
+    // Initialize Platform
+    let mut platform = S::setup_platform_with_rng(rng);
+
+    // Initialize Moderators
+    let (moderators, pks) = S::setup_mod_with_rng(&mut platform, num_moderators, rng);
+
+    // Initialize Clients
+    let clients = test_init_clients::<S, _>(num_clients, rng);
+
+    // Prepare messages
+    let ms = test_init_messages(num_clients, msg_size, rng);
+
+    // Send messages
+    let c1c2ad = test_send::<S, _>(num_clients, &pks, &clients, &ms, true, rng);
+
+    // Process messages
+    let sigma_st = test_process::<S, _>(num_clients, &c1c2ad, &mut platform, rng);
+
+    // Read messages and generate report docs
+    let rds = test_read::<S>(num_clients, &c1c2ad, &sigma_st, &clients, &pks, true);
+
+    // Generate reports
+    let reports = test_report::<S>(num_clients, &rds);
+
+    // Moderate reports
+    let verdicts = test_moderate::<S>(num_clients, &reports, &moderators, true);
+    for verdict in verdicts.iter() {
+        assert!(verdict.is_accept(), "Report rejected: {:?}", verdict);
+    }
+
+    println!();
+    println!("======================== Finished Testing {} with {} moderators ====================", S::NAME, num_moderators);
+    println!();
+    println!();
+}
+
+
+
+// Run a scheme flow where a client sends window messages in one conversation,
+// all to the same moderator, and reports them as one window. The window is
+// checked to be accepted, and to be rejected once reordered or with a message
+// left out. An empty window fails with EmptyWindow
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub fn test_window_flow<S: ReportingScheme, R: CryptoRng + RngCore>(window: usize, msg_size: usize, num_moderators: usize, rng: &mut R) -> Result<(), ReportingError> {
+    println!("======================== Started Testing {} with windows of {} messages ====================", S::NAME, window);
+    println!();
+
+    let mut platform = S::setup_platform_with_rng(rng);
+    let (moderators, pks) = S::setup_mod_with_rng(&mut platform, num_moderators, rng);
+    let client = S::init_client_with_rng(rng);
+    let ms = test_init_messages(window, msg_size, rng);
+    let mod_i: u32 = rng.gen_range(0..pks.len()).try_into().unwrap();
+
+    // Send, process and read the messages of conversation 0 in order
+    let mut rds: Vec<(String, S::ReportDoc)> = Vec::with_capacity(window);
+    for m in ms.iter() {
+        let ct = S::send_with_rng(&client, m, mod_i, &pks, rng).unwrap();
+        let (sigma, st) = S::process_with_rng(&mut platform, &ct, &ReportContext::new(0, 0, 0), rng).unwrap();
+        let (message, _moderator_id, rd) = S::read(&client, &pks, &ct, &sigma, &st).unwrap();
+        rds.push((message, rd));
+    }
+
+    // Moderate the window
+    let report = window_gen::<S>(&rds)?;
+    let moderator = &moderators[usize::try_from(mod_i).unwrap()];
+    let verdict = moderate_window::<S>(moderator, &report, &ExpiryPolicy::default()).unwrap();
+    assert!(verdict.window.is_accept(), "Window rejected: {:?}", verdict.window);
+    println!("Window moderation communication cost: {} (bytes)", report.reports.iter().map(S::moderation_cost).sum::<usize>());
+
+    if window >= 2 {
+        let mut reordered = report.clone();
+        reordered.messages.swap(0, window - 1);
+        reordered.reports.swap(0, window - 1);
+        let verdict = moderate_window::<S>(moderator, &reordered, &ExpiryPolicy::default()).unwrap();
+        assert!(matches!(verdict.window, Verdict::Reject(ReportingError::NotAdjacent(_))), "Reordered window accepted: {:?}", verdict.window);
+    }
+
+    if window >= 3 {
+        let mut gap = report.clone();
+        gap.messages.remove(1);
+        gap.reports.remove(1);
+        let verdict = moderate_window::<S>(moderator, &gap, &ExpiryPolicy::default()).unwrap();
+        assert_eq!(verdict.window, Verdict::Reject(ReportingError::NotAdjacent(2)), "Window with a gap accepted");
+    }
+
+    println!();
+    println!("======================== Finished Testing {} with windows of {} messages ====================", S::NAME, window);
+    println!();
+    println!();
+
+    Ok(())
+}
+
+
+
+// Run a scheme flow where a client sends a message of words words of
+// msg_size characters and reports only every other segment (lib_merkle.rs).
+// The partial report is checked to be accepted on the revealed text and
+// rejected on the whole message
+#[cfg(any(feature = "plain", feature = "basic", feature = "mod_priv", feature = "constant_mod_priv", feature = "rsa"))]
+pub fn test_redact_flow<S: ReportingScheme, R: CryptoRng + RngCore>(words: usize, msg_size: usize, num_moderators: usize, rng: &mut R) {
+    println!("======================== Started Testing {} with partial reports of {} segments ====================", S::NAME, words);
+    println!();
+
+    let mut platform = S::setup_platform_with_rng(rng);
+    let (moderators, pks) = S::setup_mod_with_rng(&mut platform, num_moderators, rng);
+    let clients = test_init_clients::<S, _>(1, rng);
+    let ms = vec![test_init_messages(words, msg_size, rng).join(" ")];
+
+    let c1c2ad = test_send::<S, _>(1, &pks, &clients, &ms, false, rng);
+    let sigma_st = test_process::<S, _>(1, &c1c2ad, &mut platform, rng);
+    let rds = test_read::<S>(1, &c1c2ad, &sigma_st, &clients, &pks, false);
+    let (message, moderator_id, rd) = &rds[0];
+
+    // Reveal the even segments
+    let segments = segments(message);
+    let reveal: Vec<u32> = (0..segments.len() as u32).step_by(2).collect();
+    let revealed: String = reveal.iter().map(|i| segments[*i as usize]).collect();
+    let report = S::report_gen_partial(message, rd, &reveal).unwrap();
+
+    let moderator = &moderators[usize::try_from(*moderator_id).unwrap()];
+    let verdict = S::moderate(moderator, &revealed, &report, &ExpiryPolicy::default()).unwrap();
+    assert!(verdict.is_accept(), "Partial report rejected: {:?}", verdict);
+    println!("Partial moderation communication cost: {} (bytes)", S::moderation_cost(&report));
+
+    if reveal.len() < segments.len() {
+        let verdict = S::moderate(moderator, message, &report, &ExpiryPolicy::default()).unwrap();
+        assert_eq!(verdict, Verdict::Reject(ReportingError::BadCommitment), "Partial report accepted on the whole message");
+    }
+
+    println!();
+    println!("======================== Finished Testing {} with partial reports of {} segments ====================", S::NAME, words);
+    println!();
+    println!();
+}
+
+
+
+// Run the basic scheme flow with reports moderated by a committee of
+// num_moderators members with threshold t. Each report is moderated with the
+// shares of t random members, and checked to be rejected with t - 1 of them.
+// The committee keys are dealt, or generated with a DKG when dkg is set
+#[cfg(feature = "basic")]
+pub fn test_threshold_flow<R: CryptoRng + RngCore>(num_clients: usize, msg_size: usize, t: u32, num_moderators: u32, dkg: bool, rng: &mut R) -> Result<(), ReportingError> {
+    use rand::seq::SliceRandom;
+    use crate::lib_basic::{Basic, CommitteeMember};
+
+    println!("======================== Started Testing {} with a {}-of-{} committee ====================", Basic::NAME, t, num_moderators);
+    println!();
+
+    // Initialize Platform and the committee, registered as moderator 0
+    let mut platform = Basic::setup_platform_with_rng(rng);
+    let committee = if dkg {
+        CommitteeMember::new_committee_dkg_with_rng(t, num_moderators, &mut platform, rng)?
+    } else {
+        CommitteeMember::new_committee_with_rng(t, num_moderators, &mut platform, rng)?
+    };
+    let pks = vec![committee[0].public_key()];
+
+    let clients = test_init_clients::<Basic, _>(num_clients, rng);
+    let ms = test_init_messages(num_clients, msg_size, rng);
+    let c1c2ad = test_send::<Basic, _>(num_clients, &pks, &clients, &ms, true, rng);
+    let sigma_st = test_process::<Basic, _>(num_clients, &c1c2ad, &mut platform, rng);
+    let rds = test_read::<Basic>(num_clients, &c1c2ad, &sigma_st, &clients, &pks, true);
+    let reports = test_report::<Basic>(num_clients, &rds);
+
+    // Moderate reports
+    for (message, _moderator_id, report) in reports.iter() {
+        let quorum: Vec<&CommitteeMember> = committee.choose_multiple(rng, t as usize).collect();
+        let shares: Vec<_> = quorum.iter().map(|member| member.partial_decrypt(report, rng).unwrap()).collect();
+        for share in shares.iter() {
+            // (1) Member index (2) d_i (3) proof
+            println!("Partial decryption communication cost: {} (bytes)", mem::size_of_val(&share.index) + mem::size_of_val(&share.d) + mem::size_of_val(&share.proof));
+        }
+
+        let verdict = quorum[0].moderate(message, report, &shares, &ExpiryPolicy::default()).unwrap();
+        assert!(verdict.is_accept(), "Report rejected: {:?}", verdict);
+
+        let verdict = quorum[0].moderate(message, report, &shares[1..], &ExpiryPolicy::default()).unwrap();
+        assert!(matches!(verdict, Verdict::Reject(ReportingError::InsufficientShares(_))), "Report accepted below the threshold: {:?}", verdict);
+
+        println!("Moderation communication cost: {} (bytes)", Basic::moderation_cost(report));
+    }
+
+    println!();
+    println!("======================== Finished Testing {} with a {}-of-{} committee ====================", Basic::NAME, t, num_moderators);
+    println!();
+    println!();
+
+    Ok(())
+}
+
+// A platform with one moderator and a report on a message it processed, for
+// the tests of the schemes
+#[cfg(test)]
+pub(crate) struct Fixture<S: ReportingScheme> {
+    pub platform: S::Platform,
+    pub moderators: Vec<S::Moderator>,
+    pub pks: Vec<S::PublicKey>,
+    pub client: S::Client,
+    pub ct: S::Ciphertext,
+    pub sigma: S::Sigma,
+    pub st: S::ProcessState,
+    pub message: String,
+    pub rd: S::ReportDoc,
+    pub report: S::Report
+}
+
+#[cfg(test)]
+impl<S: ReportingScheme> Fixture<S> {
+    pub fn new<R: CryptoRng + RngCore>(rng: &mut R) -> Fixture<S> {
+        let mut platform = S::setup_platform_with_rng(rng);
+        let (moderators, pks) = S::setup_mod_with_rng(&mut platform, 1, rng);
+        let client = S::init_client_with_rng(rng);
+
+        let ct = S::send_with_rng(&client, "the quick brown fox", 0, &pks, rng).unwrap();
+        let (sigma, st) = S::process_with_rng(&mut platform, &ct, &ReportContext::new(1, 2, 3), rng).unwrap();
+        let (message, _moderator_id, rd) = S::read(&client, &pks, &ct, &sigma, &st).unwrap();
+        let report = S::report_gen(&message, &rd).unwrap();
+        assert!(S::moderate(&moderators[0], &message, &report, &ExpiryPolicy::default()).unwrap().is_accept());
+
+        Fixture { platform, moderators, pks, client, ct, sigma, st, message, rd, report }
+    }
+
+    // read of the fixture's message with the given tag and process state
+    pub fn read(&self, sigma: &S::Sigma, st: &S::ProcessState) -> Result<(String, u32, S::ReportDoc), ReportingError> {
+        S::read(&self.client, &self.pks, &self.ct, sigma, st)
+    }
+
+    // Verdict of the first moderator on report about the fixture's message
+    pub fn moderate(&self, report: &S::Report) -> Verdict {
+        S::moderate(&self.moderators[0], &self.message, report, &ExpiryPolicy::default()).unwrap()
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+#[cfg(feature = "constant_mod_priv")]
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use aes_gcm::{Aes256Gcm, Key};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+#[cfg(feature = "constant_mod_priv")]
+use ff::Field;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+// Secret key material
+//
+// The wrapped value is wiped when the wrapper is dropped and Debug never
+// prints it. Dereferences to the wrapped value, so &Secret<T> can be passed
+// where &T is expected.
+pub struct Secret<T: Wipe>(T);
+
+impl<T: Wipe> Secret<T> {
+    pub fn new(value: T) -> Secret<T> {
+        Secret(value)
+    }
+}
+
+impl<T: Wipe> From<T> for Secret<T> {
+    fn from(value: T) -> Secret<T> {
+        Secret(value)
+    }
+}
+
+impl<T: Wipe> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Wipe> DerefMut for Secret<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Wipe + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Secret<T> {
+        Secret(self.0.clone())
+    }
+}
+
+impl<T: Wipe> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: Wipe> Zeroize for Secret<T> {
+    fn zeroize(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: Wipe> ZeroizeOnDrop for Secret<T> {}
+
+// Franking keys and re-encryption keys are part of report docs and reports,
+// so they serialize as the wrapped value
+impl<T: Wipe + Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Wipe + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Secret<T>, D::Error> {
+        T::deserialize(deserializer).map(Secret)
+    }
+}
+
+
+// Values that can be overwritten in place
+pub trait Wipe {
+    fn wipe(&mut self);
+}
+
+impl<const N: usize> Wipe for [u8; N] {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Wipe for Vec<u8> {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Wipe for Key<Aes256Gcm> {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Wipe for Scalar {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Wipe for RistrettoPoint {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+// blstrs does not implement Zeroize, overwrite with a volatile write the
+// same way zeroize does so the store is not optimized away
+#[cfg(feature = "constant_mod_priv")]
+impl Wipe for blstrs::Scalar {
+    fn wipe(&mut self) {
+        unsafe { std::ptr::write_volatile(self, blstrs::Scalar::ZERO) };
+        compiler_fence(Ordering::SeqCst);
+    }
+}
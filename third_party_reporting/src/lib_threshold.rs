@@ -0,0 +1,208 @@
+use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
+use curve25519_dalek::scalar::Scalar;
+use rand::{CryptoRng, RngCore};
+use serde::{Serialize, Deserialize};
+use crate::lib_domain::Domain;
+use crate::lib_error::ReportingError;
+use crate::lib_gamal as gamal;
+use crate::lib_secret::Secret;
+use crate::lib_nizk::{dleq_prove, dleq_verify, DleqProof};
+
+type Point = RistrettoPoint;
+
+// Threshold El Gamal over Ristretto
+//
+// The committee decryption key x is shared with a degree t - 1 polynomial f,
+// f(0) = x, member i (1 <= i <= n) holds x_i = f(i) and publishes
+// pk_i = x_i * G. Given an El Gamal ciphertext (u, v) = (r * G, r * pk + m),
+// member i outputs d_i = x_i * u together with a Chaum-Pedersen proof that
+// log_G(pk_i) = log_u(d_i). Any t shares with valid proofs give
+// x * u = sum(lambda_i * d_i) by Lagrange interpolation at 0, so m = v - x * u.
+
+// Public key material of a committee
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitteeKey {
+    pub threshold: u32, // t, number of shares needed to decrypt
+    pub pk: CompressedRistretto, // Committee El Gamal public key x * G
+    pub share_pks: Vec<CompressedRistretto> // pk_i = x_i * G, share_pks[i - 1] for member i
+}
+
+// Share of the committee decryption key held by member index
+pub struct KeyShare {
+    pub index: u32, // i, the evaluation point of the share
+    x_i: Secret<Scalar>
+}
+
+// Partial decryption d_i = x_i * u sent by member index
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartialDecryption {
+    pub index: u32,
+    pub d: CompressedRistretto,
+    pub proof: DleqProof
+}
+
+// Trusted dealer: shares a fresh decryption key among n members with
+// threshold t (lib_dkg.rs generates the shares without a dealer), fails with
+// InvalidThreshold unless 1 <= t <= n
+pub fn threshold_keygen_with_rng<R: CryptoRng + RngCore>(t: u32, n: u32, rng: &mut R) -> Result<(CommitteeKey, Vec<KeyShare>), ReportingError> {
+    if t == 0 || t > n {
+        return Err(ReportingError::InvalidThreshold(t));
+    }
+
+    // f(X) = coefficients[0] + coefficients[1] * X + ... , the dealer forgets them
+    let coefficients: Vec<Secret<Scalar>> = (0..t).map(|_i| Secret::new(Scalar::random(rng))).collect();
+
+    let mut shares: Vec<KeyShare> = Vec::with_capacity(n as usize);
+    let mut share_pks: Vec<CompressedRistretto> = Vec::with_capacity(n as usize);
+    for index in 1..=n {
+        let x_i = Secret::new(poly_eval(&coefficients, &Scalar::from(index)));
+        share_pks.push(RistrettoPoint::mul_base(&x_i).compress());
+        shares.push(KeyShare { index, x_i });
+    }
+
+    let pk = RistrettoPoint::mul_base(&coefficients[0]).compress();
+
+    Ok((CommitteeKey { threshold: t, pk, share_pks }, shares))
+}
+
+// Horner evaluation of the polynomial with the given coefficients at x
+pub(crate) fn poly_eval(coefficients: &[Secret<Scalar>], x: &Scalar) -> Scalar {
+    let mut y = Scalar::ZERO;
+    for a in coefficients.iter().rev() {
+        y = y * x + **a;
+    }
+
+    y
+}
+
+impl KeyShare {
+    pub(crate) fn new(index: u32, x_i: Secret<Scalar>) -> KeyShare {
+        KeyShare { index, x_i }
+    }
+
+    // d_i = x_i * u with a proof that log_G(pk_i) = log_u(d_i)
+    pub fn partial_decrypt_with_rng<R: CryptoRng + RngCore>(&self, domain: &Domain, u: &Point, rng: &mut R) -> PartialDecryption {
+        let pk_i = RistrettoPoint::mul_base(&self.x_i);
+        let d = *self.x_i * u;
+        let proof = dleq_prove(domain, &self.x_i, u, &pk_i, &d, rng);
+
+        PartialDecryption { index: self.index, d: d.compress(), proof }
+    }
+}
+
+// Combines the shares into m = v - x * u
+//
+// Shares with an unknown or repeated index, an invalid point or a proof that
+// does not verify are ignored, fails with InsufficientShares when fewer than
+// t shares are left.
+pub fn threshold_decrypt(domain: &Domain, key: &CommitteeKey, ct: &(Point, Point), shares: &[PartialDecryption]) -> Result<Point, ReportingError> {
+    let (u, v) = ct;
+    let t = key.threshold as usize;
+
+    let mut valid: Vec<(Scalar, Point)> = Vec::with_capacity(t);
+    let mut indices: Vec<u32> = Vec::with_capacity(t);
+    for share in shares {
+        if valid.len() == t {
+            break;
+        }
+        if share.index == 0 || indices.contains(&share.index) {
+            continue;
+        }
+        let Some(pk_i) = key.share_pks.get(share.index as usize - 1) else { continue };
+        let (Ok(pk_i), Ok(d)) = (gamal::decompress(pk_i), gamal::decompress(&share.d)) else { continue };
+        if !dleq_verify(domain, u, &pk_i, &d, &share.proof) {
+            continue;
+        }
+
+        indices.push(share.index);
+        valid.push((Scalar::from(share.index), d));
+    }
+
+    if valid.len() < t {
+        return Err(ReportingError::InsufficientShares(valid.len() as u32));
+    }
+
+    // x * u = sum(lambda_i * d_i), lambda_i = prod_{j != i} j / (j - i)
+    let mut x_u = RistrettoPoint::default();
+    for (i, (x_i, d_i)) in valid.iter().enumerate() {
+        let mut lambda = Scalar::ONE;
+        for (j, (x_j, _d_j)) in valid.iter().enumerate() {
+            if i != j {
+                lambda *= x_j * (x_j - x_i).invert();
+            }
+        }
+        x_u += lambda * d_i;
+    }
+
+    Ok(v - x_u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::lib_dkg::run_dkg_with_rng;
+    use crate::lib_domain::{Role, Usage};
+    use crate::lib_wire::SCHEME_BASIC;
+
+    const DOMAIN: Domain = Domain::new(SCHEME_BASIC, Role::Moderator, Usage::Proof);
+    const T: u32 = 3;
+    const N: u32 = 5;
+
+    // Encryption of a random m to the committee with the shares of every member
+    fn encrypt(key: &CommitteeKey, shares: &[KeyShare], rng: &mut StdRng) -> (Point, (Point, Point), Vec<PartialDecryption>) {
+        let m = RistrettoPoint::random(rng);
+        let ct = gamal::elgamal_enc_with_rng(&gamal::decompress(&key.pk).unwrap(), &m, rng);
+        let partials = shares.iter().map(|share| share.partial_decrypt_with_rng(&DOMAIN, &ct.0, rng)).collect();
+
+        (m, ct, partials)
+    }
+
+    #[test]
+    fn threshold_of_shares_decrypts() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let dealt = threshold_keygen_with_rng(T, N, &mut rng).unwrap();
+        let generated = run_dkg_with_rng(T, N, &mut rng).unwrap();
+        let generated = (generated[0].key.clone(), generated.into_iter().map(|output| output.share).collect());
+
+        for (key, shares) in [dealt, generated] {
+            let (m, ct, partials) = encrypt(&key, &shares, &mut rng);
+
+            // Any t shares, in any order
+            assert_eq!(threshold_decrypt(&DOMAIN, &key, &ct, &partials[..3]), Ok(m));
+            assert_eq!(threshold_decrypt(&DOMAIN, &key, &ct, &[partials[4].clone(), partials[0].clone(), partials[2].clone()]), Ok(m));
+
+            assert_eq!(threshold_decrypt(&DOMAIN, &key, &ct, &partials[..2]), Err(ReportingError::InsufficientShares(2)));
+        }
+    }
+
+    #[test]
+    fn invalid_shares_are_not_counted() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (key, shares) = threshold_keygen_with_rng(T, N, &mut rng).unwrap();
+        let (m, ct, partials) = encrypt(&key, &shares, &mut rng);
+
+        let bad_proof = PartialDecryption { proof: partials[0].proof, ..partials[2].clone() };
+        let bad_point = PartialDecryption { d: RistrettoPoint::random(&mut rng).compress(), ..partials[2].clone() };
+        let duplicate = partials[0].clone();
+        let invalid: Vec<PartialDecryption> = [0, N + 1, u32::MAX].iter().map(|index| PartialDecryption { index: *index, ..partials[2].clone() })
+            .chain([bad_proof, bad_point, duplicate])
+            .collect();
+
+        // t - 1 valid shares stay short of the threshold whatever is added
+        let short = [&partials[..2], &invalid[..]].concat();
+        assert_eq!(threshold_decrypt(&DOMAIN, &key, &ct, &short), Err(ReportingError::InsufficientShares(2)));
+
+        // and one more valid share decrypts
+        let enough = [&partials[..2], &invalid[..], &partials[3..4]].concat();
+        assert_eq!(threshold_decrypt(&DOMAIN, &key, &ct, &enough), Ok(m));
+    }
+
+    #[test]
+    fn threshold_must_be_between_one_and_n() {
+        let mut rng = StdRng::seed_from_u64(3);
+        assert_eq!(threshold_keygen_with_rng(0, N, &mut rng).err(), Some(ReportingError::InvalidThreshold(0)));
+        assert_eq!(threshold_keygen_with_rng(N + 1, N, &mut rng).err(), Some(ReportingError::InvalidThreshold(N + 1)));
+    }
+}
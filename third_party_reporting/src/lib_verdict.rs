@@ -0,0 +1,120 @@
+use curve25519_dalek::ristretto::CompressedRistretto;
+use serde::{Serialize, Deserialize};
+use crate::lib_common::Verdict;
+use crate::lib_context::ReportContext;
+use crate::lib_error::ReportingError;
+use crate::lib_nizk::DleqProof;
+use crate::lib_wire::FieldWriter;
+
+// Moderator verdicts third parties can check without any secret
+//
+// The moderator publishes the point it decrypted from the report (the
+// platform tag sigma_pt in the basic scheme, r' from c3_prime in the moderator
+// privacy schemes) with a Chaum-Pedersen proof that it is the decryption of
+// the report ciphertext under its registered key. The challenge of the proof
+// absorbs the verdict, the context and c2 (verdict_transcript), so the proof
+// is also a signature of the verdict by the holder of that key and does not
+// carry over to another verdict or report.
+//
+// Given the report, the message and the moderator public key, a verifier
+// checks the proof and re-opens the commitment (check_outcome). The platform
+// tag itself is checked with the key shared by the platform and the moderator,
+// for it the verifier relies on the signed verdict.
+
+// Signed outcome of moderate_signed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedVerdict {
+    pub verdict: Verdict, // Accept with the recovered context or Reject with the failed check
+    pub ctx: ReportContext, // Platform context of the report
+    pub decrypted: CompressedRistretto, // Plaintext of the report ciphertext
+    pub proof: DleqProof // Correct decryption, signs the fields above
+}
+
+// Message signed by the decryption proof
+pub(crate) fn verdict_transcript(verdict: &Verdict, ctx: &ReportContext, c2: &[u8]) -> Result<Vec<u8>, ReportingError> {
+    let mut w = FieldWriter::new();
+    match verdict {
+        Verdict::Accept(accepted) => {
+            w.bytes(b"accept");
+            w.bytes(&accepted.to_bytes());
+        },
+        Verdict::Reject(e) => {
+            w.bytes(b"reject");
+            w.u32(e.code());
+            w.u64(e.value());
+        }
+    }
+    w.bytes(&ctx.to_bytes());
+    w.bytes(c2);
+
+    w.finish()
+}
+
+// The verdict is about a report with context ctx whose commitment opens
+// (com_ok) or not. moderate checks the commitment before the tag, so it only
+// rejects with BadCommitment, or with BadPlatformTag once the commitment opened,
+// or with ExpiredReport on the timestamp of ctx once the tag verified.
+pub(crate) fn check_outcome(sv: &SignedVerdict, ctx: &ReportContext, com_ok: bool) -> Result<(), ReportingError> {
+    if sv.ctx != *ctx {
+        return Err(ReportingError::BadVerdict);
+    }
+
+    match (&sv.verdict, com_ok) {
+        (Verdict::Accept(accepted), true) if accepted == ctx => Ok(()),
+        (Verdict::Reject(ReportingError::BadPlatformTag), true) => Ok(()),
+        (Verdict::Reject(ReportingError::ExpiredReport(timestamp)), true) if *timestamp == ctx.timestamp => Ok(()),
+        (Verdict::Reject(ReportingError::BadCommitment), false) => Ok(()),
+        _ => Err(ReportingError::BadVerdict)
+    }
+}
+
+// Checks that sv verifies and that changing its verdict, context, decrypted
+// point or proof makes it fail
+#[cfg(test)]
+pub(crate) fn check_signed_verdict(sv: &SignedVerdict, verify: impl Fn(&SignedVerdict) -> Result<(), ReportingError>) {
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use curve25519_dalek::scalar::Scalar;
+
+    assert_eq!(verify(sv), Ok(()));
+
+    let verdict = match sv.verdict {
+        Verdict::Accept(_) => Verdict::Reject(ReportingError::BadPlatformTag),
+        Verdict::Reject(_) => Verdict::Accept(sv.ctx)
+    };
+    let ctx = ReportContext { message_id: sv.ctx.message_id + 1, ..sv.ctx };
+    let decrypted = (sv.decrypted.decompress().unwrap() + RistrettoPoint::mul_base(&Scalar::ONE)).compress();
+    let proof = DleqProof { z: sv.proof.z + Scalar::ONE, ..sv.proof };
+
+    for changed in [
+        SignedVerdict { verdict, ..sv.clone() },
+        SignedVerdict { ctx, ..sv.clone() },
+        SignedVerdict { decrypted, ..sv.clone() },
+        SignedVerdict { proof, ..sv.clone() }
+    ] {
+        let result = verify(&changed);
+        assert!(matches!(result, Err(ReportingError::BadVerdict | ReportingError::BadProof)), "Changed verdict verified: {:?}", result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_signs_error_code_and_value() {
+        let ctx = ReportContext { timestamp: 4, ..ReportContext::new(1, 2, 3) };
+        let transcript = |e: ReportingError| verdict_transcript(&Verdict::Reject(e), &ctx, b"c2").unwrap();
+
+        // The encoding of a rejection is fixed by the code, not by the error text
+        let mut w = FieldWriter::new();
+        w.bytes(b"reject");
+        w.u32(23);
+        w.u64(4);
+        w.bytes(&ctx.to_bytes());
+        w.bytes(b"c2");
+        assert_eq!(transcript(ReportingError::ExpiredReport(4)), w.finish().unwrap());
+
+        assert_ne!(transcript(ReportingError::ExpiredEpoch(1)), transcript(ReportingError::ExpiredEpoch(2)));
+        assert_ne!(transcript(ReportingError::ExpiredEpoch(1)), transcript(ReportingError::UnknownEpoch(1)));
+    }
+}
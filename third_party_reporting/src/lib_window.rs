@@ -0,0 +1,150 @@
+use serde::{Serialize, Deserialize};
+use crate::lib_common::Verdict;
+use crate::lib_context::{ReportContext, ExpiryPolicy};
+use crate::lib_error::ReportingError;
+use crate::lib_scheme::ReportingScheme;
+use crate::lib_wire::{WireMessage, MessageKind, FieldWriter, FieldReader};
+
+// Conversation window reports
+//
+// A window bundles the reports of consecutive messages of one conversation,
+// oldest first, the reported message last (for example the reported message
+// and the k messages before it). The moderator verifies every report on its
+// own with the moderate of the scheme, then checks on the verified contexts
+// that they all belong to the same conversation and that their sequence
+// numbers, assigned by Platform::process (lib_replay.rs) and bound into the
+// platform tag, follow each other without a gap. A reporter can therefore
+// neither reorder the messages nor leave one out of the window.
+//
+// Works with every scheme, in particular lib_basic and lib_mod_priv. The
+// reporter needs a report doc for every message of the window.
+
+// Output of window_gen, sent to the moderator
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WindowReport<R> {
+    pub messages: Vec<String>, // Plaintexts, oldest first
+    pub reports: Vec<R> // Report on each message, in the order of messages
+}
+
+// Outcome of moderate_window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowVerdict {
+    pub verdicts: Vec<Verdict>, // Verdict on each report of the window
+    // Accept with the context of the reported message if every report was
+    // accepted and the messages are consecutive, else the first failed check
+    pub window: Verdict
+}
+
+// Every report of a window is encoded with the fields of the scheme's Report
+impl<R: WireMessage> WireMessage for WindowReport<R> {
+    const SCHEME_ID: u8 = R::SCHEME_ID;
+    const KIND: MessageKind = MessageKind::WindowReport;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.reports.len() as u32);
+        for (message, report) in self.messages.iter().zip(self.reports.iter()) {
+            let mut fields = FieldWriter::new();
+            report.write_fields(&mut fields);
+
+            w.bytes(message.as_bytes());
+            w.nested(fields);
+        }
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<WindowReport<R>, ReportingError> {
+        let count = r.u32()?;
+        let mut messages = Vec::new();
+        let mut reports = Vec::new();
+        for _i in 0..count {
+            messages.push(r.string()?);
+
+            let mut fields = FieldReader::new(r.bytes()?);
+            reports.push(R::read_fields(&mut fields)?);
+            fields.finish()?;
+        }
+
+        Ok(WindowReport { messages, reports })
+    }
+}
+
+// report(m, rd) on each (m, rd) of a window, given oldest first
+pub fn window_gen<S: ReportingScheme>(window: &[(String, S::ReportDoc)]) -> Result<WindowReport<S::Report>, ReportingError> {
+    if window.is_empty() {
+        return Err(ReportingError::EmptyWindow);
+    }
+
+    let mut messages = Vec::with_capacity(window.len());
+    let mut reports = Vec::with_capacity(window.len());
+    for (message, rd) in window.iter() {
+        reports.push(S::report_gen(message, rd)?);
+        messages.push(message.clone());
+    }
+
+    Ok(WindowReport { messages, reports })
+}
+
+// Contexts of one conversation with consecutive sequence numbers
+pub fn check_window(contexts: &[ReportContext]) -> Result<(), ReportingError> {
+    let first = contexts.first().ok_or(ReportingError::EmptyWindow)?;
+
+    for (previous, ctx) in contexts.iter().zip(contexts.iter().skip(1)) {
+        if ctx.conversation != first.conversation || previous.sequence.checked_add(1) != Some(ctx.sequence) {
+            return Err(ReportingError::NotAdjacent(ctx.sequence));
+        }
+    }
+
+    Ok(())
+}
+
+// moderate(sk_mod, sk_p, m, report) on every report of the window, then
+// check_window on the verified contexts. A window that cannot be decoded, or
+// without reports, yields an error
+pub fn moderate_window<S: ReportingScheme>(moderator: &S::Moderator, window: &WindowReport<S::Report>, policy: &ExpiryPolicy) -> Result<WindowVerdict, ReportingError> {
+    if window.messages.len() != window.reports.len() {
+        return Err(ReportingError::MalformedEncoding);
+    }
+    if window.reports.is_empty() {
+        return Err(ReportingError::EmptyWindow);
+    }
+
+    let mut verdicts = Vec::with_capacity(window.reports.len());
+    let mut contexts = Vec::with_capacity(window.reports.len());
+    let mut rejected = None;
+    for (message, report) in window.messages.iter().zip(window.reports.iter()) {
+        let verdict = S::moderate(moderator, message, report, policy)?;
+        match &verdict {
+            Verdict::Accept(ctx) => contexts.push(*ctx),
+            Verdict::Reject(e) => { rejected.get_or_insert_with(|| e.clone()); }
+        }
+        verdicts.push(verdict);
+    }
+
+    let window = match (rejected, check_window(&contexts)) {
+        (Some(e), _) | (None, Err(e)) => Verdict::Reject(e),
+        (None, Ok(())) => Verdict::Accept(contexts[contexts.len() - 1])
+    };
+
+    Ok(WindowVerdict { verdicts, window })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(conversation: u64, sequence: u64) -> ReportContext {
+        ReportContext { sequence, ..ReportContext::new(1, 2, conversation) }
+    }
+
+    #[test]
+    fn window_must_be_consecutive() {
+        assert_eq!(check_window(&[ctx(5, 3), ctx(5, 4), ctx(5, 5)]), Ok(()));
+        assert_eq!(check_window(&[ctx(5, 3)]), Ok(()));
+
+        // A left out message, a reordered window and a message of another conversation
+        assert_eq!(check_window(&[ctx(5, 3), ctx(5, 5)]), Err(ReportingError::NotAdjacent(5)));
+        assert_eq!(check_window(&[ctx(5, 4), ctx(5, 3)]), Err(ReportingError::NotAdjacent(3)));
+        assert_eq!(check_window(&[ctx(5, 3), ctx(6, 4)]), Err(ReportingError::NotAdjacent(4)));
+        assert_eq!(check_window(&[ctx(5, u64::MAX), ctx(5, 0)]), Err(ReportingError::NotAdjacent(0)));
+        assert_eq!(check_window(&[]), Err(ReportingError::EmptyWindow));
+    }
+}
@@ -0,0 +1,222 @@
+use curve25519_dalek::ristretto::CompressedRistretto;
+use curve25519_dalek::scalar::Scalar;
+
+use crate::lib_error::ReportingError;
+
+// Wire format shared by every scheme
+//
+// envelope = version (1 byte) || scheme id (1 byte) || message kind (1 byte) || fields
+// field    = length (4 bytes, big endian) || bytes
+//
+// Decoders reject any version other than WIRE_VERSION, any scheme id other
+// than the one of the message type and trailing bytes after the last field.
+// Encoders reject fields longer than a length prefix can carry.
+
+pub const WIRE_VERSION: u8 = 1;
+
+// Scheme identifiers
+pub const SCHEME_PLAIN: u8 = 0;
+pub const SCHEME_BASIC: u8 = 1;
+pub const SCHEME_MOD_PRIV: u8 = 2;
+pub const SCHEME_CONSTANT_MOD_PRIV: u8 = 3;
+pub const SCHEME_RSA_BASIC: u8 = 4;
+
+// Kind of message carried in an envelope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MessageKind {
+    Send = 1, // Output of send: (c1, c2, ad)
+    Process = 2, // Output of process: (sigma, st)
+    ReportDoc = 3, // Output of read, stored by the receiver
+    Report = 4, // Output of report_gen, sent to the moderator
+    PartialDecryption = 5, // Committee member share of a threshold decryption
+    RegistrationRequest = 6, // Moderator public keys with a proof of possession
+    RegistrationResponse = 7, // Issued MAC key and certificate
+    Certificate = 8, // Platform signature on a moderator directory entry
+    RevocationList = 9, // Signed list of revoked moderator ids
+    RotationRequest = 10, // New moderator keys authorized by the current ones
+    KeyRotation = 11, // New platform key endorsed by the previous one
+    WindowReport = 12 // Consecutive reports of one conversation (lib_window.rs)
+}
+
+// Protocol message with a wire encoding
+pub trait WireMessage: Sized {
+    const SCHEME_ID: u8;
+    const KIND: MessageKind;
+
+    fn write_fields(&self, w: &mut FieldWriter);
+    fn read_fields(r: &mut FieldReader) -> Result<Self, ReportingError>;
+}
+
+pub fn encode<M: WireMessage>(msg: &M) -> Result<Vec<u8>, ReportingError> {
+    let mut w = FieldWriter::new();
+    msg.write_fields(&mut w);
+
+    Ok([&[WIRE_VERSION, M::SCHEME_ID, M::KIND as u8][..], &w.finish()?[..]].concat())
+}
+
+pub fn decode<M: WireMessage>(bytes: &[u8]) -> Result<M, ReportingError> {
+    if bytes.len() < 3 {
+        return Err(ReportingError::MalformedEncoding);
+    }
+    if bytes[0] != WIRE_VERSION {
+        return Err(ReportingError::UnsupportedVersion(bytes[0]));
+    }
+    if bytes[1] != M::SCHEME_ID {
+        return Err(ReportingError::WrongScheme(bytes[1]));
+    }
+    if bytes[2] != M::KIND as u8 {
+        return Err(ReportingError::MalformedEncoding);
+    }
+
+    let mut r = FieldReader::new(&bytes[3..]);
+    let msg = M::read_fields(&mut r)?;
+    r.finish()?;
+
+    Ok(msg)
+}
+
+
+// Writes length-prefixed fields, a field too long for its prefix fails finish
+pub struct FieldWriter {
+    buf: Vec<u8>,
+    too_long: bool
+}
+
+impl FieldWriter {
+    pub fn new() -> FieldWriter {
+        FieldWriter { buf: Vec::new(), too_long: false }
+    }
+
+    pub fn bytes(&mut self, field: &[u8]) {
+        match u32::try_from(field.len()) {
+            Ok(len) => {
+                self.buf.extend_from_slice(&len.to_be_bytes());
+                self.buf.extend_from_slice(field);
+            },
+            Err(_) => self.too_long = true
+        }
+    }
+
+    // Fields of inner as a single field
+    pub fn nested(&mut self, inner: FieldWriter) {
+        self.too_long |= inner.too_long;
+        self.bytes(&inner.buf);
+    }
+
+    pub fn u32(&mut self, x: u32) {
+        self.bytes(&x.to_be_bytes());
+    }
+
+    pub fn u64(&mut self, x: u64) {
+        self.bytes(&x.to_be_bytes());
+    }
+
+    pub fn point(&mut self, p: &CompressedRistretto) {
+        self.bytes(p.as_bytes());
+    }
+
+    pub fn scalar(&mut self, s: &Scalar) {
+        self.bytes(s.as_bytes());
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, ReportingError> {
+        if self.too_long {
+            return Err(ReportingError::MalformedEncoding);
+        }
+
+        Ok(self.buf)
+    }
+}
+
+impl Default for FieldWriter {
+    fn default() -> FieldWriter {
+        FieldWriter::new()
+    }
+}
+
+
+// Reads length-prefixed fields
+pub struct FieldReader<'a> {
+    buf: &'a [u8]
+}
+
+impl<'a> FieldReader<'a> {
+    pub fn new(buf: &'a [u8]) -> FieldReader<'a> {
+        FieldReader { buf }
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], ReportingError> {
+        if self.buf.len() < 4 {
+            return Err(ReportingError::MalformedEncoding);
+        }
+        let len = u32::from_be_bytes(self.buf[..4].try_into().unwrap()) as usize;
+        let rest = &self.buf[4..];
+        if rest.len() < len {
+            return Err(ReportingError::MalformedEncoding);
+        }
+
+        let (field, rest) = rest.split_at(len);
+        self.buf = rest;
+
+        Ok(field)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], ReportingError> {
+        self.bytes()?.try_into().map_err(|_| ReportingError::MalformedEncoding)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, ReportingError> {
+        Ok(u32::from_be_bytes(self.array::<4>()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, ReportingError> {
+        Ok(u64::from_be_bytes(self.array::<8>()?))
+    }
+
+    pub fn point(&mut self) -> Result<CompressedRistretto, ReportingError> {
+        Ok(CompressedRistretto(self.array::<32>()?))
+    }
+
+    pub fn scalar(&mut self) -> Result<Scalar, ReportingError> {
+        Option::from(Scalar::from_canonical_bytes(self.array::<32>()?)).ok_or(ReportingError::MalformedEncoding)
+    }
+
+    pub fn string(&mut self) -> Result<String, ReportingError> {
+        let s = std::str::from_utf8(self.bytes()?).map_err(|_| ReportingError::MalformedEncoding)?;
+
+        Ok(s.to_string())
+    }
+
+    // Fails if bytes are left after the last field
+    pub fn finish(self) -> Result<(), ReportingError> {
+        if !self.buf.is_empty() {
+            return Err(ReportingError::MalformedEncoding);
+        }
+
+        Ok(())
+    }
+}
+
+
+// Checks that msg decodes from its encoding back to the same encoding, and
+// that a wrong header, a truncated length prefix or trailing bytes are rejected
+#[cfg(test)]
+pub(crate) fn check_encoding<M: WireMessage>(msg: &M) {
+    let bytes = encode(msg).unwrap();
+    assert_eq!(encode(&decode::<M>(&bytes).unwrap()).unwrap(), bytes);
+
+    let with = |i: usize, b: u8| {
+        let mut bytes = bytes.clone();
+        bytes[i] = b;
+        decode::<M>(&bytes).err()
+    };
+    assert_eq!(with(0, WIRE_VERSION + 1), Some(ReportingError::UnsupportedVersion(WIRE_VERSION + 1)));
+    assert_eq!(with(1, M::SCHEME_ID ^ 0x80), Some(ReportingError::WrongScheme(M::SCHEME_ID ^ 0x80)));
+    assert_eq!(with(2, M::KIND as u8 ^ 0x80), Some(ReportingError::MalformedEncoding));
+
+    assert_eq!(decode::<M>(&bytes[..2]).err(), Some(ReportingError::MalformedEncoding));
+    assert_eq!(decode::<M>(&bytes[..5]).err(), Some(ReportingError::MalformedEncoding));
+    assert_eq!(decode::<M>(&bytes[..bytes.len() - 1]).err(), Some(ReportingError::MalformedEncoding));
+    assert_eq!(decode::<M>(&[&bytes[..], &[0]].concat()).err(), Some(ReportingError::MalformedEncoding));
+}
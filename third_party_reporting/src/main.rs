@@ -1,9 +1,15 @@
-use third_party_reporting::lib_basic as basic;
-use third_party_reporting::lib_mod_priv as mod_priv;
-use third_party_reporting::lib_constant_mod_priv as constant_mod_priv;
-use third_party_reporting::lib_plain as plain;
+use third_party_reporting::lib_basic::Basic;
+use third_party_reporting::lib_mod_priv::ModPriv;
+use third_party_reporting::lib_constant_mod_priv::ConstantModPriv;
+use third_party_reporting::lib_plain::Plain;
+#[cfg(feature = "rsa")]
+use third_party_reporting::rsa_lib_basic::RsaBasic;
+use third_party_reporting::lib_scheme::{test_flow, test_threshold_flow, test_window_flow, test_redact_flow};
+use third_party_reporting::lib_error::ReportingError;
 
 use clap::Parser;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand::rngs::{OsRng, StdRng};
 
 
 #[derive(Parser, Debug)]
@@ -18,199 +24,114 @@ struct Args {
     #[arg(long, default_value_t = false)]
     const_priv: bool,
 
+    // Basic scheme with RSA-OAEP instead of El Gamal (needs the rsa feature),
+    // its moderator keys are not rotated
+    #[cfg(feature = "rsa")]
+    #[arg(long, default_value_t = false)]
+    rsa_basic: bool,
+
     #[arg(long, default_value_t = 1)]
     num_clients: usize,
 
     #[arg(long, default_value_t = 1)]
     num_moderators: usize,
 
+    // Basic scheme with a committee of num_moderators, threshold t, whose keys
+    // stay at epoch 0
+    #[arg(long)]
+    threshold: Option<u32>,
+
+    // Generate the committee keys with a DKG instead of a trusted dealer
+    #[arg(long, default_value_t = false)]
+    dkg: bool,
+
+    // Report windows of this many consecutive messages with the selected
+    // basic and moderator privacy schemes
+    #[arg(long)]
+    window: Option<usize>,
+
+    // Report every other word of a message of this many words with the
+    // selected basic and moderator privacy schemes
+    #[arg(long)]
+    redact: Option<usize>,
+
     #[arg(long, default_value_t = 100)]
     msg_size: usize,
 
     #[arg(long, default_value_t = false)]
-    test_e2ee: bool
-}
+    test_e2ee: bool,
 
-fn main() {
-
-    let args = Args::parse();
+    // Seed for a reproducible run (OsRng when not set)
+    #[arg(long)]
+    seed: Option<u64>
+}
 
+fn run<R: CryptoRng + RngCore>(args: &Args, rng: &mut R) -> Result<(), ReportingError> {
+    // Run each selected scheme flow with variable number of clients / msgs sent, msg_size, and
+    // number of moderators
     if args.basic {
-        test_basic(args.num_clients, args.msg_size, args.num_moderators);
+        test_flow::<Basic, _>(args.num_clients, args.msg_size, args.num_moderators, rng);
     }
-    
+
     if args.mod_priv {
-        test_priv(args.num_clients, args.msg_size, args.num_moderators);
+        test_flow::<ModPriv, _>(args.num_clients, args.msg_size, args.num_moderators, rng);
     }
-    
+
     if args.const_priv {
-        test_constant_mod_priv(args.num_clients, args.msg_size, args.num_moderators);
+        test_flow::<ConstantModPriv, _>(args.num_clients, args.msg_size, args.num_moderators, rng);
     }
 
-    if args.test_e2ee {
-        test_e2ee(args.num_clients, args.msg_size);
+    if let Some(t) = args.threshold {
+        let num_moderators = u32::try_from(args.num_moderators).map_err(|_| ReportingError::InvalidThreshold(t))?;
+        test_threshold_flow(args.num_clients, args.msg_size, t, num_moderators, args.dkg, rng)?;
     }
 
-}
-
-// Run the plain franking scheme
-pub fn test_e2ee(num_clients: usize, msg_size: usize) {
-    println!();
-    println!("======================== Started Testing Plain Franking Scheme ====================");
-    println!();
-    
-
-    // Initialize Moderator
-    let moderator = plain::Moderator::new();
-
-    // Initialize Clients
-    let clients = plain::test_init_clients(num_clients);
-
-    // Prepare messages
-    let mut ms: Vec<Vec<String>> = Vec::new();
-    for _i in 0..num_clients {
-        ms.push(plain::test_init_messages(1, msg_size));
+    if let Some(window) = args.window {
+        if args.basic {
+            test_window_flow::<Basic, _>(window, args.msg_size, args.num_moderators, rng)?;
+        }
+        if args.mod_priv {
+            test_window_flow::<ModPriv, _>(window, args.msg_size, args.num_moderators, rng)?;
+        }
     }
 
-    // Send messages
-    let c1c2s = plain::test_send(&clients, &ms, true);
-
-    // Process messages
-    let sigmas = plain::test_process(&moderator, &c1c2s);
-
-    // Read messages
-    let reports = plain::test_read(&clients, &c1c2s, &sigmas, true);
-
-    // Moderate reports
-    plain::test_moderate(&moderator, &reports, &ms, true);
-
-
-    println!();
-    println!("======================== Finished Testing Plain Franking Scheme with ====================");
-    println!();
-
-}
-
-
-// Method for running the constant moderator privacy scheme flow with variable number of clients, msg sizes
-// and number of moderators
-pub fn test_constant_mod_priv(num_clients: usize, msg_size: usize, num_moderators: usize) {
-    println!("======================== Started Testing Constant Moderator Privacy Scheme with {} moderators ====================", num_moderators);
-    println!();
-
-    // Initialize Platform
-    let mut platform = constant_mod_priv::test_setup_platform();
-
-    // Initialize Moderators
-    let (moderators, pks) = constant_mod_priv::test_setup_mod(&mut platform, num_moderators);
-
-    // Initialize Clients
-    let clients = constant_mod_priv::test_init_clients(num_clients);
-
-    // Prepare messages
-    let ms = constant_mod_priv::test_init_messages(num_clients, msg_size);
-
-    // Send messages
-    let c1c2ad = constant_mod_priv::test_send(num_clients, &moderators, &clients, &ms, true);
-
-    // Process messages
-    let sigma_st = constant_mod_priv::test_process(num_clients, msg_size, &c1c2ad, &platform, true);
-
-    // Read messages
-    let report_docs = constant_mod_priv::test_read(num_clients, &c1c2ad, &sigma_st, &clients, &pks, true);
-
-    // Generate reports
-    let reports = constant_mod_priv::test_report(num_clients, &report_docs, true);
-
-    // Moderate reports
-    constant_mod_priv::test_moderate(num_clients, &reports, &moderators, true);
-
-
-    println!();
-    println!("======================== Finished Testing Constant Moderator Privacy Scheme with {} moderators ====================", num_moderators);
-    println!();
-    println!();
-
-}
-
-
-// Method for running the moderator privacy scheme flow with variable number of clients, msg sizes
-// and number of moderators
-pub fn test_priv(num_clients: usize, msg_size: usize, num_moderators: usize) {
-    println!("======================== Started Testing Moderator Privacy Scheme with {} moderators ====================", num_moderators);
-    println!();
-
-    // Initialize Platform
-    let mut platform = mod_priv::test_setup_platform();
-
-    // Initialize Moderators
-    let (moderators, pks) = mod_priv::test_setup_mod(&mut platform, num_moderators);
-
-    // Initialize Clients
-    let clients = mod_priv::test_init_clients(num_clients);
-
-    // Prepare messages
-    let ms = mod_priv::test_init_messages(num_clients, msg_size);
-
-    // Send messages
-    let c1c2ad = mod_priv::test_send(num_clients, &moderators, &clients, &ms, true);
-
-    // Process messages
-    let sigma_st = mod_priv::test_process(num_clients, msg_size, &c1c2ad, &platform, true);
-
-    // Read messages
-    let rds = mod_priv::test_read(num_clients, &c1c2ad, &sigma_st, &clients, &pks, true);
-
-    // Generate reports
-    let reports = mod_priv::test_report(num_clients, &rds, true);
+    if let Some(words) = args.redact {
+        if args.basic {
+            test_redact_flow::<Basic, _>(words, args.msg_size, args.num_moderators, rng);
+        }
+        if args.mod_priv {
+            test_redact_flow::<ModPriv, _>(words, args.msg_size, args.num_moderators, rng);
+        }
+        if args.const_priv {
+            test_redact_flow::<ConstantModPriv, _>(words, args.msg_size, args.num_moderators, rng);
+        }
+    }
 
-    // Moderate reports
-    mod_priv::test_moderate(num_clients, &reports, &moderators, true);
+    #[cfg(feature = "rsa")]
+    if args.rsa_basic {
+        test_flow::<RsaBasic, _>(args.num_clients, args.msg_size, args.num_moderators, rng);
+    }
 
+    // The plain franking scheme has a single moderator acting as the platform
+    if args.test_e2ee {
+        test_flow::<Plain, _>(args.num_clients, args.msg_size, 1, rng);
+    }
 
-    println!();
-    println!("======================== Finished Testing Moderator Privacy Scheme with {} moderators ====================", num_moderators);
-    println!();
-    println!();
+    Ok(())
 }
 
-// Method for running the whole basic scheme flow with variable number of clients / msgs sent, msg_size, and 
-// number of moderators
-pub fn test_basic(num_clients: usize, msg_size: usize, num_moderators: usize) {
-    println!("======================== Started Testing Basic Scheme with {} moderators ====================", num_moderators);
-    println!();
-
-    // Initialize platform
-    let mut platform = basic::test_basic_setup_platform();
-
-    // Initialize Moderators
-    let (moderators, pks) = basic::test_basic_setup_mod(&mut platform, num_moderators);
-
-    // Initialize Clients
-    let clients = basic::test_basic_init_clients(num_clients);
-
-    // Prepare messages
-    let ms = basic::test_basic_init_messages(num_clients, msg_size);
-
-    // Send messages
-    let c1c2ad = basic::test_basic_send(num_clients, num_moderators, &clients, &ms, true);
-
-    // Process messages
-    let sigma_st = basic::test_basic_process(num_clients, msg_size, &c1c2ad, &platform, true);
-
-    // Read messages and generate report docs
-    let rds = basic::test_basic_read(num_clients, &c1c2ad, &sigma_st, &clients, &pks, true);
-
-    // Generate reports
-    let reports = basic::test_report(num_clients, &rds, true);
+fn main() {
 
-    // Moderate reports
-    basic::test_basic_moderate(num_clients, &reports, &moderators, true);
+    let args = Args::parse();
 
+    let result = match args.seed {
+        Some(seed) => run(&args, &mut StdRng::seed_from_u64(seed)),
+        None => run(&args, &mut OsRng)
+    };
 
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
 
-    println!();
-    println!("======================== Completed Testing Basic Scheme with {} moderators ====================", num_moderators);
-    println!();
-    println!();
 }
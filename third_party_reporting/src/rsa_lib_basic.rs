@@ -1,304 +1,745 @@
 use aes_gcm::{
-    aead::{Aead, AeadCore, KeyInit},
-    Aes256Gcm, Nonce, Key
+    aead::KeyInit,
+    Aes256Gcm, Key
 };
-use bincode;
+use crate::lib_common::*;
+use crate::lib_error::ReportingError;
+use crate::lib_gamal as gamal;
+use crate::lib_nizk::{self as nizk, SchnorrProof};
+use crate::lib_registration::{self as registration, Revocations};
+use crate::lib_replay::ReplayCache;
+use crate::lib_context::{ReportContext, ExpiryPolicy};
+use crate::lib_merkle::Opening;
+use crate::lib_scheme::ReportingScheme;
+use crate::lib_secret::Secret;
+use crate::lib_wire::*;
+use crate::lib_domain::{Domain, Role, Usage};
 use openssl::encrypt::{Encrypter, Decrypter};
+use openssl::hash::MessageDigest;
+use openssl::sign::{Signer, Verifier};
 use openssl::rsa::{Rsa, Padding};
 use openssl::pkey::PKey;
 use openssl::pkey::{Private, Public};
-use crate::lib_common::*;
-use rand::distributions::Alphanumeric;
-use rand::thread_rng;
-use rand::distributions::DistString;
-use rand::Rng;
+use std::mem;
+use curve25519_dalek::ristretto::{RistrettoPoint, CompressedRistretto};
+use curve25519_dalek::scalar::Scalar;
+use serde::{Serialize, Deserialize};
+use rand::{CryptoRng, RngCore};
+use rand::rngs::OsRng;
+
+// Basic scheme with the platform tag encrypted under RSA-OAEP instead of El Gamal
+//
+// OpenSSL draws its own randomness for key generation and OAEP padding, so
+// moderator keys and sigma are not reproducible from a seeded rng; the rest of
+// the flow (franking keys, MAC keys, AES-GCM nonces) still uses the rng.
+//
+// Moderator keys have no epochs here (lib_registration.rs): they are not
+// rotated and reports do not name a key. Replacing a key means revoking the
+// moderator id and registering the new key under a new one.
 
 // Global constants
 const RSA_MODULUS: u32 = 2048;
 
+// Domain separation labels (see lib_domain.rs)
+const COMMITMENT: Domain = Domain::new(SCHEME_RSA_BASIC, Role::Sender, Usage::Commitment);
+const PLATFORM_TAG: Domain = Domain::new(SCHEME_RSA_BASIC, Role::Platform, Usage::PlatformTag);
+const CERTIFICATE: Domain = Domain::new(SCHEME_RSA_BASIC, Role::Platform, Usage::Registration);
+const REVOCATION: Domain = Domain::new(SCHEME_RSA_BASIC, Role::Platform, Usage::Revocation);
 
-// Moderator Properties
-pub struct Moderator {
-    pub sk_p: [u8; 32], // Mac Key shared with the Platform
-    pub keypair: PKey<Private>, // Moderator Encryption key pair
-    pub pk_mod: Option<PKey<Public>> // Moderator public key
+type Point = CompressedRistretto;
+
+// Output of send: (c1, c2, ad)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub c1: Vec<u8>, // Encryption of (m, k_f) under the shared message key
+    pub c2: Vec<u8>, // Commitment to the message
+    pub moderator_id: u32 // ad, the moderator chosen by the sender
 }
 
-// Moderator Implementation 
-impl Moderator {
-    // SetupMod(pk_reg, 1^lambda)
-    pub fn new(_pk_reg: &Option<Vec<u8>>) -> Moderator {
-        let rsa = Rsa::generate(RSA_MODULUS).unwrap();
-        let pkey = PKey::from_rsa(rsa).unwrap();
-        Moderator {
-            sk_p: mac_keygen(),
-            keypair: pkey,
-            pk_mod: None
+// State output by process alongside the encrypted tag
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProcessState {
+    pub ctx: ReportContext, // Platform context, bound into the tag
+    pub moderator_id: u32
+}
+
+// Report sent to the moderator (the report doc stored by the receiver is identical)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub opening: Opening, // Opens c2 to the message (k_f) or to some of its segments (lib_merkle.rs)
+    pub c2: Vec<u8>, // Commitment to the message
+    pub ctx: ReportContext, // Platform context
+    pub sigma: Vec<u8> // RSA-OAEP encryption of the platform tag
+}
+
+pub type ReportDoc = Report;
+
+// Registration request (lib_registration.rs), pop is an RSA signature
+// (PKCS#1 v1.5, SHA-256) over the proof of possession message
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationRequest {
+    pub pk_mod: Vec<u8>, // DER encoding of the moderator public key
+    pub pop: Vec<u8>
+}
+
+// Platform signature on a moderator directory entry
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    pub moderator_id: u32,
+    pub pk_mod: Vec<u8>, // DER encoding of the moderator public key
+    pub signature: SchnorrProof // Signature under k_reg
+}
+
+// Registration response, sent over a private channel
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RegistrationResponse {
+    pub sk_p: Secret<[u8; 32]>, // MAC key issued by the Platform
+    pub certificate: Certificate
+}
+
+// Revoked moderator ids published by the Platform
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub version: u32, // Number of revocations so far
+    pub revoked: Vec<u32>,
+    pub signature: SchnorrProof // Signature under k_reg
+}
+
+impl Certificate {
+    // Checks the Platform signature, clients call it before using pk_mod
+    pub fn verify(&self, k_reg: &Point) -> Result<(), ReportingError> {
+        let message = registration::certificate_message(self.moderator_id, &self.pk_mod);
+        if !nizk::schnorr_verify(&CERTIFICATE, &gamal::decompress(k_reg)?, &message, &self.signature) {
+            return Err(ReportingError::BadCertificate);
         }
+
+        Ok(())
     }
 
-    // lazy init of public key
-    // to provide users with public key on demand
-    pub fn get_public_key(&mut self) -> Option<PKey<Public>> {
-        if self.pk_mod.is_none() {
-            self.pk_mod = Some(PKey::public_key_from_pem(&(self.keypair.public_key_to_pem().unwrap())).unwrap());
+    pub fn public_key(&self) -> Result<PKey<Public>, ReportingError> {
+        PKey::public_key_from_der(&self.pk_mod).map_err(|_| ReportingError::MalformedEncoding)
+    }
+}
+
+impl RevocationList {
+    pub fn verify(&self, k_reg: &Point) -> Result<(), ReportingError> {
+        let message = registration::revocation_message(self.version, &self.revoked);
+        if !nizk::schnorr_verify(&REVOCATION, &gamal::decompress(k_reg)?, &message, &self.signature) {
+            return Err(ReportingError::BadCertificate);
         }
 
-        self.pk_mod.clone()
+        Ok(())
+    }
+}
+
+impl WireMessage for SentMessage {
+    const SCHEME_ID: u8 = SCHEME_RSA_BASIC;
+    const KIND: MessageKind = MessageKind::Send;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.c1);
+        w.bytes(&self.c2);
+        w.u32(self.moderator_id);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<SentMessage, ReportingError> {
+        Ok(SentMessage { c1: r.bytes()?.to_vec(), c2: r.bytes()?.to_vec(), moderator_id: r.u32()? })
+    }
+}
+
+// (sigma, st)
+impl WireMessage for (Vec<u8>, ProcessState) {
+    const SCHEME_ID: u8 = SCHEME_RSA_BASIC;
+    const KIND: MessageKind = MessageKind::Process;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        let (sigma, st) = self;
+        w.bytes(sigma);
+        w.bytes(&st.ctx.to_bytes());
+        w.u32(st.moderator_id);
     }
 
-    pub fn moderate(keypair: &PKey<Private>, sk_p: &[u8; 32], message: &str, report: &([u8; 32], Vec<u8>, Vec<u8>, Vec<u8>)) -> String {
-        let (k_f, c2, ctx, sigma) = report;
+    fn read_fields(r: &mut FieldReader) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        Ok((r.bytes()?.to_vec(), ProcessState { ctx: ReportContext::from_bytes(r.bytes()?)?, moderator_id: r.u32()? }))
+    }
+}
 
+// Report docs are stored with the same encoding as reports
+impl WireMessage for Report {
+    const SCHEME_ID: u8 = SCHEME_RSA_BASIC;
+    const KIND: MessageKind = MessageKind::Report;
 
-        let mut decrypter = Decrypter::new(&keypair).unwrap();
-        decrypter.set_rsa_padding(Padding::PKCS1_OAEP).unwrap();
-        let buffer_len = decrypter.decrypt_len(&sigma).unwrap();
-        let mut sigma_pt = vec![0; buffer_len];
+    fn write_fields(&self, w: &mut FieldWriter) {
+        self.opening.write_fields(w);
+        w.bytes(&self.c2);
+        w.bytes(&self.ctx.to_bytes());
+        w.bytes(&self.sigma);
+    }
 
-        let len: usize = decrypter.decrypt(&sigma, &mut sigma_pt).unwrap();
-        sigma_pt.truncate(len);
+    fn read_fields(r: &mut FieldReader) -> Result<Report, ReportingError> {
+        Ok(Report { opening: Opening::read_fields(r)?, c2: r.bytes()?.to_vec(), ctx: ReportContext::from_bytes(r.bytes()?)?, sigma: r.bytes()?.to_vec() })
+    }
+}
+
+impl WireMessage for RegistrationRequest {
+    const SCHEME_ID: u8 = SCHEME_RSA_BASIC;
+    const KIND: MessageKind = MessageKind::RegistrationRequest;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.pk_mod);
+        w.bytes(&self.pop);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationRequest, ReportingError> {
+        Ok(RegistrationRequest { pk_mod: r.bytes()?.to_vec(), pop: r.bytes()?.to_vec() })
+    }
+}
+
+impl WireMessage for Certificate {
+    const SCHEME_ID: u8 = SCHEME_RSA_BASIC;
+    const KIND: MessageKind = MessageKind::Certificate;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.moderator_id);
+        w.bytes(&self.pk_mod);
+        w.scalar(&self.signature.c);
+        w.scalar(&self.signature.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<Certificate, ReportingError> {
+        Ok(Certificate { moderator_id: r.u32()?, pk_mod: r.bytes()?.to_vec(), signature: SchnorrProof { c: r.scalar()?, z: r.scalar()? } })
+    }
+}
+
+// The certificate fields follow the MAC key
+impl WireMessage for RegistrationResponse {
+    const SCHEME_ID: u8 = SCHEME_RSA_BASIC;
+    const KIND: MessageKind = MessageKind::RegistrationResponse;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.bytes(&self.sk_p[..]);
+        self.certificate.write_fields(w);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RegistrationResponse, ReportingError> {
+        Ok(RegistrationResponse { sk_p: Secret::new(r.array()?), certificate: Certificate::read_fields(r)? })
+    }
+}
+
+impl WireMessage for RevocationList {
+    const SCHEME_ID: u8 = SCHEME_RSA_BASIC;
+    const KIND: MessageKind = MessageKind::RevocationList;
+
+    fn write_fields(&self, w: &mut FieldWriter) {
+        w.u32(self.version);
+        registration::write_ids(w, &self.revoked);
+        w.scalar(&self.signature.c);
+        w.scalar(&self.signature.z);
+    }
+
+    fn read_fields(r: &mut FieldReader) -> Result<RevocationList, ReportingError> {
+        Ok(RevocationList {
+            version: r.u32()?,
+            revoked: registration::read_ids(r)?,
+            signature: SchnorrProof { c: r.scalar()?, z: r.scalar()? }
+        })
+    }
+}
+
+// Moderator keys before registration
+pub struct PendingModerator {
+    keypair: PKey<Private>, // Moderator Encryption key pair
+    pub pk_mod: Vec<u8>, // DER encoding of the moderator public key
+    pub k_reg: Point // Registration key of the Platform
+}
+
+impl PendingModerator {
+    // SetupMod(pk_reg, 1^lambda)
+    pub fn new(k_reg: &Point) -> PendingModerator {
+        let rsa = Rsa::generate(RSA_MODULUS).expect("RSA key generation");
+        let keypair = PKey::from_rsa(rsa).expect("RSA key generation");
+        let pk_mod = keypair.public_key_to_der().expect("RSA public key encoding");
+
+        PendingModerator { keypair, pk_mod, k_reg: *k_reg }
+    }
+
+    pub fn request(&self) -> RegistrationRequest {
+        let message = registration::pop_message(self.k_reg.as_bytes(), &self.pk_mod);
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.keypair).expect("RSA signature");
+        signer.update(&message).expect("RSA signature");
+        let pop = signer.sign_to_vec().expect("RSA signature");
+
+        RegistrationRequest { pk_mod: self.pk_mod.clone(), pop }
+    }
+
+    // Checks the certificate and that it certifies pk_mod
+    pub fn finish(self, response: RegistrationResponse) -> Result<Moderator, ReportingError> {
+        let RegistrationResponse { sk_p, certificate } = response;
+        certificate.verify(&self.k_reg)?;
+
+        if certificate.pk_mod != self.pk_mod {
+            return Err(ReportingError::BadCertificate);
+        }
+
+        Ok(Moderator { sk_p, pk_mod: certificate.public_key()?, keypair: self.keypair, certificate })
+    }
+}
+
+// Moderator Properties
+pub struct Moderator {
+    sk_p: Secret<[u8; 32]>, // Mac Key shared with the Platform
+    keypair: PKey<Private>, // Moderator Encryption key pair, OpenSSL clears it when freed
+    pub pk_mod: PKey<Public>, // Moderator public key
+    pub certificate: Certificate // Issued at registration
+}
+
+// Moderator Implementation
+impl Moderator {
+    pub fn id(&self) -> u32 {
+        self.certificate.moderator_id
+    }
+
+    pub fn public_key(&self) -> PKey<Public> {
+        self.pk_mod.clone()
+    }
+
+    pub fn moderate(&self, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        let Report { opening, c2, ctx, sigma } = report;
+
+        // A sigma that does not decrypt is a bad tag, checked after the commitment
+        // like a tag that decrypts to the wrong MAC
+        let sigma_pt = rsa_oaep_dec(&self.keypair, sigma);
 
         // Verify committment
-        assert!(com_open(&c2, message, k_f));
+        if !opening.open(&COMMITMENT, c2, message) {
+            return Ok(Verdict::Reject(ReportingError::BadCommitment));
+        }
 
         // Verify signature
-        assert!(mac_verify(&sk_p, &[&c2[..], &ctx[..]].concat(), sigma_pt));
+        let valid = match sigma_pt {
+            Some(sigma_pt) => mac_verify(&PLATFORM_TAG, &self.sk_p, &[&c2[..], &ctx.to_bytes()].concat(), &sigma_pt),
+            None => false
+        };
+        if !valid {
+            return Ok(Verdict::Reject(ReportingError::BadPlatformTag));
+        }
 
-        let ctx_s = std::str::from_utf8(&ctx).unwrap();
-        return ctx_s.to_string();
+        Ok(policy.verdict(ctx))
     }
 }
 
+// RSA-OAEP encryption under a moderator public key
+fn rsa_oaep_enc(pk: &PKey<Public>, m: &[u8]) -> Vec<u8> {
+    // Registered keys are 2048 bit RSA keys and the tag is 32 bytes, so none
+    // of these can fail
+    let mut encrypter = Encrypter::new(pk).expect("RSA-OAEP encryption");
+    encrypter.set_rsa_padding(Padding::PKCS1_OAEP).expect("RSA-OAEP encryption");
+    let buffer_len = encrypter.encrypt_len(m).expect("RSA-OAEP encryption");
+
+    let mut c = vec![0; buffer_len];
+    let len = encrypter.encrypt(m, &mut c).expect("RSA-OAEP encryption");
+    c.truncate(len);
+
+    c
+}
+
+// RSA-OAEP decryption, None when c is not a valid encryption under keypair
+fn rsa_oaep_dec(keypair: &PKey<Private>, c: &[u8]) -> Option<Secret<Vec<u8>>> {
+    let mut decrypter = Decrypter::new(keypair).ok()?;
+    decrypter.set_rsa_padding(Padding::PKCS1_OAEP).ok()?;
+    let buffer_len = decrypter.decrypt_len(c).ok()?;
+
+    let mut m = Secret::new(vec![0; buffer_len]);
+    let len = decrypter.decrypt(c, &mut m).ok()?;
+    m.truncate(len);
+
+    Some(m)
+}
+
 
 
 // Platform Properties
 pub struct Platform {
-    pub k_p: Option<Vec<u8>>, // Platform key used for proxy re-encryption
-                  // Integer mod Z_q
-    pub k_reg: Option<Vec<u8>>, // Registration key also used for proxy re-encyrption
-               // Group element in G_2
-    pub sk_p: Vec<([u8; 32], PKey<Public>)> // Vector of Moderator keys accessible to the Platform
+    k_p: Secret<Scalar>, // Certificate signing key
+    pub k_reg: Point, // Registration key k_p * G
+    #[allow(clippy::type_complexity)]
+    sk_p: Vec<Option<(Secret<[u8; 32]>, PKey<Public>)>>, // Vector of Moderator keys accessible to the Platform, indexed by moderator id, None once revoked
+    revocations: RevocationList, // Signed list of the revoked ids
+    pub replay: ReplayCache // Commitments processed per conversation (lib_replay.rs)
 }
 
 // Platform Implementation
 impl Platform {
     pub fn new() -> Platform {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
+        let (k_p, k_reg) = gamal::elgamal_keygen_with_rng(rng);
+        let k_p = Secret::new(k_p);
+        let revocations = sign_revocations(&k_p, 0, Vec::new(), rng);
+
         Platform {
-            k_p: None,
-            k_reg: None,
-            sk_p: Vec::<([u8; 32], PKey<Public>)>::new()
+            k_p,
+            k_reg: k_reg.compress(),
+            sk_p: Vec::<Option<(Secret<[u8; 32]>, PKey<Public>)>>::new(),
+            revocations,
+            replay: ReplayCache::new()
         }
     }
 
-    pub fn setup_platform(&mut self) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
-        self.k_p = None;
-        self.k_reg = None;
+    // Removes moderator id from the directory and returns the new revocation
+    // list for clients. The slot stays empty so no other id changes
+    pub fn revoke(&mut self, moderator_id: u32) -> Result<RevocationList, ReportingError> {
+        self.revoke_with_rng(moderator_id, &mut OsRng)
+    }
+
+    pub fn revoke_with_rng<R: CryptoRng + RngCore>(&mut self, moderator_id: u32, rng: &mut R) -> Result<RevocationList, ReportingError> {
+        let slot = self.sk_p.get_mut(moderator_id as usize).ok_or(ReportingError::UnknownModerator(moderator_id))?;
+        if slot.take().is_some() {
+            let mut revoked = self.revocations.revoked.clone();
+            revoked.push(moderator_id);
+            self.revocations = sign_revocations(&self.k_p, self.revocations.version + 1, revoked, rng);
+        }
+
+        Ok(self.revocations.clone())
+    }
+
+    pub fn revocation_list(&self) -> &RevocationList {
+        &self.revocations
+    }
 
-        (self.k_p.clone(), self.k_reg.clone())
+    // Checks the proof of possession, issues the MAC key and certifies the
+    // new directory entry
+    pub fn register(&mut self, request: &RegistrationRequest) -> Result<RegistrationResponse, ReportingError> {
+        self.register_with_rng(request, &mut OsRng)
     }
 
-    pub fn process(_k_p: &Option<Vec<u8>>, ks: &Vec<([u8; 32], PKey<Public>)>, _c1: &Vec<u8>, c2: &Vec<u8>, ad: u32, ctx: &Vec<u8>) -> (Vec<u8>, (Vec<u8>, u32)) {
-        let moderator_id: usize = ad.try_into().unwrap();
-        let (mac_key_i, mod_pk_i) = &ks[moderator_id];
-        let sigma_pt = mac_sign(&mac_key_i, &[&c2[..], &ctx[..]].concat());
+    pub fn register_with_rng<R: CryptoRng + RngCore>(&mut self, request: &RegistrationRequest, rng: &mut R) -> Result<RegistrationResponse, ReportingError> {
+        let RegistrationRequest { pk_mod, pop } = request;
+        let pk = PKey::public_key_from_der(pk_mod).map_err(|_| ReportingError::MalformedEncoding)?;
 
+        // process relies on 2048 bit RSA keys
+        if pk.rsa().map(|rsa| rsa.size() * 8 != RSA_MODULUS).unwrap_or(true) {
+            return Err(ReportingError::BadModeratorKey);
+        }
+
+        let message = registration::pop_message(self.k_reg.as_bytes(), pk_mod);
+        let valid = match Verifier::new(MessageDigest::sha256(), &pk) {
+            Ok(mut verifier) => verifier.verify_oneshot(pop, &message).unwrap_or(false),
+            Err(_) => false
+        };
+        if !valid {
+            return Err(ReportingError::BadProof);
+        }
 
-        let mut encrypter = Encrypter::new(&mod_pk_i).unwrap();
-        encrypter.set_rsa_padding(Padding::PKCS1_OAEP).unwrap();
-        let buffer_len = encrypter.encrypt_len(&sigma_pt).unwrap();
-        
-        let mut sigma = vec![0; buffer_len];
+        let sk_p = Secret::new(mac_keygen_with_rng(rng));
+        let moderator_id = self.sk_p.len() as u32;
 
-        let len = encrypter.encrypt(&sigma_pt, &mut sigma).unwrap();
-        sigma.truncate(len);
+        let message = registration::certificate_message(moderator_id, pk_mod);
+        let signature = nizk::schnorr_prove(&CERTIFICATE, &self.k_p, &RistrettoPoint::mul_base(&self.k_p), &message, rng);
+
+        self.sk_p.push(Some((sk_p.clone(), pk)));
+
+        Ok(RegistrationResponse { sk_p, certificate: Certificate { moderator_id, pk_mod: pk_mod.clone(), signature } })
+    }
 
+    // OpenSSL supplies the OAEP randomness, rng is unused
+    pub fn process<R: CryptoRng + RngCore>(&mut self, _c1: &[u8], c2: &[u8], ad: u32, ctx: &ReportContext, _rng: &mut R) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        let moderator_id = usize::try_from(ad).map_err(|_| ReportingError::MalformedEncoding)?;
+        let slot = self.sk_p.get(moderator_id).ok_or(ReportingError::UnknownModerator(ad))?;
+        let (mac_key_i, mod_pk_i) = slot.as_ref().ok_or(ReportingError::RevokedModerator(ad))?;
+
+        // Only commitments that get a tag take up room in the replay cache
+        let ctx = self.replay.admit(ctx, c2)?;
+        let sigma_pt = Secret::new(mac_sign(&PLATFORM_TAG, mac_key_i, &[c2, &ctx.to_bytes()].concat()));
+        let sigma = rsa_oaep_enc(mod_pk_i, &sigma_pt);
+
+        Ok((sigma, ProcessState { ctx, moderator_id: ad }))
+    }
+
+}
 
-        (sigma, (ctx.to_vec(), ad))
+impl Default for Platform {
+    fn default() -> Platform {
+        Platform::new()
     }
+}
+
+fn sign_revocations<R: CryptoRng + RngCore>(k_p: &Scalar, version: u32, revoked: Vec<u32>, rng: &mut R) -> RevocationList {
+    let message = registration::revocation_message(version, &revoked);
+    let signature = nizk::schnorr_prove(&REVOCATION, k_p, &RistrettoPoint::mul_base(k_p), &message, rng);
 
+    RevocationList { version, revoked, signature }
 }
 
 
 // Client Properties
 pub struct Client {
-    pub msg_key: Key<Aes256Gcm> // Symmetric key used to encrypt messages between sender and
+    msg_key: Secret<Key<Aes256Gcm>>, // Symmetric key used to encrypt messages between sender and
+    revocations: Revocations // Newest revocation list verified by this client
 }
 
 // Client Implementation
 impl Client {
     pub fn new() -> Client {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
         Client {
-            msg_key: Aes256Gcm::generate_key(aes_gcm::aead::OsRng)
+            msg_key: Secret::new(Aes256Gcm::generate_key(rng)),
+            revocations: Revocations::default()
         }
     }
 
-    pub fn ccae_enc(msg_key: &Key<Aes256Gcm>, message: &str) -> (Vec<u8>, Vec<u8>) {
-        let k_f: [u8; 32] = mac_keygen(); // franking key or r in H(m, r) for committment
-        
-        let c2 = com_commit(&k_f, message);
-
-        let cipher = Aes256Gcm::new(&msg_key);
-        let nonce = Aes256Gcm::generate_nonce(&mut rand::rngs::OsRng);
+    // Accepts the revocation list of the platform with registration key k_reg
+    pub fn update_revocations(&mut self, list: &RevocationList, k_reg: &Point) -> Result<(), ReportingError> {
+        list.verify(k_reg)?;
 
-        let payload = bincode::serialize(&(message, k_f)).expect("");
-        let c1_obj = cipher.encrypt(&nonce, payload.as_slice()).unwrap();
-        let c1 = bincode::serialize::<(Vec<u8>, Vec<u8>)>(&(c1_obj, nonce.to_vec())).expect("");
+        self.revocations.update(list.version, &list.revoked)
+    }
 
-        (c1, c2)
+    pub fn revocations(&self) -> &Revocations {
+        &self.revocations
     }
 
-    pub fn ccae_dec(msg_key: &Key<Aes256Gcm>, c1: &Vec<u8>, c2: &Vec<u8>) -> (String, [u8; 32]) {
-        let c1_obj = bincode::deserialize::<(Vec<u8>, Vec<u8>)>(&c1).unwrap();
-        let ct = c1_obj.0;
-        let nonce = Nonce::from_slice(&c1_obj.1);
+    pub fn ccae_enc<R: CryptoRng + RngCore>(&self, message: &str, rng: &mut R) -> Result<(Vec<u8>, Vec<u8>), ReportingError> {
+        let k_f = Secret::new(mac_keygen_with_rng(rng)); // franking key or r in H(m, r) for committment
 
-        let cipher = Aes256Gcm::new(&msg_key);
-        let payload_bytes = cipher.decrypt(&nonce, ct.as_ref()).unwrap();
-        let payload = bincode::deserialize::<(&str, [u8; 32])>(&payload_bytes).unwrap();
+        let c2 = com_commit(&COMMITMENT, &k_f[..], message);
 
-        let (message, k_f) = payload;
+        let mut payload = FieldWriter::new();
+        payload.bytes(message.as_bytes());
+        payload.bytes(&k_f[..]);
+        let payload = Secret::new(payload.finish()?);
+        let c1 = sym_enc(&self.msg_key, &payload, rng);
+
+        Ok((c1, c2))
+    }
+
+    pub fn ccae_dec(&self, c1: &[u8], c2: &[u8]) -> Result<(String, Secret<[u8; 32]>), ReportingError> {
+        let payload_bytes = Secret::new(sym_dec(&self.msg_key, c1)?);
+        let mut payload = FieldReader::new(&payload_bytes);
+        let message = payload.string()?;
+        let k_f = Secret::new(payload.array::<32>()?);
+        payload.finish()?;
 
         // Verify committment
-        assert!(com_open(&c2, message, &k_f));
+        if !com_open(&COMMITMENT, c2, &message, &k_f[..]) {
+            return Err(ReportingError::BadCommitment);
+        }
+
+        Ok((message, k_f))
+    }
+
+    pub fn send<R: CryptoRng + RngCore>(&self, message: &str, moderator_id: u32, rng: &mut R) -> Result<SentMessage, ReportingError> {
+        self.revocations.check(moderator_id)?;
+
+        let (c1, c2) = self.ccae_enc(message, rng)?;
 
-        (message.to_string(), k_f)
+        Ok(SentMessage { c1, c2, moderator_id })
     }
 
-    pub fn send(msg_key: &Key<Aes256Gcm>, message: &str, moderator_id: u32) -> (Vec<u8>, Vec<u8>, u32) {
-        let (c1, c2) = Self::ccae_enc(msg_key, message);       
+    pub fn read(&self, pks: &[PKey<Public>], c1: &[u8], c2: &[u8], sigma: &[u8], st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        let ProcessState { ctx, moderator_id: ad } = st;
+        self.revocations.check(*ad)?;
+
+        let (message, k_f) = self.ccae_dec(c1, c2)?;
+
+        let _mod_pk_i = pks.get(*ad as usize).ok_or(ReportingError::UnknownModerator(*ad))?;
+
+        let rd = ReportDoc { opening: Opening::Message(k_f), c2: c2.to_vec(), ctx: *ctx, sigma: sigma.to_vec() };
 
-        (c1, c2, moderator_id)
+
+        Ok((message, *ad, rd))
     }
-    
-    pub fn read(msg_key: &Key<Aes256Gcm>, _pks: &Vec<PKey<Public>>, c1: &Vec<u8>, c2: &Vec<u8>, sigma: &Vec<u8>, st: &(Vec<u8>, u32)) -> (String, u32, ([u8; 32], Vec<u8>, Vec<u8>, Vec<u8>)) {
-        let (ctx, ad) = st;
 
-        let (message, k_f) = Self::ccae_dec(msg_key, c1, c2);
+    pub fn report_gen(_msg: &str, rd: &ReportDoc) -> Report {
+        let report = rd;
 
-        let report: ([u8; 32], Vec<u8>, Vec<u8>, Vec<u8>) = (k_f, c2.clone(), ctx.clone(), sigma.clone());
+        report.clone()
+    }
 
+    // Report revealing only the segments reveal of msg (lib_merkle.rs)
+    pub fn report_gen_partial(msg: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        Ok(Report { opening: rd.opening.disclose(&COMMITMENT, msg, reveal)?, ..rd.clone() })
+    }
+}
 
-        (message, *ad, report)
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
     }
 }
 
 
-// Basic scheme testing decomposition for benchmarking
-// still have num_moderators as a variable despite 
-// this having minimal effect on running time
-// as clients choose 1 moderator and server
-// encrypts under 1 moderator's pk
+// RSA basic scheme: the platform encrypts its tag to the chosen moderator under RSA-OAEP
+pub struct RsaBasic;
 
-// SetupPlatform(1^lambda)
-pub fn test_basic_setup_platform() -> Platform {
-    let platform = Platform::new();
+impl ReportingScheme for RsaBasic {
+    const NAME: &'static str = "RSA Basic Scheme";
 
-    platform
-}
+    type Platform = Platform;
+    type Moderator = Moderator;
+    type Client = Client;
+    type PublicKey = PKey<Public>;
+    type Ciphertext = SentMessage;
+    type Sigma = Vec<u8>;
+    type ProcessState = ProcessState;
+    type ReportDoc = ReportDoc;
+    type Report = Report;
 
-// SetupMod(pk_reg, 1^lambda)
-pub fn test_basic_setup_mod(platform: &mut Platform, num_moderators: usize) -> (Vec<Moderator>, Vec<PKey<Public>>) {
-    let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
-    let mut pks: Vec<PKey<Public>> = Vec::with_capacity(num_moderators);
+    fn setup_platform_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Platform {
+        Platform::new_with_rng(rng)
+    }
+
+    fn setup_mod_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, num_moderators: usize, rng: &mut R) -> (Vec<Moderator>, Vec<PKey<Public>>) {
+        let mut moderators: Vec<Moderator> = Vec::with_capacity(num_moderators);
+        let mut pks: Vec<PKey<Public>> = Vec::with_capacity(num_moderators);
+
+        for _i in 0..num_moderators {
+            let pending = PendingModerator::new(&platform.k_reg);
+            let response = platform.register_with_rng(&pending.request(), rng).expect("registration of an honest moderator");
+            let moderator = pending.finish(response).expect("certificate of an honest platform");
+            pks.push(moderator.public_key());
+            moderators.push(moderator);
+        }
 
-    for _i in 0..num_moderators {
-        let mut moderator = Moderator::new(&platform.k_reg);
-        platform.sk_p.push((moderator.sk_p.clone(), moderator.get_public_key().expect("")));
-        pks.push(moderator.get_public_key().expect(""));
-        moderators.push(moderator);
+        (moderators, pks)
     }
 
+    fn init_client_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Client {
+        Client::new_with_rng(rng)
+    }
 
-    (moderators, pks)
-}
+    fn send_with_rng<R: CryptoRng + RngCore>(client: &Client, message: &str, moderator_id: u32, _pks: &[PKey<Public>], rng: &mut R) -> Result<SentMessage, ReportingError> {
+        client.send(message, moderator_id, rng)
+    }
 
-// Setup Clients
-pub fn test_basic_init_clients(num_clients: usize) -> Vec<Client> {
-    let mut clients: Vec<Client> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let client = Client::new();
-        clients.push(client);
+    fn process_with_rng<R: CryptoRng + RngCore>(platform: &mut Platform, ct: &SentMessage, ctx: &ReportContext, rng: &mut R) -> Result<(Vec<u8>, ProcessState), ReportingError> {
+        platform.process(&ct.c1, &ct.c2, ct.moderator_id, ctx, rng)
     }
 
-    clients
-}
-// Setup Messages
-pub fn test_basic_init_messages(num_clients: usize, msg_size: usize) -> Vec<String> {
-    // Prepare messages
-    let mut ms: Vec<String> = Vec::with_capacity(num_clients);
-    for _i in 0..num_clients {
-        let m = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        ms.push(m);
-    }
-    ms
-}
+    fn read(client: &Client, pks: &[PKey<Public>], ct: &SentMessage, sigma: &Vec<u8>, st: &ProcessState) -> Result<(String, u32, ReportDoc), ReportingError> {
+        client.read(pks, &ct.c1, &ct.c2, sigma, st)
+    }
 
-// send(k, m, pk_i)
-pub fn test_basic_send(num_clients: usize, num_moderators: usize, clients: &Vec<Client>, ms: &Vec<String>, print: bool) -> Vec<(Vec<u8>, Vec<u8>, u32)> {
-    let mut c1c2ad: Vec<(Vec<u8>, Vec<u8>, u32)> = Vec::with_capacity(num_clients);
-    // send message i to client i to be moderated by random mod
-    let mut rng = thread_rng();
-    for i in 0..num_clients {
-        let mod_i = rng.gen_range(0..num_moderators);
-        let (c1, c2, ad) = Client::send(&clients[i].msg_key, &ms[i], mod_i.try_into().unwrap());
-        
-        if print {
-            println!("Sent message: {}", &ms[i]);
-        }
-        c1c2ad.push((c1, c2, ad));
+    fn report_gen(message: &str, rd: &ReportDoc) -> Result<Report, ReportingError> {
+        Ok(Client::report_gen(message, rd))
     }
 
-    c1c2ad
-}
+    fn report_gen_partial(message: &str, rd: &ReportDoc, reveal: &[u32]) -> Result<Report, ReportingError> {
+        Client::report_gen_partial(message, rd, reveal)
+    }
 
-// process(k_p, ks, c1, c2, ad, ctx)
-pub fn test_basic_process(num_clients: usize, msg_size: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, u32)>, platform: &Platform, print: bool) -> Vec<(Vec<u8>, (Vec<u8>, u32))> {
-    let mut sigma_st: Vec<(Vec<u8>, (Vec<u8>, u32))> = Vec::with_capacity(num_clients);
-    // Platform processes message
-    for i in 0..num_clients {
-        let (c1, c2, ad) = &c1c2ad[i];
-        let ctx = Alphanumeric.sample_string(&mut rand::thread_rng(), msg_size);
-        if print {
-            println!("Adding context: {}", ctx);
-        }
-        let (sigma, st) = Platform::process(&platform.k_p, &platform.sk_p, &c1, &c2, *ad, &(ctx.as_bytes().to_vec()));
+    fn moderate(moderator: &Moderator, message: &str, report: &Report, policy: &ExpiryPolicy) -> Result<Verdict, ReportingError> {
+        moderator.moderate(message, report, policy)
+    }
 
-        sigma_st.push((sigma, st));
+    // (1) Commitment randomness (32 bytes)
+    // (2) Commitment to the Message
+    // (3) Moderator id
+    fn send_cost(ct: &SentMessage) -> usize {
+        32 + mem::size_of_val(&*ct.c2) + mem::size_of_val(&ct.moderator_id)
     }
 
-    sigma_st
-}
+    // (1) Moderator id
+    // (2) opening of the commitment
+    // (3) commitment
+    // (4) sigma
+    // (5) message id
+    // (6) sequence number
+    fn receive_cost(_sigma: &Vec<u8>, st: &ProcessState, rd: &ReportDoc) -> usize {
+        let ReportDoc { opening, c2, sigma, .. } = rd;
 
-// read(k, pks, c1, c2, sigma, st)
-pub fn test_basic_read(num_clients: usize, c1c2ad: &Vec<(Vec<u8>, Vec<u8>, u32)>, sigma_st: &Vec<(Vec<u8>, (Vec<u8>, u32))>, clients: &Vec<Client>, pks: &Vec<PKey<Public>>, print: bool) -> Vec<(String, u32, ([u8; 32], Vec<u8>, Vec<u8>, Vec<u8>))> {
-    // Receive messages
-    let mut reports: Vec<(String, u32, ([u8; 32], Vec<u8>, Vec<u8>, Vec<u8>))> = Vec::with_capacity(num_clients);
-    // Receive message 0 from client 0 to be moderated by randomly selected moderator mod_i
-    for i in 0..num_clients {
-        let (c1, c2, _ad) = &c1c2ad[i];
-        let (sigma, st) = &sigma_st[i];
-        let (message, ad, report) = Client::read(&clients[i].msg_key, &pks, &c1, &c2, &sigma, &st);
-
-        if print {
-            println!("Received message: {}", message);
-        }
-        reports.push((message, ad, report));
+        mem::size_of_val(&st.moderator_id) + opening.cost() + mem::size_of_val(&**c2) + mem::size_of_val(&**sigma) + mem::size_of_val(&st.ctx.message_id) + mem::size_of_val(&st.ctx.sequence)
     }
 
-    reports
+    // (1) Moderator id (4 bytes)
+    // (2) opening of the commitment
+    // (3) commitment
+    // (4) sigma
+    // (5) message id
+    // (6) sequence number
+    fn storage_cost(rd: &ReportDoc) -> usize {
+        let ReportDoc { opening, c2, sigma, ctx } = rd;
+
+        4 + opening.cost() + mem::size_of_val(&**c2) + mem::size_of_val(&**sigma) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
+
+    // (1) opening of the commitment (k_f, 32 bytes, or the disclosed segments)
+    // (2) commitment
+    // (3) Moderator id (4 bytes)
+    // (4) sigma
+    // (5) message id
+    // (6) sequence number
+    fn moderation_cost(report: &Report) -> usize {
+        let Report { opening, c2, sigma, ctx } = report;
+
+        opening.cost() + mem::size_of_val(&**c2) + 4 + mem::size_of_val(&**sigma) + mem::size_of_val(&ctx.message_id) + mem::size_of_val(&ctx.sequence)
+    }
 }
 
-// moderate(sk_mod, sk_p, m, report)
-pub fn test_basic_moderate(num_clients: usize, reports: &Vec<(String, u32, ([u8; 32], Vec<u8>, Vec<u8>, Vec<u8>))>, moderators: &Vec<Moderator>, print: bool) {
-    // Moderate messages
-    for i in 0..num_clients {
-        let (message, ad, report) = &reports[i];
-        let ad = usize::try_from(*ad).unwrap();
-        let ctx = Moderator::moderate(&moderators[ad].keypair, &moderators[ad].sk_p, &message, &report);
-        if print {
-            println!("Moderated message successfully with context: {:?}", ctx);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::lib_scheme::Fixture;
+    use crate::lib_wire::check_encoding;
+
+    #[test]
+    fn registration_checks_proof_of_possession() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut platform = Platform::new_with_rng(&mut rng);
+        let pending = PendingModerator::new(&platform.k_reg);
+        let other = PendingModerator::new(&platform.k_reg);
+
+        // A proof for another key
+        let request = RegistrationRequest { pop: other.request().pop, ..pending.request() };
+        assert_eq!(platform.register_with_rng(&request, &mut rng).err(), Some(ReportingError::BadProof));
+    }
+
+    #[test]
+    fn tampered_tag_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<RsaBasic>::new(&mut rng);
+
+        // The tag is bound to the context
+        let mut report = f.report.clone();
+        report.ctx.conversation += 1;
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
+
+        // A ciphertext that does not decrypt
+        let mut report = f.report.clone();
+        rng.fill_bytes(&mut report.sigma);
+        assert_eq!(f.moderate(&report), Verdict::Reject(ReportingError::BadPlatformTag));
     }
 
+    #[test]
+    fn wire_encoding_round_trips() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let f = Fixture::<RsaBasic>::new(&mut rng);
+
+        check_encoding(&f.ct);
+        check_encoding(&(f.sigma.clone(), f.st.clone()));
+        check_encoding(&f.rd); // Same type as the report
+        check_encoding(&f.report);
+    }
+
+    #[test]
+    fn replayed_message_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut f = Fixture::<RsaBasic>::new(&mut rng);
+
+        let replay = RsaBasic::process_with_rng(&mut f.platform, &f.ct, &ReportContext::new(1, 2, 3), &mut rng);
+        assert_eq!(replay.err(), Some(ReportingError::DuplicateMessage(3)));
+        assert_eq!(f.read(&f.sigma, &f.st).unwrap().0, f.message);
+
+        // The same text sent again is a new message
+        let ct = RsaBasic::send_with_rng(&f.client, &f.message, 0, &f.pks, &mut rng).unwrap();
+        let (_sigma, st) = RsaBasic::process_with_rng(&mut f.platform, &ct, &ReportContext::new(1, 2, 3), &mut rng).unwrap();
+        assert_eq!(st.ctx.message_id, f.st.ctx.message_id + 1);
+    }
 }